@@ -0,0 +1,65 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use async_trait::async_trait;
+
+use gcloud::{auth, pubsub};
+
+use protocol::DeadLetter;
+
+use toolkit::queue::MemoryQueue;
+
+use crate::interfaces::DeadLetterQueueWriter;
+
+pub struct PubSubDeadLetterWriter {
+    topic: pubsub::Topic,
+}
+
+impl PubSubDeadLetterWriter {
+    pub async fn new(
+        project_id: String,
+        authenticator: auth::AuthProvider,
+        topic_id: String,
+    ) -> Result<Self> {
+        let client = pubsub::Client::new(&project_id, authenticator).await?;
+        let topic = client.topic(&topic_id).await?;
+        Ok(Self { topic })
+    }
+
+    pub async fn from_credentials<P: AsRef<Path>>(
+        project_id: String,
+        credentials_file_path: P,
+        topic: String,
+    ) -> Result<Self> {
+        let authenticator = auth::AuthProvider::from_json_file(credentials_file_path)?;
+        Self::new(project_id, authenticator, topic).await
+    }
+}
+
+#[async_trait]
+impl DeadLetterQueueWriter for PubSubDeadLetterWriter {
+    async fn push_dead_letter(&self, dead_letter: DeadLetter) -> Result<()> {
+        self.topic.publish(dead_letter).await?;
+        Ok(())
+    }
+}
+
+pub struct InMemoryDeadLetterQueueWriter {
+    queue: Arc<MemoryQueue>,
+}
+
+impl InMemoryDeadLetterQueueWriter {
+    pub fn new(queue: Arc<MemoryQueue>) -> Self {
+        Self { queue }
+    }
+}
+
+#[async_trait]
+impl DeadLetterQueueWriter for InMemoryDeadLetterQueueWriter {
+    async fn push_dead_letter(&self, dead_letter: DeadLetter) -> Result<()> {
+        self.queue.publish(dead_letter)?;
+        Ok(())
+    }
+}
@@ -1,3 +1,7 @@
+mod dead_letter_writer;
+mod record_writer;
 mod trigger_reader;
 
+pub use dead_letter_writer::{InMemoryDeadLetterQueueWriter, PubSubDeadLetterWriter};
+pub use record_writer::{InMemoryActionRecordQueueWriter, PubSubActionRecordWriter};
 pub use trigger_reader::{InMemoryActionManifestQueueReader, PubsubActionManifestQueueReader};
@@ -0,0 +1,65 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use async_trait::async_trait;
+
+use gcloud::{auth, pubsub};
+
+use protocol::ActionRecord;
+
+use toolkit::queue::MemoryQueue;
+
+use crate::interfaces::ActionRecordQueueWriter;
+
+pub struct PubSubActionRecordWriter {
+    topic: pubsub::Topic,
+}
+
+impl PubSubActionRecordWriter {
+    pub async fn new(
+        project_id: String,
+        authenticator: auth::AuthProvider,
+        topic_id: String,
+    ) -> Result<Self> {
+        let client = pubsub::Client::new(&project_id, authenticator).await?;
+        let topic = client.topic(&topic_id).await?;
+        Ok(Self { topic })
+    }
+
+    pub async fn from_credentials<P: AsRef<Path>>(
+        project_id: String,
+        credentials_file_path: P,
+        topic: String,
+    ) -> Result<Self> {
+        let authenticator = auth::AuthProvider::from_json_file(credentials_file_path)?;
+        Self::new(project_id, authenticator, topic).await
+    }
+}
+
+#[async_trait]
+impl ActionRecordQueueWriter for PubSubActionRecordWriter {
+    async fn push_action_record(&self, record: ActionRecord) -> Result<()> {
+        self.topic.publish(record).await?;
+        Ok(())
+    }
+}
+
+pub struct InMemoryActionRecordQueueWriter {
+    queue: Arc<MemoryQueue>,
+}
+
+impl InMemoryActionRecordQueueWriter {
+    pub fn new(queue: Arc<MemoryQueue>) -> Self {
+        Self { queue }
+    }
+}
+
+#[async_trait]
+impl ActionRecordQueueWriter for InMemoryActionRecordQueueWriter {
+    async fn push_action_record(&self, record: ActionRecord) -> Result<()> {
+        self.queue.publish(record)?;
+        Ok(())
+    }
+}
@@ -2,7 +2,7 @@ use anyhow::Result;
 
 use async_trait::async_trait;
 
-use protocol::ActionManifest;
+use protocol::{ActionManifest, ActionRecord, DeadLetter};
 
 use toolkit::message::Message;
 
@@ -11,3 +11,15 @@ pub trait ActionManifestQueueReader {
     async fn pull_action_manifest(&self)
         -> Result<Option<Box<dyn Message<ActionManifest> + Send>>>;
 }
+
+/// Trait describing an object capable of pushing the records of executed actions to a queue.
+#[async_trait]
+pub trait ActionRecordQueueWriter {
+    async fn push_action_record(&self, record: ActionRecord) -> Result<()>;
+}
+
+/// Trait describing an object capable of pushing quarantined actions to a dead-letter queue.
+#[async_trait]
+pub trait DeadLetterQueueWriter {
+    async fn push_dead_letter(&self, dead_letter: DeadLetter) -> Result<()>;
+}
@@ -3,10 +3,12 @@ mod interfaces;
 mod manager;
 mod system;
 
-pub use interfaces::ActionManifestQueueReader;
+pub use interfaces::{ActionManifestQueueReader, ActionRecordQueueWriter, DeadLetterQueueWriter};
 pub use system::{ExecutorSystem, ExecutorSystemConfig};
 
 type BoxedQueueReader = Box<dyn ActionManifestQueueReader + Send>;
+type BoxedRecordWriter = Box<dyn ActionRecordQueueWriter + Send + Sync>;
+type BoxedDeadLetterWriter = Box<dyn DeadLetterQueueWriter + Send + Sync>;
 
 #[cfg(test)]
 mod tests;
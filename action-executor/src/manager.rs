@@ -1,21 +1,86 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::sync::{mpsc, Arc};
-use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 
-use plugin_core::ActionPlugin;
+use plugin_core::{AsyncActionPlugin, Error as PluginError};
 use plugin_host::PluginHost;
 
-use crate::BoxedQueueReader;
+use protocol::{ActionFailure, ActionManifest, ActionRecord, ActionResult, DeadLetter, RuleID};
+
+use tokio::sync::mpsc::{self as async_mpsc, UnboundedReceiver, UnboundedSender};
+
+use toolkit::message::Message;
+
+use crate::{BoxedDeadLetterWriter, BoxedQueueReader, BoxedRecordWriter};
+
+/// Number of times an action is attempted before being quarantined.
+///
+/// The message of an action is only acked once the action is done, so the ack deadline of the
+/// manifest queue must cover the retries of an action, or they are redelivered meanwhile.
+const MAX_ATTEMPTS: u32 = 5;
+
+/// Delay before the first retry of an action, doubled on every subsequent attempt.
+const BASE_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Number of actions pulled and not acked yet, past which the executor stops pulling.
+pub(crate) const MAX_IN_FLIGHT: usize = 64;
+
+/// How long stopping waits for the actions being executed to finish.
+const STOP_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// An action, along with the queue message it was pulled from. The message is acked once the
+/// action is executed or quarantined.
+struct Action {
+    manifest: ActionManifest,
+    message: Box<dyn Message<ActionManifest> + Send>,
+}
+
+impl Action {
+    async fn ack(mut self) {
+        if let Err(e) = self.message.ack().await {
+            log::error!(
+                "[{}] failed to ack action manifest: {}",
+                self.manifest.envelope,
+                e
+            );
+        }
+    }
+}
+
+/// An action waiting to be retried.
+struct PendingRetry {
+    due: Instant,
+    action: Action,
+}
+
+/// Outcome of an action executed in the background.
+struct ActionOutcome {
+    action: Action,
+    result: Result<ActionResult, PluginError>,
+}
 
 pub struct ExecutorManager {
     manifest_reader: BoxedQueueReader,
+    record_writer: Option<BoxedRecordWriter>,
+    dead_letter_writer: Option<BoxedDeadLetterWriter>,
 
     stop_rx: mpsc::Receiver<()>,
 
-    executors: HashMap<String, Arc<Box<dyn ActionPlugin>>>,
+    executors: HashMap<String, Arc<Box<dyn AsyncActionPlugin>>>,
+
+    // Generation of the plugin host the executors were fetched at.
+    plugin_generation: u64,
+
+    pending_retries: Vec<PendingRetry>,
+
+    // Actions waiting for the action being executed for their rule, by rule. Actions of a rule
+    // are executed one at a time, in order, while actions of different rules run concurrently.
+    running: HashMap<RuleID, VecDeque<Action>>,
+    outcome_tx: UnboundedSender<ActionOutcome>,
+    outcome_rx: UnboundedReceiver<ActionOutcome>,
 
     plugin_host: Arc<PluginHost>,
 }
@@ -24,12 +89,23 @@ impl ExecutorManager {
     pub fn new(
         stop_rx: mpsc::Receiver<()>,
         manifest_reader: BoxedQueueReader,
+        record_writer: Option<BoxedRecordWriter>,
+        dead_letter_writer: Option<BoxedDeadLetterWriter>,
         plugin_host: Arc<PluginHost>,
     ) -> Result<Self> {
+        let (outcome_tx, outcome_rx) = async_mpsc::unbounded_channel();
+
         let mut manager = ExecutorManager {
             manifest_reader,
+            record_writer,
+            dead_letter_writer,
             stop_rx,
             executors: HashMap::new(),
+            plugin_generation: 0,
+            pending_retries: Vec::new(),
+            running: HashMap::new(),
+            outcome_tx,
+            outcome_rx,
             plugin_host,
         };
 
@@ -39,6 +115,7 @@ impl ExecutorManager {
     }
 
     fn refresh_plugins(&mut self) -> Result<()> {
+        self.plugin_generation = self.plugin_host.generation();
         self.executors.clear();
         for action_plugin in self.plugin_host.get_action_plugins() {
             let action_name = String::from(action_plugin.get_type());
@@ -48,25 +125,271 @@ impl ExecutorManager {
         Ok(())
     }
 
-    async fn pull_cycle(&mut self) -> Result<()> {
-        if let Some(mut msg) = self.manifest_reader.pull_action_manifest().await? {
-            // Deserialize message.
-            let action_manifest = msg.data()?;
+    /// Retries the failed action later if its error is retryable and it has attempts left.
+    /// Otherwise, the action is quarantined.
+    async fn handle_failure(&mut self, mut action: Action, error: PluginError) {
+        let attempt = action.manifest.envelope.attempt;
+        if !error.is_retryable() || attempt >= MAX_ATTEMPTS {
+            self.quarantine(action, error).await;
+            return;
+        }
+
+        let delay = error.retry_after.unwrap_or_else(|| {
+            BASE_RETRY_DELAY
+                .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+                .min(MAX_RETRY_DELAY)
+        });
+
+        log::warn!(
+            "[{}] action ({}) failed, retrying in {:?}: {}",
+            action.manifest.envelope,
+            &action.manifest.action_type,
+            delay,
+            error
+        );
+        self.record_failure(&action.manifest, &error, true).await;
+
+        action.manifest.envelope.attempt += 1;
+        self.pending_retries.push(PendingRetry {
+            due: Instant::now() + delay,
+            action,
+        });
+    }
+
+    /// Gives up on an action: it is published to the dead-letter queue, then acked.
+    ///
+    /// When the action can't be published, it isn't acked either, so it is redelivered instead
+    /// of being lost.
+    async fn quarantine(&self, action: Action, error: PluginError) {
+        let manifest = &action.manifest;
+        log::error!(
+            "[{}] action ({}) quarantined after {} attempt(s): {} (data: {})",
+            manifest.envelope,
+            &manifest.action_type,
+            manifest.envelope.attempt,
+            error,
+            &manifest.data
+        );
+        self.record_failure(manifest, &error, false).await;
+
+        if let Some(dead_letter_writer) = &self.dead_letter_writer {
+            if let Err(e) = dead_letter_writer
+                .push_dead_letter(DeadLetter::new(manifest.clone(), error.to_string()))
+                .await
+            {
+                log::error!(
+                    "[{}] failed to publish dead letter, leaving the action unacked: {:?}",
+                    manifest.envelope,
+                    e
+                );
+                return;
+            }
+        }
+
+        action.ack().await;
+    }
+
+    /// Executes an action in the background, once the actions of its rule executed before it
+    /// are done.
+    async fn execute(&mut self, action: Action) {
+        if let Some(waiting) = self.running.get_mut(&action.manifest.rule) {
+            waiting.push_back(action);
+            return;
+        }
+
+        let rule = action.manifest.rule.clone();
+        match self.spawn(action) {
+            Ok(()) => {
+                self.running.insert(rule, VecDeque::new());
+            }
+            Err(action) => self.quarantine_unknown(*action).await,
+        }
+    }
+
+    /// Starts executing an action, or gives it back if its type is unknown.
+    ///
+    /// Calls are bounded by the timeouts of the plugin host, so a hung plugin only holds up
+    /// the actions of its rule.
+    fn spawn(&self, action: Action) -> std::result::Result<(), Box<Action>> {
+        let executor = match self.executors.get(&action.manifest.action_type) {
+            Some(executor) => executor.clone(),
+            None => return Err(Box::new(action)),
+        };
+
+        log::info!(
+            "[{}] executing action ({})",
+            action.manifest.envelope,
+            &action.manifest.action_type
+        );
+
+        let plugin_host = self.plugin_host.clone();
+        let outcome_tx = self.outcome_tx.clone();
+        tokio::spawn(async move {
+            let result = plugin_host
+                .watch(
+                    &action.manifest.action_type,
+                    &action.manifest.rule,
+                    executor.execute_with_result(action.manifest.clone()),
+                )
+                .await;
+
+            // The manager only goes away when stopping.
+            let _ = outcome_tx.send(ActionOutcome { action, result });
+        });
+
+        Ok(())
+    }
+
+    /// Quarantines an action no plugin can execute.
+    async fn quarantine_unknown(&self, action: Action) {
+        let error = PluginError::permanent(format!(
+            "unknown action type: {:?}",
+            &action.manifest.action_type
+        ));
+        self.quarantine(action, error).await;
+    }
 
-            log::debug!("got manifest: {:?}", action_manifest);
+    /// Logs the result of an executed action, and publishes its record.
+    async fn record(&self, manifest: &ActionManifest, result: ActionResult) {
+        match &result.summary {
+            Some(summary) => log::info!("[{}] action executed: {}", manifest.envelope, summary),
+            None => log::info!("[{}] action executed", manifest.envelope),
+        }
+        if !result.data.is_null() {
+            log::debug!("[{}] action result: {}", manifest.envelope, &result.data);
+        }
 
-            if let Some(ex) = self.executors.get(&action_manifest.action_type) {
-                ex.execute_action(action_manifest)?;
-            } else {
-                log::warn!("unknown action type: {:?}", &action_manifest.action_type);
+        self.publish_record(manifest, ActionRecord::new(manifest, result))
+            .await;
+    }
+
+    /// Publishes the record of a failed attempt of an action.
+    async fn record_failure(&self, manifest: &ActionManifest, error: &PluginError, retrying: bool) {
+        let failure = ActionFailure {
+            error: error.to_string(),
+            attempt: manifest.envelope.attempt,
+            retrying,
+        };
+        self.publish_record(manifest, ActionRecord::failed(manifest, failure))
+            .await;
+    }
+
+    async fn publish_record(&self, manifest: &ActionManifest, record: ActionRecord) {
+        if let Some(record_writer) = &self.record_writer {
+            // Records are informational: the action is acked or retried regardless.
+            if let Err(e) = record_writer.push_action_record(record).await {
+                log::error!(
+                    "[{}] failed to publish action record: {:?}",
+                    manifest.envelope,
+                    e
+                );
             }
+        }
+    }
 
-            msg.ack().await?;
+    /// Handles the actions done executing, and starts the actions waiting for them.
+    async fn collect_outcomes(&mut self) {
+        while let Ok(outcome) = self.outcome_rx.try_recv() {
+            self.handle_outcome(outcome).await;
+        }
+    }
+
+    /// Acks an action once it is executed, or handles its failure. Then starts the next action
+    /// of its rule.
+    async fn handle_outcome(&mut self, outcome: ActionOutcome) {
+        let rule = outcome.action.manifest.rule.clone();
+        match outcome.result {
+            Ok(result) => {
+                self.record(&outcome.action.manifest, result).await;
+                outcome.action.ack().await;
+            }
+            Err(e) => self.handle_failure(outcome.action, e).await,
+        }
+
+        loop {
+            let next = self.running.get_mut(&rule).and_then(VecDeque::pop_front);
+            match next {
+                Some(action) => match self.spawn(action) {
+                    Ok(()) => break,
+                    Err(action) => self.quarantine_unknown(*action).await,
+                },
+                None => {
+                    self.running.remove(&rule);
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Returns the number of actions pulled and not acked yet.
+    fn in_flight(&self) -> usize {
+        let running: usize = self.running.values().map(|waiting| waiting.len() + 1).sum();
+        running + self.pending_retries.len()
+    }
+
+    async fn retry_cycle(&mut self) {
+        let now = Instant::now();
+        let (due, pending): (Vec<PendingRetry>, Vec<PendingRetry>) = self
+            .pending_retries
+            .drain(..)
+            .partition(|retry| retry.due <= now);
+        self.pending_retries = pending;
+
+        for retry in due.into_iter() {
+            self.execute(retry.action).await;
+        }
+    }
+
+    /// Picks up the plugins loaded or reloaded by the host since the last refresh.
+    fn refresh_plugins_if_changed(&mut self) -> Result<()> {
+        if self.plugin_host.generation() != self.plugin_generation {
+            log::info!("plugins changed, refreshing executors");
+            self.refresh_plugins()?;
+        }
+        Ok(())
+    }
+
+    async fn pull_cycle(&mut self) -> Result<()> {
+        self.refresh_plugins_if_changed()?;
+        self.collect_outcomes().await;
+        self.retry_cycle().await;
+
+        // Actions are pulled until the queue is empty, or too many actions are in flight.
+        while self.in_flight() < MAX_IN_FLIGHT {
+            let message = match self.manifest_reader.pull_action_manifest().await? {
+                Some(message) => message,
+                None => break,
+            };
+
+            // Deserialize message.
+            let manifest = message.data()?;
+
+            log::debug!("got manifest: {:?}", manifest);
+
+            self.execute(Action { manifest, message }).await;
         }
 
         Ok(())
     }
 
+    /// Waits for the actions being executed to finish, for up to [`STOP_GRACE_PERIOD`].
+    ///
+    /// Actions waiting for their rule aren't started, and are left unacked along with the
+    /// actions still running after the grace period, to be redelivered.
+    async fn drain(&mut self) {
+        for waiting in self.running.values_mut() {
+            waiting.clear();
+        }
+
+        let deadline = tokio::time::Instant::now() + STOP_GRACE_PERIOD;
+        while !self.running.is_empty() {
+            match tokio::time::timeout_at(deadline, self.outcome_rx.recv()).await {
+                Ok(Some(outcome)) => self.handle_outcome(outcome).await,
+                _ => break,
+            }
+        }
+    }
+
     #[tokio::main]
     pub async fn start(&mut self) {
         log::debug!("executor loop running");
@@ -76,12 +399,35 @@ impl ExecutorManager {
             }
 
             // TODO: Make this configurable
-            thread::sleep(Duration::from_millis(100));
+            tokio::time::sleep(Duration::from_millis(100)).await;
 
             if self.stop_rx.try_recv().is_ok() {
                 log::debug!("executor stopping");
                 break;
             }
         }
+
+        let waiting: usize = self.running.values().map(VecDeque::len).sum();
+        if waiting > 0 {
+            log::warn!(
+                "executor stopping with {} action(s) not started yet, left unacked",
+                waiting
+            );
+        }
+
+        self.drain().await;
+        if !self.running.is_empty() {
+            log::warn!(
+                "executor stopped with {} action(s) still running, left unacked",
+                self.running.len()
+            );
+        }
+
+        if !self.pending_retries.is_empty() {
+            log::warn!(
+                "executor stopped with {} action(s) pending retry, left unacked",
+                self.pending_retries.len()
+            );
+        }
     }
 }
@@ -1,3 +1,4 @@
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::Arc;
 
 use anyhow::{Context, Error, Result};
@@ -7,10 +8,17 @@ use plugin_host::PluginHost;
 use toolkit::{thread::StoppableThread, Stop};
 
 use crate::manager::ExecutorManager;
-use crate::BoxedQueueReader;
+use crate::{BoxedDeadLetterWriter, BoxedQueueReader, BoxedRecordWriter};
 
 pub struct ExecutorSystemConfig {
     pub queue_reader: BoxedQueueReader,
+
+    /// Where the records of executed actions are published. Records are only logged when `None`.
+    pub record_writer: Option<BoxedRecordWriter>,
+
+    /// Where quarantined actions are published. Quarantined actions are only logged when `None`.
+    pub dead_letter_writer: Option<BoxedDeadLetterWriter>,
+
     pub plugin_host: Arc<PluginHost>,
 }
 
@@ -24,8 +32,19 @@ impl ExecutorSystem {
 
         let sys = Self {
             handle: StoppableThread::spawn(move |stop_rx| {
-                match ExecutorManager::new(stop_rx, cfg.queue_reader, cfg.plugin_host) {
-                    Ok(mut e) => e.start(),
+                match ExecutorManager::new(
+                    stop_rx,
+                    cfg.queue_reader,
+                    cfg.record_writer,
+                    cfg.dead_letter_writer,
+                    cfg.plugin_host,
+                ) {
+                    Ok(mut e) => {
+                        // Plugin calls are guarded, so this is a bug of the manager itself.
+                        if panic::catch_unwind(AssertUnwindSafe(|| e.start())).is_err() {
+                            log::error!("executor manager panicked, stopping");
+                        }
+                    }
                     Err(err) => log::error!("failed to start the manager: {:?}", err),
                 }
             }),
@@ -2,10 +2,13 @@ use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time;
 
-use plugin_host::PluginHost;
+use plugin_host::{CallTimeouts, PluginHost, PluginHostConfig};
 
-use protocol::ActionManifest;
+use protocol::{ActionFailure, ActionManifest, Envelope, Payload};
 
+use serde_json::json;
+
+use crate::manager::MAX_IN_FLIGHT;
 use crate::system::{ExecutorSystem, ExecutorSystemConfig};
 
 use super::mock;
@@ -14,6 +17,8 @@ use super::mock;
 fn basic_test() {
     let cfg = ExecutorSystemConfig {
         queue_reader: Box::from(mock::Dummy::default()),
+        record_writer: None,
+        dead_letter_writer: None,
         plugin_host: Arc::new(PluginHost::default()),
     };
 
@@ -29,14 +34,17 @@ fn in_memory_full_loop() {
     for i in 0..10 {
         let mut guard = queue_reader.lock().unwrap();
         (*guard).incoming_queue.push(ActionManifest {
-            data: i.to_string(),
+            data: json!(i).into(),
             action_type: String::from("bing"),
             rule: "1".into(),
+            envelope: Envelope::new(),
         });
     }
 
     let cfg = ExecutorSystemConfig {
         queue_reader: queue_reader.clone(),
+        record_writer: None,
+        dead_letter_writer: None,
         plugin_host: Arc::new(PluginHost::default()),
     };
     let sys = ExecutorSystem::start(cfg);
@@ -52,3 +60,519 @@ fn in_memory_full_loop() {
     assert!(reader_ref.incoming_queue.is_empty());
     assert_eq!(reader_ref.ack_count(), 10)
 }
+
+#[test]
+fn in_memory_async_action() {
+    let queue_reader = Box::from(Arc::new(Mutex::new(mock::InMemoryReader::default())));
+
+    for i in 0..3 {
+        queue_reader
+            .lock()
+            .unwrap()
+            .incoming_queue
+            .push(ActionManifest {
+                data: json!(i).into(),
+                action_type: String::from("record"),
+                rule: "1".into(),
+                envelope: Envelope::new(),
+            });
+    }
+
+    let action = mock::RecordingAction::default();
+    let mut plugin_host = PluginHost::default();
+    plugin_host
+        .add_in_memory_async_action_plugin(Box::new(action.clone()))
+        .unwrap();
+
+    let cfg = ExecutorSystemConfig {
+        queue_reader: queue_reader.clone(),
+        record_writer: None,
+        dead_letter_writer: None,
+        plugin_host: Arc::new(plugin_host),
+    };
+    let sys = ExecutorSystem::start(cfg);
+    thread::sleep(time::Duration::from_millis(500)); // Give the system a chance to boot & consume.
+
+    sys.terminate().unwrap();
+
+    let executed = action.executed.lock().unwrap();
+    assert_eq!(executed.len(), 3);
+    assert!(executed.iter().any(|m| m.data == json!(0).into()));
+    assert_eq!(queue_reader.lock().unwrap().ack_count(), 3);
+}
+
+#[test]
+fn transient_failure_is_retried() {
+    let queue_reader = Box::from(Arc::new(Mutex::new(mock::InMemoryReader::default())));
+    queue_reader
+        .lock()
+        .unwrap()
+        .incoming_queue
+        .push(ActionManifest {
+            data: json!(1).into(),
+            action_type: String::from("flaky"),
+            rule: "1".into(),
+            envelope: Envelope::new(),
+        });
+
+    let action = mock::FlakyAction::new(2);
+    let mut plugin_host = PluginHost::default();
+    plugin_host
+        .add_in_memory_async_action_plugin(Box::new(action.clone()))
+        .unwrap();
+
+    let cfg = ExecutorSystemConfig {
+        queue_reader: queue_reader.clone(),
+        record_writer: None,
+        dead_letter_writer: None,
+        plugin_host: Arc::new(plugin_host),
+    };
+    let sys = ExecutorSystem::start(cfg);
+    thread::sleep(time::Duration::from_millis(500));
+
+    sys.terminate().unwrap();
+
+    assert_eq!(*action.attempts.lock().unwrap(), vec![1, 2, 3]);
+    assert_eq!(queue_reader.lock().unwrap().ack_count(), 1);
+}
+
+#[test]
+fn attempt_zero_is_retried() {
+    // Envelopes from other producers may number attempts from 0.
+    let mut envelope = Envelope::new();
+    envelope.attempt = 0;
+
+    let queue_reader = Box::from(Arc::new(Mutex::new(mock::InMemoryReader::default())));
+    queue_reader
+        .lock()
+        .unwrap()
+        .incoming_queue
+        .push(ActionManifest {
+            data: json!(1).into(),
+            action_type: String::from("flaky"),
+            rule: "1".into(),
+            envelope,
+        });
+
+    let action = mock::FlakyAction::new(1);
+    let mut plugin_host = PluginHost::default();
+    plugin_host
+        .add_in_memory_async_action_plugin(Box::new(action.clone()))
+        .unwrap();
+
+    let cfg = ExecutorSystemConfig {
+        queue_reader: queue_reader.clone(),
+        record_writer: None,
+        dead_letter_writer: None,
+        plugin_host: Arc::new(plugin_host),
+    };
+    let sys = ExecutorSystem::start(cfg);
+    thread::sleep(time::Duration::from_millis(500));
+
+    sys.terminate().unwrap();
+
+    assert_eq!(*action.attempts.lock().unwrap(), vec![0, 1]);
+    assert_eq!(queue_reader.lock().unwrap().ack_count(), 1);
+}
+
+#[test]
+fn permanent_failure_is_quarantined() {
+    let queue_reader = Box::from(Arc::new(Mutex::new(mock::InMemoryReader::default())));
+    queue_reader
+        .lock()
+        .unwrap()
+        .incoming_queue
+        .push(ActionManifest {
+            data: json!(1).into(),
+            action_type: String::from("broken"),
+            rule: "1".into(),
+            envelope: Envelope::new(),
+        });
+
+    let action = mock::BrokenAction::default();
+    let mut plugin_host = PluginHost::default();
+    plugin_host
+        .add_in_memory_async_action_plugin(Box::new(action.clone()))
+        .unwrap();
+
+    let dead_letter_writer = mock::DeadLetterWriter::default();
+    let cfg = ExecutorSystemConfig {
+        queue_reader: queue_reader.clone(),
+        record_writer: None,
+        dead_letter_writer: Some(Box::new(dead_letter_writer.clone())),
+        plugin_host: Arc::new(plugin_host),
+    };
+    let sys = ExecutorSystem::start(cfg);
+    thread::sleep(time::Duration::from_millis(500));
+
+    sys.terminate().unwrap();
+
+    assert_eq!(*action.calls.lock().unwrap(), 1);
+    assert_eq!(queue_reader.lock().unwrap().ack_count(), 1);
+
+    let dead_letters = dead_letter_writer.dead_letters.lock().unwrap();
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].manifest.action_type, "broken");
+    assert!(dead_letters[0].error.contains("bad request"));
+}
+
+#[test]
+fn quarantined_action_is_not_acked_without_dead_letter() {
+    let queue_reader = Box::from(Arc::new(Mutex::new(mock::InMemoryReader::default())));
+    for action_type in ["broken", "bing"] {
+        queue_reader
+            .lock()
+            .unwrap()
+            .incoming_queue
+            .push(ActionManifest {
+                data: json!(1).into(),
+                action_type: String::from(action_type),
+                rule: action_type.into(),
+                envelope: Envelope::new(),
+            });
+    }
+
+    let mut plugin_host = PluginHost::default();
+    plugin_host
+        .add_in_memory_async_action_plugin(Box::new(mock::BrokenAction::default()))
+        .unwrap();
+
+    let cfg = ExecutorSystemConfig {
+        queue_reader: queue_reader.clone(),
+        record_writer: None,
+        dead_letter_writer: Some(Box::new(mock::DeadLetterWriter {
+            unavailable: true,
+            ..Default::default()
+        })),
+        plugin_host: Arc::new(plugin_host),
+    };
+    let sys = ExecutorSystem::start(cfg);
+    thread::sleep(time::Duration::from_millis(500));
+
+    sys.terminate().unwrap();
+
+    // Neither the failed action nor the action of unknown type is lost.
+    assert_eq!(queue_reader.lock().unwrap().ack_count(), 0);
+}
+
+#[test]
+fn action_pending_retry_is_not_acked() {
+    let queue_reader = Box::from(Arc::new(Mutex::new(mock::InMemoryReader::default())));
+    queue_reader
+        .lock()
+        .unwrap()
+        .incoming_queue
+        .push(ActionManifest {
+            data: json!(1).into(),
+            action_type: String::from("flaky"),
+            rule: "1".into(),
+            envelope: Envelope::new(),
+        });
+
+    let action = mock::FlakyAction::new(1).with_retry_after(time::Duration::from_secs(60));
+    let mut plugin_host = PluginHost::default();
+    plugin_host
+        .add_in_memory_async_action_plugin(Box::new(action.clone()))
+        .unwrap();
+
+    let cfg = ExecutorSystemConfig {
+        queue_reader: queue_reader.clone(),
+        record_writer: None,
+        dead_letter_writer: None,
+        plugin_host: Arc::new(plugin_host),
+    };
+    let sys = ExecutorSystem::start(cfg);
+    thread::sleep(time::Duration::from_millis(500));
+
+    sys.terminate().unwrap();
+
+    // The executor stopped before the retry, so the action is left to be redelivered.
+    assert_eq!(*action.attempts.lock().unwrap(), vec![1]);
+    assert_eq!(queue_reader.lock().unwrap().ack_count(), 0);
+}
+
+#[test]
+fn hung_action_times_out() {
+    let queue_reader = Box::from(Arc::new(Mutex::new(mock::InMemoryReader::default())));
+    // Manifests are pulled from the end of the queue.
+    for (action_type, rule) in [("record", "slow"), ("record", "fast"), ("hang", "slow")] {
+        queue_reader
+            .lock()
+            .unwrap()
+            .incoming_queue
+            .push(ActionManifest {
+                data: json!(rule).into(),
+                action_type: String::from(action_type),
+                rule: rule.into(),
+                envelope: Envelope::new(),
+            });
+    }
+
+    let mut plugin_host = PluginHost::initialize(PluginHostConfig {
+        timeouts: CallTimeouts {
+            rules: vec![(String::from("slow"), 100)].into_iter().collect(),
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .unwrap();
+    let action = mock::RecordingAction::default();
+    plugin_host
+        .add_in_memory_async_action_plugin(Box::new(action.clone()))
+        .unwrap();
+    plugin_host
+        .add_in_memory_async_action_plugin(Box::new(mock::HangingAction))
+        .unwrap();
+    let plugin_host = Arc::new(plugin_host);
+
+    let cfg = ExecutorSystemConfig {
+        queue_reader: queue_reader.clone(),
+        record_writer: None,
+        dead_letter_writer: None,
+        plugin_host: plugin_host.clone(),
+    };
+    let sys = ExecutorSystem::start(cfg);
+    thread::sleep(time::Duration::from_millis(600));
+
+    sys.terminate().unwrap();
+
+    // The hung action doesn't hold up the other rules, and the actions of its rule run once
+    // it timed out.
+    let executed = action.executed.lock().unwrap();
+    assert_eq!(executed.len(), 2);
+    assert_eq!(executed[0].rule, "fast");
+    assert_eq!(plugin_host.timed_out_calls().get("hang"), Some(&1));
+
+    // The timed out action is still waiting for its retry, so it isn't acked.
+    assert_eq!(queue_reader.lock().unwrap().ack_count(), 2);
+}
+
+#[test]
+fn panicking_action_is_isolated() {
+    let queue_reader = Box::from(Arc::new(Mutex::new(mock::InMemoryReader::default())));
+    for (action_type, rule) in [("record", "2"), ("record", "1"), ("panic", "1")] {
+        queue_reader
+            .lock()
+            .unwrap()
+            .incoming_queue
+            .push(ActionManifest {
+                data: json!(rule).into(),
+                action_type: String::from(action_type),
+                rule: rule.into(),
+                envelope: Envelope::new(),
+            });
+    }
+
+    let mut plugin_host = PluginHost::default();
+    let action = mock::RecordingAction::default();
+    plugin_host
+        .add_in_memory_async_action_plugin(Box::new(action.clone()))
+        .unwrap();
+    plugin_host
+        .add_in_memory_action_plugin(Box::new(mock::PanickingAction))
+        .unwrap();
+    let plugin_host = Arc::new(plugin_host);
+
+    let cfg = ExecutorSystemConfig {
+        queue_reader: queue_reader.clone(),
+        record_writer: None,
+        dead_letter_writer: None,
+        plugin_host: plugin_host.clone(),
+    };
+    let sys = ExecutorSystem::start(cfg);
+    thread::sleep(time::Duration::from_millis(500));
+
+    sys.terminate().unwrap();
+
+    // The panic is quarantined like a permanent failure, and the loop keeps going.
+    assert_eq!(action.executed.lock().unwrap().len(), 2);
+    assert_eq!(queue_reader.lock().unwrap().ack_count(), 3);
+
+    let unhealthy = plugin_host.unhealthy_plugins();
+    assert_eq!(unhealthy.len(), 1);
+    assert_eq!(unhealthy[0].plugin_type, "panic");
+    assert_eq!(unhealthy[0].panics, 1);
+    assert_eq!(unhealthy[0].last_panic, "bing bong");
+}
+
+#[test]
+fn action_records_are_published() {
+    let queue_reader = Box::from(Arc::new(Mutex::new(mock::InMemoryReader::default())));
+    let manifest = ActionManifest {
+        data: json!({"url": "http://bing.bong"}).into(),
+        action_type: String::from("create"),
+        rule: "1".into(),
+        envelope: Envelope::new(),
+    };
+    queue_reader
+        .lock()
+        .unwrap()
+        .incoming_queue
+        .push(manifest.clone());
+
+    let mut plugin_host = PluginHost::default();
+    plugin_host
+        .add_in_memory_action_plugin(Box::new(mock::CreatingAction))
+        .unwrap();
+
+    let record_writer = mock::RecordingWriter::default();
+    let cfg = ExecutorSystemConfig {
+        queue_reader: queue_reader.clone(),
+        record_writer: Some(Box::new(record_writer.clone())),
+        dead_letter_writer: None,
+        plugin_host: Arc::new(plugin_host),
+    };
+    let sys = ExecutorSystem::start(cfg);
+    thread::sleep(time::Duration::from_millis(500));
+
+    sys.terminate().unwrap();
+
+    let records = record_writer.records.lock().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].rule, "1");
+    assert_eq!(records[0].action_type, "create");
+    assert_eq!(records[0].result.summary.as_deref(), Some("201 Created"));
+    assert_eq!(records[0].result.data, Payload::from(json!({"id": 42})));
+    assert_eq!(records[0].envelope.trigger_id, manifest.envelope.trigger_id);
+}
+
+#[test]
+fn failure_records_are_published() {
+    let queue_reader = Box::from(Arc::new(Mutex::new(mock::InMemoryReader::default())));
+    for (action_type, rule) in [("flaky", "1"), ("broken", "2")] {
+        queue_reader
+            .lock()
+            .unwrap()
+            .incoming_queue
+            .push(ActionManifest {
+                data: Payload::default(),
+                action_type: String::from(action_type),
+                rule: rule.into(),
+                envelope: Envelope::new(),
+            });
+    }
+
+    let mut plugin_host = PluginHost::default();
+    plugin_host
+        .add_in_memory_async_action_plugin(Box::new(mock::FlakyAction::new(1)))
+        .unwrap();
+    plugin_host
+        .add_in_memory_async_action_plugin(Box::new(mock::BrokenAction::default()))
+        .unwrap();
+
+    let record_writer = mock::RecordingWriter::default();
+    let cfg = ExecutorSystemConfig {
+        queue_reader: queue_reader.clone(),
+        record_writer: Some(Box::new(record_writer.clone())),
+        dead_letter_writer: None,
+        plugin_host: Arc::new(plugin_host),
+    };
+    let sys = ExecutorSystem::start(cfg);
+    thread::sleep(time::Duration::from_millis(500));
+
+    sys.terminate().unwrap();
+
+    let records = record_writer.records.lock().unwrap();
+    let failures = |rule: &str| -> Vec<ActionFailure> {
+        records
+            .iter()
+            .filter(|r| r.rule == rule)
+            .filter_map(|r| r.failure.clone())
+            .collect()
+    };
+
+    // Retried attempts are recorded, along with the final outcome.
+    let flaky = failures("1");
+    assert_eq!(flaky.len(), 1);
+    assert_eq!(flaky[0].attempt, 1);
+    assert!(flaky[0].retrying);
+    assert!(records.iter().any(|r| r.rule == "1" && r.failure.is_none()));
+
+    let broken = failures("2");
+    assert_eq!(broken.len(), 1);
+    assert!(!broken[0].retrying);
+    assert!(broken[0].error.contains("bad request"));
+}
+
+#[test]
+fn in_flight_actions_are_capped() {
+    let queue_reader = Box::from(Arc::new(Mutex::new(mock::InMemoryReader::default())));
+    for i in 0..MAX_IN_FLIGHT + 3 {
+        queue_reader
+            .lock()
+            .unwrap()
+            .incoming_queue
+            .push(ActionManifest {
+                data: json!(i).into(),
+                action_type: String::from("flaky"),
+                rule: i.to_string(),
+                envelope: Envelope::new(),
+            });
+    }
+
+    // Every action waits for a retry, so none of them is acked.
+    let action =
+        mock::FlakyAction::new(usize::MAX).with_retry_after(time::Duration::from_secs(3600));
+    let mut plugin_host = PluginHost::default();
+    plugin_host
+        .add_in_memory_async_action_plugin(Box::new(action.clone()))
+        .unwrap();
+
+    let cfg = ExecutorSystemConfig {
+        queue_reader: queue_reader.clone(),
+        record_writer: None,
+        dead_letter_writer: None,
+        plugin_host: Arc::new(plugin_host),
+    };
+    let sys = ExecutorSystem::start(cfg);
+    thread::sleep(time::Duration::from_millis(500));
+
+    sys.terminate().unwrap();
+
+    // The executor stops pulling once too many actions are in flight.
+    assert_eq!(action.attempts.lock().unwrap().len(), MAX_IN_FLIGHT);
+    let reader = queue_reader.lock().unwrap();
+    assert_eq!(reader.incoming_queue.len(), 3);
+    assert_eq!(reader.ack_count(), 0);
+}
+
+#[test]
+fn running_actions_are_drained_on_stop() {
+    let queue_reader = Box::from(Arc::new(Mutex::new(mock::InMemoryReader::default())));
+    for rule in ["1", "2"] {
+        queue_reader
+            .lock()
+            .unwrap()
+            .incoming_queue
+            .push(ActionManifest {
+                data: Payload::default(),
+                action_type: String::from("slow"),
+                rule: rule.into(),
+                envelope: Envelope::new(),
+            });
+    }
+
+    let action = mock::SlowAction {
+        delay: time::Duration::from_millis(300),
+        ..Default::default()
+    };
+    let mut plugin_host = PluginHost::default();
+    plugin_host
+        .add_in_memory_async_action_plugin(Box::new(action.clone()))
+        .unwrap();
+
+    let cfg = ExecutorSystemConfig {
+        queue_reader: queue_reader.clone(),
+        record_writer: None,
+        dead_letter_writer: None,
+        plugin_host: Arc::new(plugin_host),
+    };
+    let sys = ExecutorSystem::start(cfg);
+    thread::sleep(time::Duration::from_millis(100));
+
+    sys.terminate().unwrap();
+
+    // Stopping waits for the actions being executed, and acks them.
+    assert_eq!(*action.executed.lock().unwrap(), 2);
+    assert_eq!(queue_reader.lock().unwrap().ack_count(), 2);
+}
@@ -6,11 +6,17 @@ use async_std::sync::{Arc as AsyncArc, Mutex as AsyncMutex};
 
 use async_trait::async_trait;
 
-use protocol::ActionManifest;
+use plugin_core::{ActionPlugin, AsyncActionPlugin, Error as PluginError};
+
+use protocol::{ActionManifest, ActionRecord, ActionResult, DeadLetter};
+
+use serde_json::json;
 
 use toolkit::message::{Error as MessageError, Message};
 
-use crate::interfaces::ActionManifestQueueReader;
+use crate::interfaces::{
+    ActionManifestQueueReader, ActionRecordQueueWriter, DeadLetterQueueWriter,
+};
 
 pub struct DummyMessage {
     manifest: ActionManifest,
@@ -80,3 +86,204 @@ impl ActionManifestQueueReader for MultiThreadedReader {
         Ok(msg_maybe)
     }
 }
+
+/// Async action plugin recording the manifests it executes.
+#[derive(Clone, Default)]
+pub struct RecordingAction {
+    pub executed: Arc<Mutex<Vec<ActionManifest>>>,
+}
+
+#[async_trait]
+impl AsyncActionPlugin for RecordingAction {
+    async fn execute_action(
+        &self,
+        manifest: ActionManifest,
+    ) -> std::result::Result<(), PluginError> {
+        // Yield to the runtime like a real async plugin would.
+        tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+        self.executed.lock().unwrap().push(manifest);
+        Ok(())
+    }
+
+    fn get_type(&self) -> &str {
+        "record"
+    }
+}
+
+/// Async action plugin failing with a transient error a given number of times before succeeding.
+#[derive(Clone, Default)]
+pub struct FlakyAction {
+    pub failures_left: Arc<Mutex<usize>>,
+    pub attempts: Arc<Mutex<Vec<u32>>>,
+    pub retry_after: std::time::Duration,
+}
+
+impl FlakyAction {
+    pub fn new(failures: usize) -> Self {
+        FlakyAction {
+            failures_left: Arc::new(Mutex::new(failures)),
+            retry_after: std::time::Duration::from_millis(10),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_retry_after(mut self, retry_after: std::time::Duration) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
+}
+
+#[async_trait]
+impl AsyncActionPlugin for FlakyAction {
+    async fn execute_action(
+        &self,
+        manifest: ActionManifest,
+    ) -> std::result::Result<(), PluginError> {
+        self.attempts
+            .lock()
+            .unwrap()
+            .push(manifest.envelope.attempt);
+
+        let mut failures_left = self.failures_left.lock().unwrap();
+        if *failures_left > 0 {
+            *failures_left -= 1;
+            return Err(
+                PluginError::transient("service unavailable").with_retry_after(self.retry_after)
+            );
+        }
+        Ok(())
+    }
+
+    fn get_type(&self) -> &str {
+        "flaky"
+    }
+}
+
+/// Async action plugin always failing with a permanent error.
+#[derive(Clone, Default)]
+pub struct BrokenAction {
+    pub calls: Arc<Mutex<usize>>,
+}
+
+#[async_trait]
+impl AsyncActionPlugin for BrokenAction {
+    async fn execute_action(
+        &self,
+        _manifest: ActionManifest,
+    ) -> std::result::Result<(), PluginError> {
+        *self.calls.lock().unwrap() += 1;
+        Err(PluginError::permanent("bad request"))
+    }
+
+    fn get_type(&self) -> &str {
+        "broken"
+    }
+}
+
+/// Async action plugin taking a while to execute.
+#[derive(Clone, Default)]
+pub struct SlowAction {
+    pub delay: std::time::Duration,
+    pub executed: Arc<Mutex<usize>>,
+}
+
+#[async_trait]
+impl AsyncActionPlugin for SlowAction {
+    async fn execute_action(
+        &self,
+        _manifest: ActionManifest,
+    ) -> std::result::Result<(), PluginError> {
+        tokio::time::sleep(self.delay).await;
+        *self.executed.lock().unwrap() += 1;
+        Ok(())
+    }
+
+    fn get_type(&self) -> &str {
+        "slow"
+    }
+}
+
+/// Async action plugin never returning.
+#[derive(Clone, Default)]
+pub struct HangingAction;
+
+#[async_trait]
+impl AsyncActionPlugin for HangingAction {
+    async fn execute_action(
+        &self,
+        _manifest: ActionManifest,
+    ) -> std::result::Result<(), PluginError> {
+        std::future::pending().await
+    }
+
+    fn get_type(&self) -> &str {
+        "hang"
+    }
+}
+
+/// Synchronous action plugin panicking on every execution.
+#[derive(Clone, Default)]
+pub struct PanickingAction;
+
+impl ActionPlugin for PanickingAction {
+    fn execute_action(&self, _manifest: ActionManifest) -> std::result::Result<(), PluginError> {
+        panic!("bing bong")
+    }
+
+    fn get_type(&self) -> &str {
+        "panic"
+    }
+}
+
+/// Synchronous action plugin reporting the creation of a resource.
+#[derive(Clone, Default)]
+pub struct CreatingAction;
+
+impl ActionPlugin for CreatingAction {
+    fn execute_action(&self, manifest: ActionManifest) -> std::result::Result<(), PluginError> {
+        self.execute_with_result(manifest).map(|_| ())
+    }
+
+    fn get_type(&self) -> &str {
+        "create"
+    }
+
+    fn execute_with_result(
+        &self,
+        _manifest: ActionManifest,
+    ) -> std::result::Result<ActionResult, PluginError> {
+        Ok(ActionResult::new(json!({"id": 42}).into()).with_summary("201 Created"))
+    }
+}
+
+/// Record writer keeping the records it is given.
+#[derive(Clone, Default)]
+pub struct RecordingWriter {
+    pub records: Arc<Mutex<Vec<ActionRecord>>>,
+}
+
+#[async_trait]
+impl ActionRecordQueueWriter for RecordingWriter {
+    async fn push_action_record(&self, record: ActionRecord) -> Result<()> {
+        self.records.lock().unwrap().push(record);
+        Ok(())
+    }
+}
+
+/// Dead-letter writer keeping the dead letters it is given, or failing to publish them.
+#[derive(Clone, Default)]
+pub struct DeadLetterWriter {
+    pub dead_letters: Arc<Mutex<Vec<DeadLetter>>>,
+    pub unavailable: bool,
+}
+
+#[async_trait]
+impl DeadLetterQueueWriter for DeadLetterWriter {
+    async fn push_dead_letter(&self, dead_letter: DeadLetter) -> Result<()> {
+        if self.unavailable {
+            return Err(anyhow::anyhow!("dead-letter queue unavailable"));
+        }
+        self.dead_letters.lock().unwrap().push(dead_letter);
+        Ok(())
+    }
+}
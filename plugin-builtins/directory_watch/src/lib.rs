@@ -1,90 +1,178 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 use std::fs;
-use std::path::PathBuf;
-use std::sync::Mutex;
+use std::path::{Path, PathBuf};
 
-use plugin_core::{Error, TriggerPlugin};
+use plugin_core::{Error, PluginContext, PluginState, TriggerPlugin, TypedTriggerPlugin};
 
-use protocol::{RuleID, Trigger, TriggerConfiguration};
+use protocol::TriggerConfiguration;
+
+use schemars::JsonSchema;
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize)]
-struct DirectoryWatchPayload {
+/// Configuration of a watched directory.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DirectoryWatchPayload {
     directory: PathBuf,
 }
 
-#[derive(Debug, Serialize)]
-struct TriggerData {
+/// Data of the triggers emitted for new files.
+#[derive(Debug, JsonSchema, Serialize)]
+pub struct TriggerData {
     file_name: String,
 }
 
-#[derive(Default)]
+/// Watches directories for new files.
+///
+/// The files seen by each rule are kept in the plugin state, so files added while the
+/// node was down are picked up on the next start.
+#[derive(Default, TriggerPlugin)]
+#[plugin(
+    type = "directory_watch",
+    description = "Triggers when a new file appears in a directory."
+)]
+#[cfg_attr(feature = "export", plugin(export))]
 pub struct DirectoryWatcher {
-    seen_files: Mutex<HashMap<RuleID, HashSet<PathBuf>>>,
+    state: PluginState,
+}
+
+impl DirectoryWatcher {
+    fn list_directory(directory: &Path) -> Result<HashSet<PathBuf>, Error> {
+        Ok(fs::read_dir(directory)
+            .map_err(|e| {
+                Error::transient(format!("failed to read {}", directory.display())).with_source(e)
+            })?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .collect())
+    }
 }
 
-impl TriggerPlugin for DirectoryWatcher {
-    fn get_type(&self) -> &str {
-        "directory_watch"
+impl TypedTriggerPlugin for DirectoryWatcher {
+    type Config = DirectoryWatchPayload;
+    type Data = TriggerData;
+
+    fn validate(&self, payload: &DirectoryWatchPayload) -> Result<(), Error> {
+        if !payload.directory.is_dir() {
+            return Err(Error::invalid_config(format!(
+                "{} is not a directory",
+                payload.directory.display()
+            )));
+        }
+
+        Ok(())
     }
 
-    fn pull_trigger(&self, cfg: &TriggerConfiguration) -> Result<Vec<Trigger>, Error> {
-        let payload: DirectoryWatchPayload =
-            serde_json::from_str(&cfg.data).map_err(|e| Error {
-                message: e.to_string(),
-            })?;
+    fn init(&mut self, ctx: &PluginContext) -> Result<(), Error> {
+        self.state = ctx.state.clone();
+        Ok(())
+    }
 
-        let mut seen_files_guard = self.seen_files.lock().map_err(|e| Error {
-            message: e.to_string(),
-        })?;
-        let seen_files = &mut (*seen_files_guard);
+    fn pull(
+        &self,
+        payload: DirectoryWatchPayload,
+        cfg: &TriggerConfiguration,
+    ) -> Result<Vec<TriggerData>, Error> {
+        let state_key = format!("seen_files.{}", cfg.rule);
+        let current_files = DirectoryWatcher::list_directory(&payload.directory)?;
 
-        match seen_files.get_mut(&cfg.rule) {
-            Some(seen_files) => {
+        match self.state.get::<HashSet<PathBuf>>(&state_key)? {
+            Some(mut seen_files) => {
                 let mut results = Vec::new();
 
-                for entry in fs::read_dir(&payload.directory)
-                    .map_err(|e| Error {
-                        message: e.to_string(),
-                    })?
-                    .filter_map(Result::ok)
-                {
-                    if !seen_files.contains(&entry.path()) {
+                for path in current_files.into_iter() {
+                    if !seen_files.contains(&path) {
                         // Add a trigger.
-                        seen_files.insert(entry.path());
-                        results.push(Trigger {
-                            rule: cfg.rule.clone(),
-                            trigger_type: cfg.trigger_type.clone(),
-                            data: serde_json::to_string(&TriggerData {
-                                file_name: entry.file_name().to_string_lossy().to_string(),
-                            })
-                            .map_err(|e| Error {
-                                message: e.to_string(),
-                            })?,
-                        })
+                        let file_name = path
+                            .file_name()
+                            .map(|f| f.to_string_lossy().to_string())
+                            .unwrap_or_default();
+                        seen_files.insert(path);
+                        results.push(TriggerData { file_name })
                     }
                 }
+
+                if !results.is_empty() {
+                    self.state.set(&state_key, &seen_files)?;
+                }
+
                 Ok(results)
             }
             None => {
-                let mut initial_files = HashSet::new();
-
-                for entry in fs::read_dir(&payload.directory)
-                    .map_err(|e| Error {
-                        message: e.to_string(),
-                    })?
-                    .filter_map(Result::ok)
-                {
-                    initial_files.insert(entry.path());
-                }
-
-                seen_files.insert(cfg.rule.clone(), initial_files);
-
+                // First run for this rule, snapshot the directory.
+                plugin_core::log::info!(
+                    "watching {} ({} existing files)",
+                    payload.directory.display(),
+                    current_files.len()
+                );
+                self.state.set(&state_key, &current_files)?;
                 Ok(Vec::new())
             }
         }
     }
 }
 
-plugin_core::export!((), (DirectoryWatcher));
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use plugin_core::ErrorKind;
+
+    use plugin_testkit::{assert_trigger_data, TriggerConfigBuilder, TriggerHarness};
+
+    use serde_json::json;
+
+    use tempfile::tempdir;
+
+    use super::DirectoryWatcher;
+
+    #[test]
+    fn new_files() {
+        let watched_dir = tempdir().unwrap();
+        fs::write(watched_dir.path().join("existing.txt"), "bing").unwrap();
+
+        let harness = TriggerHarness::new(DirectoryWatcher::default());
+        let cfg = TriggerConfigBuilder::new("directory_watch")
+            .rule("42")
+            .data(json!({ "directory": watched_dir.path() }))
+            .build();
+        assert!(harness.validate(&cfg).is_ok());
+
+        assert!(harness.pull(&cfg).unwrap().is_empty());
+        let seen = harness
+            .state()
+            .value::<HashSet<PathBuf>>("seen_files.42")
+            .unwrap();
+        assert_eq!(seen.len(), 1);
+
+        fs::write(watched_dir.path().join("new.txt"), "bong").unwrap();
+        assert_trigger_data(
+            &harness.pull(&cfg).unwrap(),
+            &[json!({ "file_name": "new.txt" })],
+        );
+        assert!(harness.pull(&cfg).unwrap().is_empty());
+    }
+
+    #[test]
+    fn invalid_config() {
+        let harness = TriggerHarness::new(DirectoryWatcher::default());
+
+        let cfg = TriggerConfigBuilder::new("directory_watch")
+            .data(json!({ "path": "/" }))
+            .build();
+        assert_eq!(
+            harness.validate(&cfg).unwrap_err().kind,
+            ErrorKind::InvalidConfig
+        );
+
+        let cfg = TriggerConfigBuilder::new("directory_watch")
+            .data(json!({ "directory": "/does/not/exist" }))
+            .build();
+        assert_eq!(
+            harness.validate(&cfg).unwrap_err().kind,
+            ErrorKind::InvalidConfig
+        );
+    }
+}
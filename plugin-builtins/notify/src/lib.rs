@@ -1,50 +1,125 @@
 use std::process::Command;
 
-use plugin_core::{ActionPlugin, Error};
+use plugin_core::{ActionPlugin, Error, PluginContext, TypedActionPlugin};
 
 use protocol::ActionManifest;
 
+use schemars::JsonSchema;
+
 use serde::Deserialize;
 
-#[derive(Deserialize)]
-struct NotifyPayload {
+/// Configuration of a notification.
+#[derive(Deserialize, JsonSchema)]
+pub struct NotifyPayload {
     title: String,
     body: String,
 }
 
-#[derive(Clone, Debug, Default)]
-pub struct NotifyPlugin {}
+const DEFAULT_COMMAND: &str = "notify-send";
+
+#[derive(Deserialize)]
+#[serde(default)]
+struct NotifySettings {
+    /// Command used to show notifications. Called with the title and body as arguments.
+    command: String,
+}
 
-impl ActionPlugin for NotifyPlugin {
-    fn get_type(&self) -> &str {
-        "notify"
+impl Default for NotifySettings {
+    fn default() -> Self {
+        NotifySettings {
+            command: String::from(DEFAULT_COMMAND),
+        }
     }
+}
 
-    fn execute_action(&self, manifest: ActionManifest) -> Result<(), Error> {
-        let payload: NotifyPayload = serde_json::from_str(&manifest.data).map_err(|e| Error {
-            message: e.to_string(),
-        })?;
+#[derive(ActionPlugin, Clone, Debug)]
+#[plugin(type = "notify", description = "Shows a desktop notification.")]
+#[cfg_attr(feature = "export", plugin(export))]
+pub struct NotifyPlugin {
+    command: String,
+}
+
+impl Default for NotifyPlugin {
+    fn default() -> Self {
+        NotifyPlugin {
+            command: String::from(DEFAULT_COMMAND),
+        }
+    }
+}
+
+impl TypedActionPlugin for NotifyPlugin {
+    type Config = NotifyPayload;
+
+    fn init(&mut self, ctx: &PluginContext) -> Result<(), Error> {
+        let settings: NotifySettings = ctx.settings()?;
+        self.command = settings.command;
+        Ok(())
+    }
 
-        let mut child_process = Command::new("notify-send")
+    fn execute(&self, payload: NotifyPayload, _manifest: &ActionManifest) -> Result<(), Error> {
+        let mut child_process = Command::new(&self.command)
             .arg(payload.title)
             .arg(payload.body)
             .spawn()
-            .map_err(|e| Error {
-                message: e.to_string(),
+            .map_err(|e| {
+                Error::permanent(format!("failed to run [{}]", &self.command)).with_source(e)
             })?;
 
-        let exit_status = child_process.wait().map_err(|e| Error {
-            message: e.to_string(),
-        })?;
+        let exit_status = child_process
+            .wait()
+            .map_err(|e| Error::transient("failed to wait for notification").with_source(e))?;
 
         if !exit_status.success() {
-            Err(Error {
-                message: String::from("non-zero status code"),
-            })
+            // Most likely no notification server is available yet.
+            Err(Error::transient(format!(
+                "[{}] exited with {}",
+                &self.command, exit_status
+            )))
         } else {
             Ok(())
         }
     }
 }
 
-plugin_core::export!((NotifyPlugin), ());
+#[cfg(test)]
+mod tests {
+    use plugin_core::ErrorKind;
+
+    use plugin_testkit::{ActionHarness, ManifestBuilder, TestContext};
+
+    use serde_json::json;
+
+    use super::NotifyPlugin;
+
+    fn harness(command: &str) -> ActionHarness<NotifyPlugin> {
+        let ctx = TestContext::new().settings(json!({ "command": command }));
+        ActionHarness::with_context(NotifyPlugin::default(), ctx).unwrap()
+    }
+
+    fn notification() -> ManifestBuilder {
+        ManifestBuilder::new("notify").data(json!({"title": "bing", "body": "bong"}))
+    }
+
+    #[test]
+    fn execute() {
+        let mut harness = harness("true");
+        harness.execute(notification().build()).unwrap();
+        assert_eq!(harness.executed().len(), 1);
+    }
+
+    #[test]
+    fn error_kinds() {
+        let mut harness = harness("false");
+        let err = harness.execute(notification().build()).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Transient);
+
+        let err = harness
+            .execute(ManifestBuilder::new("notify").data(json!({})).build())
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidConfig);
+
+        let mut harness = self::harness("/does/not/exist");
+        let err = harness.execute(notification().build()).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Permanent);
+    }
+}
@@ -0,0 +1,20 @@
+use std::env;
+use std::process::Command;
+
+fn main() {
+    // Plugins and hosts must be built by the same compiler for the Rust-ABI plugin
+    // objects to be compatible. Record the compiler version for the load-time handshake.
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| String::from("rustc"));
+    let version = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .unwrap_or_default();
+
+    println!(
+        "cargo:rustc-env=PLUGIN_CORE_RUSTC_VERSION={}",
+        version.trim()
+    );
+    println!("cargo:rerun-if-env-changed=RUSTC");
+}
@@ -0,0 +1,79 @@
+//! ABI-stable plugin entry point.
+//!
+//! Plugin libraries export a single `#[repr(C)]` [`PluginDeclaration`] static, which the host
+//! inspects before calling into any Rust-ABI code. The plugin objects themselves are regular
+//! Rust trait objects, so the host only initializes a plugin when the ABI version, the compiler
+//! version and the `plugin-core` version all match its own.
+
+use std::borrow::Cow;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+use log::{LevelFilter, Log};
+
+use crate::Plugin;
+
+/// Version of the plugin ABI.
+///
+/// Must be bumped whenever the layout of [`PluginDeclaration`], of [`Plugin`] or of the plugin
+/// traits changes.
+pub const ABI_VERSION: u32 = 12;
+
+/// Version of the compiler that built this copy of `plugin-core`.
+pub const RUSTC_VERSION: &str = env!("PLUGIN_CORE_RUSTC_VERSION");
+
+/// Version of this copy of `plugin-core`.
+pub const CORE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Name of the symbol under which plugin libraries export their declaration.
+pub const PLUGIN_DECLARATION_SYMBOL: &str = "shift3_plugin_declaration";
+
+#[doc(hidden)]
+pub const RUSTC_VERSION_CSTR: &str = concat!(env!("PLUGIN_CORE_RUSTC_VERSION"), "\0");
+
+#[doc(hidden)]
+pub const CORE_VERSION_CSTR: &str = concat!(env!("CARGO_PKG_VERSION"), "\0");
+
+/// Declaration exported by plugin libraries. Generated by [`export!`](crate::export).
+#[repr(C)]
+pub struct PluginDeclaration {
+    /// Must stay the first field, so it can be checked before anything else is read.
+    pub abi_version: u32,
+
+    /// NUL-terminated compiler version.
+    pub rustc_version: *const c_char,
+
+    /// NUL-terminated `plugin-core` version.
+    pub core_version: *const c_char,
+
+    /// Initializes the plugin. Returns null if initialization panicked.
+    ///
+    /// The returned pointer must be reclaimed with `Box::from_raw`, and only once the
+    /// declaration was found to be compatible.
+    pub init: unsafe extern "C" fn() -> *mut Plugin,
+
+    /// Installs the logger records emitted by the plugin are sent to, along with the maximum
+    /// level to log. Called by the host before `init`.
+    pub set_logger: fn(&'static dyn Log, LevelFilter),
+}
+
+// The declaration only points to static, immutable data.
+unsafe impl Sync for PluginDeclaration {}
+
+impl PluginDeclaration {
+    /// Returns the version of the compiler that built the plugin.
+    ///
+    /// # Safety
+    /// The declaration must have a matching ABI version.
+    pub unsafe fn rustc_version(&self) -> Cow<'_, str> {
+        CStr::from_ptr(self.rustc_version).to_string_lossy()
+    }
+
+    /// Returns the `plugin-core` version the plugin was built against.
+    ///
+    /// # Safety
+    /// The declaration must have a matching ABI version.
+    pub unsafe fn core_version(&self) -> Cow<'_, str> {
+        CStr::from_ptr(self.core_version).to_string_lossy()
+    }
+}
@@ -1,8 +1,78 @@
-use protocol::ActionManifest;
+use async_trait::async_trait;
 
-use crate::Error;
+use protocol::{ActionManifest, ActionResult, Payload};
+
+use crate::{Error, PluginContext, PluginMetadata};
 
 pub trait ActionPlugin: Send + Sync {
     fn execute_action(&self, manifest: ActionManifest) -> Result<(), Error>;
     fn get_type(&self) -> &str;
+
+    /// Executes an action, reporting its result. This is what the host calls.
+    ///
+    /// Plugins with results to report (e.g. a response status or the id of a created
+    /// resource) implement this method, and implement `execute_action` by discarding the
+    /// result. By default, actions have an empty result.
+    fn execute_with_result(&self, manifest: ActionManifest) -> Result<ActionResult, Error> {
+        self.execute_action(manifest)?;
+        Ok(ActionResult::default())
+    }
+
+    fn metadata(&self) -> PluginMetadata {
+        PluginMetadata::new(self.get_type())
+    }
+
+    /// Checks an action configuration when a rule is created, before any action is executed.
+    ///
+    /// The configuration is the action template of the rule, rendered with sample data of the
+    /// trigger type of the rule.
+    fn validate_config(&self, _config: &Payload) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called once by the host before the plugin is used.
+    fn init(&mut self, _ctx: &PluginContext) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called once by the host when it stops. The plugin won't be used afterwards.
+    fn shutdown(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Asynchronous variant of [`ActionPlugin`], driven natively by the action executor.
+///
+/// Futures returned by plugin libraries are polled by the host runtime, but a dynamically loaded
+/// plugin links its own copy of any runtime crate: it can't rely on the host's runtime
+/// context (e.g. `tokio::spawn` or tokio IO types).
+#[async_trait]
+pub trait AsyncActionPlugin: Send + Sync {
+    async fn execute_action(&self, manifest: ActionManifest) -> Result<(), Error>;
+    fn get_type(&self) -> &str;
+
+    /// See [`ActionPlugin::execute_with_result`].
+    async fn execute_with_result(&self, manifest: ActionManifest) -> Result<ActionResult, Error> {
+        self.execute_action(manifest).await?;
+        Ok(ActionResult::default())
+    }
+
+    fn metadata(&self) -> PluginMetadata {
+        PluginMetadata::new(self.get_type())
+    }
+
+    /// See [`ActionPlugin::validate_config`].
+    fn validate_config(&self, _config: &Payload) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// See [`ActionPlugin::init`].
+    fn init(&mut self, _ctx: &PluginContext) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// See [`ActionPlugin::shutdown`].
+    fn shutdown(&self) -> Result<(), Error> {
+        Ok(())
+    }
 }
@@ -0,0 +1,49 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// Source of the current time. Implemented by the host, and faked in tests.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> SystemTime;
+}
+
+/// Clock reading the system time.
+#[derive(Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Host-provided clock of a plugin.
+///
+/// Plugins dealing with time (e.g. expiring cursors) should read it from here instead of
+/// the system, so they can be tested with a fake clock.
+#[derive(Clone)]
+pub struct PluginClock {
+    clock: Arc<dyn Clock>,
+}
+
+impl PluginClock {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        PluginClock { clock }
+    }
+
+    pub fn now(&self) -> SystemTime {
+        self.clock.now()
+    }
+}
+
+impl Default for PluginClock {
+    fn default() -> Self {
+        PluginClock::new(Arc::new(SystemClock))
+    }
+}
+
+impl fmt::Debug for PluginClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PluginClock")
+    }
+}
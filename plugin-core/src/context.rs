@@ -0,0 +1,46 @@
+use serde::de::DeserializeOwned;
+
+use protocol::Payload;
+
+use crate::{Error, PluginClock, PluginState};
+
+/// Host-provided context, passed to plugins when they are initialized.
+#[derive(Clone, Debug, Default)]
+pub struct PluginContext {
+    /// Settings of the plugin, from the host configuration. Null when none are configured.
+    pub settings: Payload,
+
+    /// State of the plugin. Persistent when the host has a state store configured.
+    pub state: PluginState,
+
+    /// Clock the plugin should read the current time from.
+    pub clock: PluginClock,
+}
+
+impl PluginContext {
+    pub fn new(settings: Payload, state: PluginState) -> Self {
+        PluginContext {
+            settings,
+            state,
+            clock: PluginClock::default(),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: PluginClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Deserializes the plugin settings into a concrete structure.
+    ///
+    /// Returns the default settings when none are configured.
+    pub fn settings<T: DeserializeOwned + Default>(&self) -> Result<T, Error> {
+        if self.settings.is_null() {
+            return Ok(T::default());
+        }
+
+        self.settings
+            .decode()
+            .map_err(|e| Error::invalid_config(format!("invalid plugin settings: {}", e)))
+    }
+}
@@ -1,14 +1,136 @@
+use std::error::Error as StdError;
 use std::fmt;
+use std::time::Duration;
+
+/// Category of a plugin error, used by the host to decide between retrying and quarantining.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ErrorKind {
+    /// The configuration or payload handed to the plugin is invalid. Never retried.
+    InvalidConfig,
+
+    /// The failure is expected to go away (e.g. network down). Retried.
+    Transient,
+
+    /// The operation can't succeed. Never retried.
+    Permanent,
+
+    /// The operation took too long. Retried.
+    Timeout,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            ErrorKind::InvalidConfig => "invalid config",
+            ErrorKind::Transient => "transient",
+            ErrorKind::Permanent => "permanent",
+            ErrorKind::Timeout => "timeout",
+        };
+        write!(f, "{}", kind)
+    }
+}
 
 #[derive(Debug)]
 pub struct Error {
+    pub kind: ErrorKind,
     pub message: String,
+
+    /// Underlying cause of the error.
+    pub source: Option<Box<dyn StdError + Send + Sync>>,
+
+    /// How long the host should wait before retrying, when the plugin knows better than the
+    /// host's default backoff (e.g. from a rate limit response).
+    pub retry_after: Option<Duration>,
+}
+
+impl Error {
+    pub fn new<S: Into<String>>(kind: ErrorKind, message: S) -> Self {
+        Error {
+            kind,
+            message: message.into(),
+            source: None,
+            retry_after: None,
+        }
+    }
+
+    pub fn invalid_config<S: Into<String>>(message: S) -> Self {
+        Error::new(ErrorKind::InvalidConfig, message)
+    }
+
+    pub fn transient<S: Into<String>>(message: S) -> Self {
+        Error::new(ErrorKind::Transient, message)
+    }
+
+    pub fn permanent<S: Into<String>>(message: S) -> Self {
+        Error::new(ErrorKind::Permanent, message)
+    }
+
+    pub fn timeout<S: Into<String>>(message: S) -> Self {
+        Error::new(ErrorKind::Timeout, message)
+    }
+
+    pub fn with_source<E: StdError + Send + Sync + 'static>(mut self, source: E) -> Self {
+        self.source = Some(Box::new(source));
+        self
+    }
+
+    pub fn with_retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = Some(retry_after);
+        self
+    }
+
+    /// Whether the failed operation is worth retrying.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self.kind, ErrorKind::Transient | ErrorKind::Timeout)
+    }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:?}", self)
+        write!(f, "{} error: {}", self.kind, self.message)?;
+        if let Some(source) = &self.source {
+            write!(f, ": {}", source)?;
+        }
+        Ok(())
     }
 }
 
-impl std::error::Error for Error {}
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| source.as_ref() as &(dyn StdError + 'static))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error as StdError;
+    use std::io;
+    use std::time::Duration;
+
+    use super::{Error, ErrorKind};
+
+    #[test]
+    fn retryable() {
+        assert!(Error::transient("network down").is_retryable());
+        assert!(Error::timeout("too slow").is_retryable());
+        assert!(!Error::permanent("nope").is_retryable());
+        assert!(!Error::invalid_config("bad payload").is_retryable());
+    }
+
+    #[test]
+    fn source_chain() {
+        let e = Error::transient("failed to reach server")
+            .with_source(io::Error::new(io::ErrorKind::ConnectionRefused, "refused"))
+            .with_retry_after(Duration::from_secs(3));
+
+        assert_eq!(e.kind, ErrorKind::Transient);
+        assert_eq!(e.retry_after, Some(Duration::from_secs(3)));
+        assert_eq!(e.source().unwrap().to_string(), "refused");
+        assert_eq!(
+            e.to_string(),
+            "transient error: failed to reach server: refused"
+        );
+    }
+}
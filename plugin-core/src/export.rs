@@ -1,22 +1,44 @@
 #[macro_export]
 macro_rules! export {
     (($($action_struct:ty)*), ($($trigger_struct:ty)*)) => {
-        #[no_mangle]
-        pub extern "C" fn init_plugin() -> Box<$crate::Plugin> {
+        $crate::export!(($($action_struct)*), ($($trigger_struct)*), (), (), ());
+    };
+    (($($action_struct:ty)*), ($($trigger_struct:ty)*), ($($async_action_struct:ty)*), ($($async_trigger_struct:ty)*)) => {
+        $crate::export!(($($action_struct)*), ($($trigger_struct)*), ($($async_action_struct)*), ($($async_trigger_struct)*), ());
+    };
+    (($($action_struct:ty)*), ($($trigger_struct:ty)*), ($($async_action_struct:ty)*), ($($async_trigger_struct:ty)*), ($($push_trigger_struct:ty)*)) => {
+        unsafe extern "C" fn __shift3_init_plugin() -> *mut $crate::Plugin {
             match std::panic::catch_unwind(|| {
-                let mut actions: Vec<Box<dyn $crate::ActionPlugin + 'static>> = vec![$(Box::from(<$action_struct>::default()),)*];
-                let mut triggers: Vec<Box<dyn $crate::TriggerPlugin + 'static>> = vec![$(Box::from(<$trigger_struct>::default()),)*];
-                Box::new(
-                    $crate::Plugin::new(actions, triggers)
-                )
+                let actions: Vec<Box<dyn $crate::ActionPlugin + 'static>> = vec![$(Box::from(<$action_struct>::default()),)*];
+                let triggers: Vec<Box<dyn $crate::TriggerPlugin + 'static>> = vec![$(Box::from(<$trigger_struct>::default()),)*];
+                let async_actions: Vec<Box<dyn $crate::AsyncActionPlugin + 'static>> = vec![$(Box::from(<$async_action_struct>::default()),)*];
+                let async_triggers: Vec<Box<dyn $crate::AsyncTriggerPlugin + 'static>> = vec![$(Box::from(<$async_trigger_struct>::default()),)*];
+                let push_triggers: Vec<Box<dyn $crate::PushTriggerPlugin + 'static>> = vec![$(Box::from(<$push_trigger_struct>::default()),)*];
+                $crate::Plugin::new(actions, triggers)
+                    .with_async(async_actions, async_triggers)
+                    .with_push(push_triggers)
             }) {
-                Ok(ptr) => ptr,
+                Ok(plugin) => Box::into_raw(Box::new(plugin)),
                 Err(e) => {
-                    // TODO: Find way of logging this _properly_ in case of error.
-                    eprintln!("FFI: caught unwinding panic in plugin [{}]", env!("CARGO_PKG_NAME"));
-                    Box::new($crate::Plugin::new(Vec::new(), Vec::new()))
+                    // The host logger is installed before init, so this reaches the host logs.
+                    $crate::log::error!(
+                        "caught unwinding panic while initializing plugin [{}]: {}",
+                        env!("CARGO_PKG_NAME"),
+                        $crate::logging::panic_message(e.as_ref())
+                    );
+                    std::ptr::null_mut()
                 }
             }
         }
-    }
+
+        #[no_mangle]
+        #[allow(non_upper_case_globals)]
+        pub static shift3_plugin_declaration: $crate::PluginDeclaration = $crate::PluginDeclaration {
+            abi_version: $crate::ABI_VERSION,
+            rustc_version: $crate::abi::RUSTC_VERSION_CSTR.as_ptr() as *const std::os::raw::c_char,
+            core_version: $crate::abi::CORE_VERSION_CSTR.as_ptr() as *const std::os::raw::c_char,
+            init: __shift3_init_plugin,
+            set_logger: $crate::logging::install,
+        };
+    };
 }
@@ -1,20 +1,53 @@
 use std::sync::Arc;
 
+pub mod abi;
 mod action;
+mod clock;
+mod context;
 mod error;
 mod export;
+pub mod logging;
+mod metadata;
+mod push;
+mod state;
 mod trigger;
+mod typed;
 
-pub use action::ActionPlugin;
-pub use error::Error;
-pub use trigger::TriggerPlugin;
+pub use abi::{
+    PluginDeclaration, ABI_VERSION, CORE_VERSION, PLUGIN_DECLARATION_SYMBOL, RUSTC_VERSION,
+};
+pub use action::{ActionPlugin, AsyncActionPlugin};
+pub use clock::{Clock, PluginClock, SystemClock};
+pub use context::PluginContext;
+pub use error::{Error, ErrorKind};
+pub use metadata::PluginMetadata;
+pub use push::{PushTriggerPlugin, TriggerSink};
+pub use state::{MemoryStateStore, PluginState, StateStore};
+pub use trigger::{AsyncTriggerPlugin, TriggerPlugin};
+pub use typed::{TypedActionPlugin, TypedTriggerPlugin};
 
-pub const PLUGIN_INIT_SYMBOL: &str = "init_plugin";
+pub use plugin_derive::{ActionPlugin, TriggerPlugin};
+
+/// The `log` crate plugins should log with. Records logged through it reach the host, see
+/// [`logging`].
+pub use log;
+
+#[doc(hidden)]
+pub use protocol;
+
+#[doc(hidden)]
+pub use typed::derive as __derive;
+
+// Lets the code generated by the plugin derives refer to this crate from within it.
+extern crate self as plugin_core;
 
 #[derive(Default)]
 pub struct Plugin {
     pub actions: Vec<Arc<Box<dyn ActionPlugin>>>,
     pub triggers: Vec<Arc<Box<dyn TriggerPlugin>>>,
+    pub async_actions: Vec<Arc<Box<dyn AsyncActionPlugin>>>,
+    pub async_triggers: Vec<Arc<Box<dyn AsyncTriggerPlugin>>>,
+    pub push_triggers: Vec<Arc<Box<dyn PushTriggerPlugin>>>,
 }
 
 impl Plugin {
@@ -22,6 +55,24 @@ impl Plugin {
         Plugin {
             actions: actions.into_iter().map(Arc::new).collect(),
             triggers: triggers.into_iter().map(Arc::new).collect(),
+            async_actions: Vec::new(),
+            async_triggers: Vec::new(),
+            push_triggers: Vec::new(),
         }
     }
+
+    pub fn with_async(
+        mut self,
+        async_actions: Vec<Box<dyn AsyncActionPlugin>>,
+        async_triggers: Vec<Box<dyn AsyncTriggerPlugin>>,
+    ) -> Self {
+        self.async_actions = async_actions.into_iter().map(Arc::new).collect();
+        self.async_triggers = async_triggers.into_iter().map(Arc::new).collect();
+        self
+    }
+
+    pub fn with_push(mut self, push_triggers: Vec<Box<dyn PushTriggerPlugin>>) -> Self {
+        self.push_triggers = push_triggers.into_iter().map(Arc::new).collect();
+        self
+    }
 }
@@ -0,0 +1,171 @@
+//! Forwarding of plugin log records to the host logger.
+//!
+//! Plugin libraries embed their own copy of the `log` crate, so records emitted inside a
+//! dynamically loaded plugin never reach the logger installed by the host. When loading a
+//! library, the host installs a [`PluginLogger`] in it, which forwards records to the host
+//! logger tagged with the plugin and the rule being processed.
+//!
+//! Plugins must log through the [`log`](crate::log) re-exported by `plugin-core`: a plugin
+//! depending on another version of `log` has its own logger, which the host doesn't install.
+//!
+//! The host describes what is being processed with [`scope`] and [`scoped`] around its calls
+//! into plugins.
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct LogScope {
+    plugin: String,
+    rule: Option<String>,
+}
+
+thread_local! {
+    static CURRENT_SCOPE: RefCell<Option<LogScope>> = const { RefCell::new(None) };
+}
+
+/// Runs `f` with the records logged on this thread tagged with the given plugin and rule.
+pub fn scope<R, F: FnOnce() -> R>(plugin: &str, rule: Option<&str>, f: F) -> R {
+    let new_scope = LogScope {
+        plugin: String::from(plugin),
+        rule: rule.map(String::from),
+    };
+    let previous = CURRENT_SCOPE.with(|s| s.replace(Some(new_scope)));
+
+    // Restore the previous scope even if `f` unwinds.
+    struct Restore(Option<LogScope>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            CURRENT_SCOPE.with(|s| *s.borrow_mut() = previous);
+        }
+    }
+    let _restore = Restore(previous);
+
+    f()
+}
+
+/// Future tagging the records logged while it is polled. Created by [`scoped`].
+pub struct Scoped<F> {
+    inner: F,
+    plugin: String,
+    rule: Option<String>,
+}
+
+/// Tags the records logged while polling `future` with the given plugin and rule.
+pub fn scoped<F: Future + Unpin>(plugin: &str, rule: Option<&str>, future: F) -> Scoped<F> {
+    Scoped {
+        inner: future,
+        plugin: String::from(plugin),
+        rule: rule.map(String::from),
+    }
+}
+
+impl<F: Future + Unpin> Future for Scoped<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let inner = &mut this.inner;
+        scope(&this.plugin, this.rule.as_deref(), || {
+            Pin::new(inner).poll(cx)
+        })
+    }
+}
+
+/// Logger forwarding the records of a plugin library to the host logger.
+///
+/// Must be created by the host: the scope and the destination logger are read from the copy
+/// of `plugin-core` that created the logger.
+pub struct PluginLogger {
+    library: String,
+}
+
+impl PluginLogger {
+    /// Creates a logger for a library. Records logged outside of a [`scope`] are tagged with
+    /// the name of the library.
+    pub fn new<S: Into<String>>(library: S) -> Self {
+        PluginLogger {
+            library: library.into(),
+        }
+    }
+
+    fn tag(&self) -> String {
+        CURRENT_SCOPE.with(|s| match s.borrow().as_ref() {
+            Some(LogScope {
+                plugin,
+                rule: Some(rule),
+            }) => format!("{} rule={}", plugin, rule),
+            Some(LogScope { plugin, rule: None }) => plugin.clone(),
+            None => self.library.clone(),
+        })
+    }
+}
+
+impl Log for PluginLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        log::logger().enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        log::logger().log(
+            &Record::builder()
+                .args(format_args!("[{}] {}", self.tag(), record.args()))
+                .level(record.level())
+                .target(record.target())
+                .module_path(record.module_path())
+                .file(record.file())
+                .line(record.line())
+                .build(),
+        );
+    }
+
+    fn flush(&self) {
+        log::logger().flush()
+    }
+}
+
+/// Installs the host logger in a plugin library. Called by the host through the
+/// [`PluginDeclaration`](crate::PluginDeclaration) of the library.
+#[doc(hidden)]
+pub fn install(logger: &'static dyn Log, max_level: LevelFilter) {
+    // A library already holding a logger was loaded before, and still forwards to the host.
+    let _ = log::set_logger(logger);
+    log::set_max_level(max_level);
+}
+
+#[doc(hidden)]
+pub fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{scope, PluginLogger};
+
+    #[test]
+    fn tags() {
+        let logger = PluginLogger::new("libbing");
+        assert_eq!(logger.tag(), "libbing");
+
+        scope("bing", None, || {
+            assert_eq!(logger.tag(), "bing");
+
+            scope("bong", Some("42"), || {
+                assert_eq!(logger.tag(), "bong rule=42");
+            });
+
+            assert_eq!(logger.tag(), "bing");
+        });
+
+        assert_eq!(logger.tag(), "libbing");
+    }
+}
@@ -0,0 +1,91 @@
+use schemars::{schema_for, JsonSchema};
+
+use serde::{Deserialize, Serialize};
+
+use serde_json::Value;
+
+/// Self-description of a plugin, used by tooling to list installed plugins and validate rules.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PluginMetadata {
+    /// Name of the plugin. Matches the plugin type.
+    pub name: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// JSON Schema of the configuration payload (`TriggerConfiguration::data` for triggers,
+    /// the rendered action configuration for actions).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config_schema: Option<Value>,
+
+    /// JSON Schema of the data carried by emitted triggers. Only meaningful for trigger plugins.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_schema: Option<Value>,
+}
+
+impl PluginMetadata {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        PluginMetadata {
+            name: name.into(),
+            version: None,
+            description: None,
+            config_schema: None,
+            data_schema: None,
+        }
+    }
+
+    pub fn version<S: Into<String>>(mut self, version: S) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    pub fn description<S: Into<String>>(mut self, description: S) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Sets the config schema to the schema of `T`.
+    pub fn config_schema<T: JsonSchema>(mut self) -> Self {
+        self.config_schema = Some(schema_value::<T>());
+        self
+    }
+
+    /// Sets the trigger data schema to the schema of `T`.
+    pub fn data_schema<T: JsonSchema>(mut self) -> Self {
+        self.data_schema = Some(schema_value::<T>());
+        self
+    }
+}
+
+fn schema_value<T: JsonSchema>() -> Value {
+    // Serializing a schema can't fail: it only holds maps, strings and numbers.
+    serde_json::to_value(schema_for!(T)).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use schemars::JsonSchema;
+
+    use super::PluginMetadata;
+
+    #[derive(JsonSchema)]
+    #[allow(dead_code)]
+    struct Payload {
+        directory: String,
+    }
+
+    #[test]
+    fn config_schema() {
+        let metadata = PluginMetadata::new("test")
+            .version("1.0.0")
+            .config_schema::<Payload>();
+
+        let schema = metadata.config_schema.unwrap();
+        assert_eq!(schema["properties"]["directory"]["type"], "string");
+        assert_eq!(schema["required"][0], "directory");
+        assert!(metadata.data_schema.is_none());
+    }
+}
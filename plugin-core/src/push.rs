@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use protocol::{Trigger, TriggerConfiguration};
+
+use crate::{Error, PluginContext, PluginMetadata};
+
+/// Destination of the triggers emitted by push trigger plugins. Implemented by the host.
+pub trait TriggerSink: Send + Sync {
+    /// Hands a trigger to the trigger system. Fails once the trigger system is stopped.
+    fn emit(&self, trigger: Trigger) -> Result<(), Error>;
+}
+
+/// Event-driven trigger plugin (e.g. inotify, webhooks, socket listeners).
+///
+/// Instead of being polled, the plugin is started once per trigger configuration and emits
+/// triggers to a sink whenever events happen, from threads it manages itself.
+pub trait PushTriggerPlugin: Send + Sync {
+    fn get_type(&self) -> &str;
+
+    /// Starts watching for events of a configuration. Must not block.
+    fn start(&self, cfg: &TriggerConfiguration, sink: Arc<dyn TriggerSink>) -> Result<(), Error>;
+
+    /// Stops watching for events of a configuration. The sink of the configuration must not
+    /// be used afterwards.
+    fn stop(&self, cfg: &TriggerConfiguration) -> Result<(), Error>;
+
+    fn metadata(&self) -> PluginMetadata {
+        PluginMetadata::new(self.get_type())
+    }
+
+    /// See [`TriggerPlugin::validate_config`](crate::TriggerPlugin::validate_config).
+    fn validate_config(&self, _cfg: &TriggerConfiguration) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// See [`TriggerPlugin::init`](crate::TriggerPlugin::init).
+    fn init(&mut self, _ctx: &PluginContext) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// See [`TriggerPlugin::shutdown`](crate::TriggerPlugin::shutdown).
+    fn shutdown(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::Error;
+
+/// Key/value storage backing a [`PluginState`]. Implemented by the host.
+pub trait StateStore: Send + Sync {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error>;
+    fn set(&self, key: &str, value: &[u8]) -> Result<(), Error>;
+    fn remove(&self, key: &str) -> Result<(), Error>;
+
+    /// Persists pending writes.
+    fn flush(&self) -> Result<(), Error>;
+}
+
+/// Volatile state store, used when the host has no persistent storage configured.
+#[derive(Default)]
+pub struct MemoryStateStore {
+    data: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+impl StateStore for MemoryStateStore {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        let data = self
+            .data
+            .lock()
+            .map_err(|e| Error::transient(e.to_string()))?;
+        Ok(data.get(key).cloned())
+    }
+
+    fn set(&self, key: &str, value: &[u8]) -> Result<(), Error> {
+        let mut data = self
+            .data
+            .lock()
+            .map_err(|e| Error::transient(e.to_string()))?;
+        data.insert(String::from(key), Vec::from(value));
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> Result<(), Error> {
+        let mut data = self
+            .data
+            .lock()
+            .map_err(|e| Error::transient(e.to_string()))?;
+        data.remove(key);
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Host-provided state handle of a plugin, used to persist cursors, seen-sets and the like
+/// across restarts.
+///
+/// Each plugin type gets its own key space.
+#[derive(Clone)]
+pub struct PluginState {
+    store: Arc<dyn StateStore>,
+}
+
+impl PluginState {
+    pub fn new(store: Arc<dyn StateStore>) -> Self {
+        PluginState { store }
+    }
+
+    /// Returns the value stored at a key, deserialized from JSON.
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Error> {
+        match self.store.get(key)? {
+            Some(data) => serde_json::from_slice(&data).map(Some).map_err(|e| {
+                Error::permanent(format!("failed to deserialize state [{}]: {}", key, e))
+            }),
+            None => Ok(None),
+        }
+    }
+
+    /// Stores a value at a key, serialized as JSON.
+    pub fn set<T: Serialize>(&self, key: &str, value: &T) -> Result<(), Error> {
+        let data = serde_json::to_vec(value)
+            .map_err(|e| Error::permanent(format!("failed to serialize state [{}]: {}", key, e)))?;
+        self.store.set(key, &data)
+    }
+
+    pub fn remove(&self, key: &str) -> Result<(), Error> {
+        self.store.remove(key)
+    }
+
+    pub fn flush(&self) -> Result<(), Error> {
+        self.store.flush()
+    }
+}
+
+impl Default for PluginState {
+    fn default() -> Self {
+        PluginState::new(Arc::new(MemoryStateStore::default()))
+    }
+}
+
+impl fmt::Debug for PluginState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PluginState")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PluginState;
+
+    #[test]
+    fn memory_round_trip() {
+        let state = PluginState::default();
+        assert_eq!(state.get::<Vec<String>>("seen").unwrap(), None);
+
+        state.set("seen", &vec!["a.pdf", "b.pdf"]).unwrap();
+        assert_eq!(
+            state.get::<Vec<String>>("seen").unwrap(),
+            Some(vec![String::from("a.pdf"), String::from("b.pdf")])
+        );
+
+        state.remove("seen").unwrap();
+        assert_eq!(state.get::<Vec<String>>("seen").unwrap(), None);
+    }
+}
@@ -1,8 +1,60 @@
+use async_trait::async_trait;
+
 use protocol::{Trigger, TriggerConfiguration};
 
-use crate::Error;
+use crate::{Error, PluginContext, PluginMetadata};
 
 pub trait TriggerPlugin: Send + Sync {
     fn get_type(&self) -> &str;
     fn pull_trigger(&self, cfg: &TriggerConfiguration) -> Result<Vec<Trigger>, Error>;
+
+    fn metadata(&self) -> PluginMetadata {
+        PluginMetadata::new(self.get_type())
+    }
+
+    /// Checks a trigger configuration when it is loaded.
+    ///
+    /// Configurations failing validation are never pulled.
+    fn validate_config(&self, _cfg: &TriggerConfiguration) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called once by the host before the plugin is used.
+    fn init(&mut self, _ctx: &PluginContext) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called once by the host when it stops. The plugin won't be used afterwards.
+    fn shutdown(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Asynchronous variant of [`TriggerPlugin`], driven natively by the trigger system.
+///
+/// See [`AsyncActionPlugin`](crate::AsyncActionPlugin) for the restrictions applying to
+/// dynamically loaded async plugins.
+#[async_trait]
+pub trait AsyncTriggerPlugin: Send + Sync {
+    fn get_type(&self) -> &str;
+    async fn pull_trigger(&self, cfg: &TriggerConfiguration) -> Result<Vec<Trigger>, Error>;
+
+    fn metadata(&self) -> PluginMetadata {
+        PluginMetadata::new(self.get_type())
+    }
+
+    /// See [`TriggerPlugin::validate_config`].
+    fn validate_config(&self, _cfg: &TriggerConfiguration) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// See [`TriggerPlugin::init`].
+    fn init(&mut self, _ctx: &PluginContext) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// See [`TriggerPlugin::shutdown`].
+    fn shutdown(&self) -> Result<(), Error> {
+        Ok(())
+    }
 }
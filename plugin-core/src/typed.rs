@@ -0,0 +1,294 @@
+use schemars::JsonSchema;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use protocol::{ActionManifest, ActionResult, Payload, Trigger, TriggerConfiguration};
+
+use crate::{Error, PluginContext, PluginMetadata};
+
+/// Action plugin executing actions from a typed configuration.
+///
+/// Deriving [`ActionPlugin`](derive@crate::ActionPlugin) implements the
+/// [`ActionPlugin`](trait@crate::ActionPlugin) trait on top of it: the derive decodes
+/// configurations, and fills the action type and the metadata in from the `#[plugin(...)]`
+/// attribute of the plugin.
+pub trait TypedActionPlugin: Send + Sync {
+    /// Configuration of the action, decoded from the rendered action configuration.
+    type Config: DeserializeOwned + JsonSchema;
+
+    fn execute(&self, config: Self::Config, manifest: &ActionManifest) -> Result<(), Error>;
+
+    /// Executes an action, reporting its result.
+    ///
+    /// See [`ActionPlugin::execute_with_result`](crate::ActionPlugin::execute_with_result).
+    fn execute_for_result(
+        &self,
+        config: Self::Config,
+        manifest: &ActionManifest,
+    ) -> Result<ActionResult, Error> {
+        self.execute(config, manifest)?;
+        Ok(ActionResult::default())
+    }
+
+    /// Checks an action configuration when a rule is created.
+    ///
+    /// The configuration is rendered from the action template of the rule with sample trigger
+    /// data, so it has the shape of the real configurations but not their values.
+    fn validate(&self, _config: &Self::Config) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// See [`ActionPlugin::init`](crate::ActionPlugin::init).
+    fn init(&mut self, _ctx: &PluginContext) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// See [`ActionPlugin::shutdown`](crate::ActionPlugin::shutdown).
+    fn shutdown(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Trigger plugin pulling typed trigger data from a typed configuration.
+///
+/// See [`TypedActionPlugin`]. Deriving [`TriggerPlugin`](derive@crate::TriggerPlugin) also
+/// encodes the trigger data.
+pub trait TypedTriggerPlugin: Send + Sync {
+    /// Configuration of the trigger, decoded from `TriggerConfiguration::data`.
+    type Config: DeserializeOwned + JsonSchema;
+
+    /// Data carried by the emitted triggers.
+    type Data: Serialize + JsonSchema;
+
+    /// Returns the data of the triggers to emit for a configuration.
+    fn pull(
+        &self,
+        config: Self::Config,
+        cfg: &TriggerConfiguration,
+    ) -> Result<Vec<Self::Data>, Error>;
+
+    /// See [`TriggerPlugin::validate_config`](crate::TriggerPlugin::validate_config).
+    fn validate(&self, _config: &Self::Config) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// See [`TriggerPlugin::init`](crate::TriggerPlugin::init).
+    fn init(&mut self, _ctx: &PluginContext) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// See [`TriggerPlugin::shutdown`](crate::TriggerPlugin::shutdown).
+    fn shutdown(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Glue called by the code generated by the plugin derives.
+pub mod derive {
+    use super::*;
+
+    fn decode<T: DeserializeOwned>(plugin_type: &str, payload: &Payload) -> Result<T, Error> {
+        payload.decode().map_err(|e| {
+            Error::invalid_config(format!("invalid {} config", plugin_type)).with_source(e)
+        })
+    }
+
+    pub fn action_metadata<P: TypedActionPlugin>(plugin_type: &str) -> PluginMetadata {
+        PluginMetadata::new(plugin_type).config_schema::<P::Config>()
+    }
+
+    pub fn validate_action<P: TypedActionPlugin>(
+        plugin: &P,
+        plugin_type: &str,
+        config: &Payload,
+    ) -> Result<(), Error> {
+        plugin.validate(&decode(plugin_type, config)?)
+    }
+
+    pub fn execute_action<P: TypedActionPlugin>(
+        plugin: &P,
+        plugin_type: &str,
+        manifest: ActionManifest,
+    ) -> Result<ActionResult, Error> {
+        let config = decode(plugin_type, &manifest.data)?;
+        plugin.execute_for_result(config, &manifest)
+    }
+
+    pub fn trigger_metadata<P: TypedTriggerPlugin>(plugin_type: &str) -> PluginMetadata {
+        PluginMetadata::new(plugin_type)
+            .config_schema::<P::Config>()
+            .data_schema::<P::Data>()
+    }
+
+    pub fn validate_trigger<P: TypedTriggerPlugin>(
+        plugin: &P,
+        plugin_type: &str,
+        cfg: &TriggerConfiguration,
+    ) -> Result<(), Error> {
+        plugin.validate(&decode(plugin_type, &cfg.data)?)
+    }
+
+    pub fn pull_trigger<P: TypedTriggerPlugin>(
+        plugin: &P,
+        plugin_type: &str,
+        cfg: &TriggerConfiguration,
+    ) -> Result<Vec<Trigger>, Error> {
+        let config = decode(plugin_type, &cfg.data)?;
+        plugin
+            .pull(config, cfg)?
+            .iter()
+            .map(|data| {
+                let data = Payload::encode(data).map_err(|e| {
+                    Error::permanent("failed to encode trigger data").with_source(e)
+                })?;
+                Ok(Trigger::new(
+                    cfg.rule.clone(),
+                    cfg.trigger_type.clone(),
+                    data,
+                ))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use protocol::{ActionManifest, ActionResult, Envelope, Payload, TriggerConfiguration};
+
+    use schemars::JsonSchema;
+
+    use serde::{Deserialize, Serialize};
+
+    use serde_json::json;
+
+    use crate::{ActionPlugin, Error, ErrorKind, TriggerPlugin};
+
+    use super::{TypedActionPlugin, TypedTriggerPlugin};
+
+    #[derive(Deserialize, JsonSchema)]
+    struct CountConfig {
+        count: u32,
+    }
+
+    #[derive(Deserialize, JsonSchema, Serialize)]
+    struct CountData {
+        index: u32,
+    }
+
+    #[derive(Default, TriggerPlugin)]
+    #[plugin(type = "count", description = "Emits a number of triggers.")]
+    struct CountTrigger;
+
+    impl TypedTriggerPlugin for CountTrigger {
+        type Config = CountConfig;
+        type Data = CountData;
+
+        fn pull(
+            &self,
+            config: CountConfig,
+            _cfg: &TriggerConfiguration,
+        ) -> Result<Vec<CountData>, Error> {
+            Ok((0..config.count).map(|index| CountData { index }).collect())
+        }
+
+        fn validate(&self, config: &CountConfig) -> Result<(), Error> {
+            if config.count == 0 {
+                return Err(Error::invalid_config("count must be positive"));
+            }
+            Ok(())
+        }
+    }
+
+    #[derive(Default, ActionPlugin)]
+    #[plugin(type = "record")]
+    struct RecordAction {
+        executed: Mutex<Vec<u32>>,
+    }
+
+    impl TypedActionPlugin for RecordAction {
+        type Config = CountConfig;
+
+        fn execute(&self, config: CountConfig, manifest: &ActionManifest) -> Result<(), Error> {
+            self.execute_for_result(config, manifest).map(|_| ())
+        }
+
+        fn execute_for_result(
+            &self,
+            config: CountConfig,
+            _manifest: &ActionManifest,
+        ) -> Result<ActionResult, Error> {
+            let mut executed = self.executed.lock().unwrap();
+            executed.push(config.count);
+            Ok(ActionResult::new(Payload::new(
+                json!({ "total": executed.len() }),
+            )))
+        }
+    }
+
+    fn trigger_config(data: serde_json::Value) -> TriggerConfiguration {
+        TriggerConfiguration {
+            id: 1,
+            rule: String::from("42"),
+            trigger_type: String::from("count"),
+            data: Payload::new(data),
+            schedule: Default::default(),
+        }
+    }
+
+    #[test]
+    fn typed_trigger() {
+        let plugin = CountTrigger;
+        assert_eq!(TriggerPlugin::get_type(&plugin), "count");
+
+        let metadata = TriggerPlugin::metadata(&plugin);
+        assert_eq!(metadata.name, "count");
+        assert_eq!(
+            metadata.description.as_deref(),
+            Some("Emits a number of triggers.")
+        );
+        assert_eq!(metadata.version.as_deref(), Some(env!("CARGO_PKG_VERSION")));
+        assert!(metadata.config_schema.unwrap()["properties"]["count"].is_object());
+        assert!(metadata.data_schema.unwrap()["properties"]["index"].is_object());
+
+        let triggers = plugin
+            .pull_trigger(&trigger_config(json!({"count": 2})))
+            .unwrap();
+        let data: Vec<serde_json::Value> =
+            triggers.iter().map(|t| t.data.as_value().clone()).collect();
+        assert_eq!(data, vec![json!({"index": 0}), json!({"index": 1})]);
+        assert_eq!(triggers[0].rule, "42");
+        assert_eq!(triggers[0].trigger_type, "count");
+
+        let err = TriggerPlugin::validate_config(&plugin, &trigger_config(json!({"count": 0})))
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidConfig);
+        let err = TriggerPlugin::validate_config(&plugin, &trigger_config(json!({}))).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidConfig);
+    }
+
+    #[test]
+    fn typed_action() {
+        let plugin = RecordAction::default();
+        assert_eq!(ActionPlugin::get_type(&plugin), "record");
+        assert!(ActionPlugin::metadata(&plugin).description.is_none());
+
+        assert!(ActionPlugin::validate_config(&plugin, &Payload::new(json!({"count": 3}))).is_ok());
+        let err = ActionPlugin::validate_config(&plugin, &Payload::new(json!({"count": "3"})))
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidConfig);
+
+        let manifest = ActionManifest {
+            rule: String::from("42"),
+            action_type: String::from("record"),
+            data: Payload::new(json!({"count": 3})),
+            envelope: Envelope::new(),
+        };
+        plugin.execute_action(manifest.clone()).unwrap();
+        let result = ActionPlugin::execute_with_result(&plugin, manifest).unwrap();
+        assert_eq!(result.data.as_value(), &json!({"total": 2}));
+        assert_eq!(*plugin.executed.lock().unwrap(), vec![3, 3]);
+    }
+}
@@ -0,0 +1,203 @@
+//! Derives of the plugin traits of `plugin-core`, for typed plugins.
+//!
+//! Use them through `plugin-core`, which re-exports them next to the traits they implement.
+
+use proc_macro::TokenStream;
+
+use proc_macro2::TokenStream as TokenStream2;
+
+use quote::quote;
+
+use syn::{parse_macro_input, DeriveInput, LitStr};
+
+/// Settings of a plugin, from its `#[plugin(...)]` attributes.
+struct PluginAttributes {
+    plugin_type: LitStr,
+    description: Option<LitStr>,
+    export: bool,
+}
+
+impl PluginAttributes {
+    fn parse(input: &DeriveInput) -> syn::Result<Self> {
+        let mut plugin_type = None;
+        let mut description = None;
+        let mut export = false;
+
+        for attr in input.attrs.iter().filter(|a| a.path().is_ident("plugin")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("type") {
+                    plugin_type = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("description") {
+                    description = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("export") {
+                    export = true;
+                } else {
+                    return Err(meta.error("unknown plugin attribute"));
+                }
+                Ok(())
+            })?;
+        }
+
+        let plugin_type = plugin_type.ok_or_else(|| {
+            syn::Error::new_spanned(
+                &input.ident,
+                "missing plugin type, add #[plugin(type = \"...\")]",
+            )
+        })?;
+
+        Ok(PluginAttributes {
+            plugin_type,
+            description,
+            export,
+        })
+    }
+
+    /// Returns the expression building the metadata of the plugin, from the base metadata
+    /// holding its schemas.
+    fn metadata(&self, base: TokenStream2) -> TokenStream2 {
+        let description = self
+            .description
+            .as_ref()
+            .map(|description| quote!(.description(#description)));
+        quote! {
+            #base.version(env!("CARGO_PKG_VERSION")) #description
+        }
+    }
+}
+
+/// Implements `ActionPlugin` for a `TypedActionPlugin`.
+///
+/// The action type is set with `#[plugin(type = "...")]`. The attribute also takes an optional
+/// `description`, and `export` to export the plugin from a library holding no other plugin.
+#[proc_macro_derive(ActionPlugin, attributes(plugin))]
+pub fn derive_action_plugin(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let attributes = match PluginAttributes::parse(&input) {
+        Ok(attributes) => attributes,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let plugin_type = &attributes.plugin_type;
+    let metadata =
+        attributes.metadata(quote!(::plugin_core::__derive::action_metadata::<Self>(#plugin_type)));
+    let export = if attributes.export {
+        quote!(::plugin_core::export!((#name), ());)
+    } else {
+        TokenStream2::new()
+    };
+
+    let expanded = quote! {
+        impl #impl_generics ::plugin_core::ActionPlugin for #name #ty_generics #where_clause {
+            fn execute_action(
+                &self,
+                manifest: ::plugin_core::protocol::ActionManifest,
+            ) -> ::std::result::Result<(), ::plugin_core::Error> {
+                ::plugin_core::__derive::execute_action(self, #plugin_type, manifest).map(|_| ())
+            }
+
+            fn get_type(&self) -> &str {
+                #plugin_type
+            }
+
+            fn execute_with_result(
+                &self,
+                manifest: ::plugin_core::protocol::ActionManifest,
+            ) -> ::std::result::Result<::plugin_core::protocol::ActionResult, ::plugin_core::Error> {
+                ::plugin_core::__derive::execute_action(self, #plugin_type, manifest)
+            }
+
+            fn metadata(&self) -> ::plugin_core::PluginMetadata {
+                #metadata
+            }
+
+            fn validate_config(
+                &self,
+                config: &::plugin_core::protocol::Payload,
+            ) -> ::std::result::Result<(), ::plugin_core::Error> {
+                ::plugin_core::__derive::validate_action(self, #plugin_type, config)
+            }
+
+            fn init(
+                &mut self,
+                ctx: &::plugin_core::PluginContext,
+            ) -> ::std::result::Result<(), ::plugin_core::Error> {
+                ::plugin_core::TypedActionPlugin::init(self, ctx)
+            }
+
+            fn shutdown(&self) -> ::std::result::Result<(), ::plugin_core::Error> {
+                ::plugin_core::TypedActionPlugin::shutdown(self)
+            }
+        }
+
+        #export
+    };
+    expanded.into()
+}
+
+/// Implements `TriggerPlugin` for a `TypedTriggerPlugin`.
+///
+/// Takes the same `#[plugin(...)]` attribute as [`ActionPlugin`](derive@ActionPlugin).
+#[proc_macro_derive(TriggerPlugin, attributes(plugin))]
+pub fn derive_trigger_plugin(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let attributes = match PluginAttributes::parse(&input) {
+        Ok(attributes) => attributes,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let plugin_type = &attributes.plugin_type;
+    let metadata = attributes
+        .metadata(quote!(::plugin_core::__derive::trigger_metadata::<Self>(#plugin_type)));
+    let export = if attributes.export {
+        quote!(::plugin_core::export!((), (#name));)
+    } else {
+        TokenStream2::new()
+    };
+
+    let expanded = quote! {
+        impl #impl_generics ::plugin_core::TriggerPlugin for #name #ty_generics #where_clause {
+            fn get_type(&self) -> &str {
+                #plugin_type
+            }
+
+            fn pull_trigger(
+                &self,
+                cfg: &::plugin_core::protocol::TriggerConfiguration,
+            ) -> ::std::result::Result<
+                ::std::vec::Vec<::plugin_core::protocol::Trigger>,
+                ::plugin_core::Error,
+            > {
+                ::plugin_core::__derive::pull_trigger(self, #plugin_type, cfg)
+            }
+
+            fn metadata(&self) -> ::plugin_core::PluginMetadata {
+                #metadata
+            }
+
+            fn validate_config(
+                &self,
+                cfg: &::plugin_core::protocol::TriggerConfiguration,
+            ) -> ::std::result::Result<(), ::plugin_core::Error> {
+                ::plugin_core::__derive::validate_trigger(self, #plugin_type, cfg)
+            }
+
+            fn init(
+                &mut self,
+                ctx: &::plugin_core::PluginContext,
+            ) -> ::std::result::Result<(), ::plugin_core::Error> {
+                ::plugin_core::TypedTriggerPlugin::init(self, ctx)
+            }
+
+            fn shutdown(&self) -> ::std::result::Result<(), ::plugin_core::Error> {
+                ::plugin_core::TypedTriggerPlugin::shutdown(self)
+            }
+        }
+
+        #export
+    };
+    expanded.into()
+}
@@ -0,0 +1,372 @@
+use std::collections::HashMap;
+use std::panic;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use async_trait::async_trait;
+
+use plugin_core::{
+    logging, ActionPlugin, AsyncActionPlugin, AsyncTriggerPlugin, Error, PluginMetadata,
+    PushTriggerPlugin, TriggerPlugin, TriggerSink,
+};
+
+use protocol::{ActionManifest, ActionResult, Payload, Trigger, TriggerConfiguration};
+
+use tokio::task::JoinError;
+
+/// Returns the result of a call run on the blocking thread pool.
+///
+/// Panics are resumed, for callers to handle them like the panics of async plugins.
+fn join<T>(result: Result<Result<T, Error>, JoinError>) -> Result<T, Error> {
+    match result {
+        Ok(result) => result,
+        Err(e) if e.is_panic() => panic::resume_unwind(e.into_panic()),
+        Err(e) => Err(Error::permanent("plugin task failed").with_source(e)),
+    }
+}
+
+/// Number of calls into a plugin still running after their caller gave up on them, by rule.
+#[derive(Clone, Default)]
+struct AbandonedCalls(Arc<Mutex<HashMap<String, usize>>>);
+
+impl AbandonedCalls {
+    fn calls(&self) -> MutexGuard<'_, HashMap<String, usize>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn running(&self, rule: &str) -> usize {
+        self.calls().get(rule).copied().unwrap_or(0)
+    }
+
+    fn add(&self, rule: &str) {
+        *self.calls().entry(String::from(rule)).or_insert(0) += 1;
+    }
+
+    fn remove(&self, rule: &str) {
+        let mut calls = self.calls();
+        if let Some(running) = calls.get_mut(rule) {
+            *running -= 1;
+            if *running == 0 {
+                calls.remove(rule);
+            }
+        }
+    }
+}
+
+/// Whether a call on the blocking thread pool is done, and whether its caller gave up on it.
+#[derive(Default)]
+struct CallState {
+    finished: bool,
+    abandoned: bool,
+}
+
+/// Held by a call on the blocking thread pool and by its caller. Whichever is dropped first
+/// decides whether the call was abandoned: calls still running when their caller is dropped,
+/// e.g. because they timed out, are counted until they return.
+struct CallGuard {
+    state: Arc<Mutex<CallState>>,
+    abandoned: AbandonedCalls,
+    rule: String,
+    caller: bool,
+}
+
+impl Drop for CallGuard {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if self.caller {
+            if !state.finished {
+                state.abandoned = true;
+                self.abandoned.add(&self.rule);
+            }
+        } else {
+            state.finished = true;
+            if state.abandoned {
+                self.abandoned.remove(&self.rule);
+            }
+        }
+    }
+}
+
+/// Runs a call into a synchronous plugin on the blocking thread pool.
+///
+/// Threads of the pool can't be interrupted, so a call keeps running after its caller gave up
+/// on it. New calls for the same rule are refused with a transient error until its abandoned
+/// calls return, so a call hung on a rule holds up a single thread of the pool, and the other
+/// rules of the plugin keep running.
+async fn run_blocking<T, F>(
+    plugin_type: &str,
+    rule: &str,
+    abandoned: &AbandonedCalls,
+    call: F,
+) -> Result<T, Error>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, Error> + Send + 'static,
+{
+    let running = abandoned.running(rule);
+    if running > 0 {
+        return Err(Error::transient(format!(
+            "[{}] {} abandoned call(s) still running for rule {}",
+            plugin_type, running, rule
+        )));
+    }
+
+    let state = Arc::new(Mutex::new(CallState::default()));
+    let _caller = CallGuard {
+        state: state.clone(),
+        abandoned: abandoned.clone(),
+        rule: String::from(rule),
+        caller: true,
+    };
+    let callee = CallGuard {
+        state,
+        abandoned: abandoned.clone(),
+        rule: String::from(rule),
+        caller: false,
+    };
+
+    join(
+        tokio::task::spawn_blocking(move || {
+            let _callee = callee;
+            call()
+        })
+        .await,
+    )
+}
+
+/// Drives a synchronous action plugin on the blocking thread pool of the runtime.
+///
+/// The wrapped plugin must already be initialized.
+pub(crate) struct BlockingAction {
+    plugin: Arc<Box<dyn ActionPlugin>>,
+
+    // Calls still running after their caller gave up on them.
+    abandoned: AbandonedCalls,
+}
+
+impl BlockingAction {
+    pub fn wrap(plugin: Arc<Box<dyn ActionPlugin>>) -> Arc<Box<dyn AsyncActionPlugin>> {
+        Arc::new(Box::new(BlockingAction {
+            plugin,
+            abandoned: AbandonedCalls::default(),
+        }))
+    }
+}
+
+#[async_trait]
+impl AsyncActionPlugin for BlockingAction {
+    async fn execute_action(&self, manifest: ActionManifest) -> Result<(), Error> {
+        self.execute_with_result(manifest).await.map(|_| ())
+    }
+
+    fn get_type(&self) -> &str {
+        self.plugin.get_type()
+    }
+
+    async fn execute_with_result(&self, manifest: ActionManifest) -> Result<ActionResult, Error> {
+        let plugin = self.plugin.clone();
+        let rule = manifest.rule.clone();
+        run_blocking(self.get_type(), &rule, &self.abandoned, move || {
+            let rule = manifest.rule.clone();
+            logging::scope(plugin.get_type(), Some(&rule), || {
+                plugin.execute_with_result(manifest)
+            })
+        })
+        .await
+    }
+
+    fn metadata(&self) -> PluginMetadata {
+        self.plugin.metadata()
+    }
+
+    fn validate_config(&self, config: &Payload) -> Result<(), Error> {
+        logging::scope(self.get_type(), None, || {
+            self.plugin.validate_config(config)
+        })
+    }
+
+    fn shutdown(&self) -> Result<(), Error> {
+        logging::scope(self.get_type(), None, || self.plugin.shutdown())
+    }
+}
+
+/// Drives a synchronous trigger plugin on the blocking thread pool of the runtime.
+///
+/// The wrapped plugin must already be initialized.
+pub(crate) struct BlockingTrigger {
+    plugin: Arc<Box<dyn TriggerPlugin>>,
+
+    // Calls still running after their caller gave up on them.
+    abandoned: AbandonedCalls,
+}
+
+impl BlockingTrigger {
+    pub fn wrap(plugin: Arc<Box<dyn TriggerPlugin>>) -> Arc<Box<dyn AsyncTriggerPlugin>> {
+        Arc::new(Box::new(BlockingTrigger {
+            plugin,
+            abandoned: AbandonedCalls::default(),
+        }))
+    }
+}
+
+#[async_trait]
+impl AsyncTriggerPlugin for BlockingTrigger {
+    fn get_type(&self) -> &str {
+        self.plugin.get_type()
+    }
+
+    async fn pull_trigger(&self, cfg: &TriggerConfiguration) -> Result<Vec<Trigger>, Error> {
+        let plugin = self.plugin.clone();
+        let rule = cfg.rule.clone();
+        let cfg = cfg.clone();
+        run_blocking(self.get_type(), &rule, &self.abandoned, move || {
+            logging::scope(plugin.get_type(), Some(&cfg.rule), || {
+                plugin.pull_trigger(&cfg)
+            })
+        })
+        .await
+    }
+
+    fn metadata(&self) -> PluginMetadata {
+        self.plugin.metadata()
+    }
+
+    fn validate_config(&self, cfg: &TriggerConfiguration) -> Result<(), Error> {
+        logging::scope(self.get_type(), Some(&cfg.rule), || {
+            self.plugin.validate_config(cfg)
+        })
+    }
+
+    fn shutdown(&self) -> Result<(), Error> {
+        logging::scope(self.get_type(), None, || self.plugin.shutdown())
+    }
+}
+
+/// Tags the records logged by an async action plugin with the rule being processed.
+pub(crate) struct ScopedAction {
+    plugin: Arc<Box<dyn AsyncActionPlugin>>,
+}
+
+impl ScopedAction {
+    pub fn wrap(plugin: Arc<Box<dyn AsyncActionPlugin>>) -> Arc<Box<dyn AsyncActionPlugin>> {
+        Arc::new(Box::new(ScopedAction { plugin }))
+    }
+}
+
+#[async_trait]
+impl AsyncActionPlugin for ScopedAction {
+    async fn execute_action(&self, manifest: ActionManifest) -> Result<(), Error> {
+        self.execute_with_result(manifest).await.map(|_| ())
+    }
+
+    fn get_type(&self) -> &str {
+        self.plugin.get_type()
+    }
+
+    async fn execute_with_result(&self, manifest: ActionManifest) -> Result<ActionResult, Error> {
+        let rule = manifest.rule.clone();
+        logging::scoped(
+            self.get_type(),
+            Some(&rule),
+            self.plugin.execute_with_result(manifest),
+        )
+        .await
+    }
+
+    fn metadata(&self) -> PluginMetadata {
+        self.plugin.metadata()
+    }
+
+    fn validate_config(&self, config: &Payload) -> Result<(), Error> {
+        logging::scope(self.get_type(), None, || {
+            self.plugin.validate_config(config)
+        })
+    }
+
+    fn shutdown(&self) -> Result<(), Error> {
+        logging::scope(self.get_type(), None, || self.plugin.shutdown())
+    }
+}
+
+/// Tags the records logged by an async trigger plugin with the rule being processed.
+pub(crate) struct ScopedTrigger {
+    plugin: Arc<Box<dyn AsyncTriggerPlugin>>,
+}
+
+impl ScopedTrigger {
+    pub fn wrap(plugin: Arc<Box<dyn AsyncTriggerPlugin>>) -> Arc<Box<dyn AsyncTriggerPlugin>> {
+        Arc::new(Box::new(ScopedTrigger { plugin }))
+    }
+}
+
+#[async_trait]
+impl AsyncTriggerPlugin for ScopedTrigger {
+    fn get_type(&self) -> &str {
+        self.plugin.get_type()
+    }
+
+    async fn pull_trigger(&self, cfg: &TriggerConfiguration) -> Result<Vec<Trigger>, Error> {
+        logging::scoped(
+            self.get_type(),
+            Some(&cfg.rule),
+            self.plugin.pull_trigger(cfg),
+        )
+        .await
+    }
+
+    fn metadata(&self) -> PluginMetadata {
+        self.plugin.metadata()
+    }
+
+    fn validate_config(&self, cfg: &TriggerConfiguration) -> Result<(), Error> {
+        logging::scope(self.get_type(), Some(&cfg.rule), || {
+            self.plugin.validate_config(cfg)
+        })
+    }
+
+    fn shutdown(&self) -> Result<(), Error> {
+        logging::scope(self.get_type(), None, || self.plugin.shutdown())
+    }
+}
+
+/// Tags the records logged by a push trigger plugin while it is started or stopped.
+///
+/// Records logged from the threads of the plugin are only tagged with its library.
+pub(crate) struct ScopedPushTrigger {
+    plugin: Arc<Box<dyn PushTriggerPlugin>>,
+}
+
+impl ScopedPushTrigger {
+    pub fn wrap(plugin: Arc<Box<dyn PushTriggerPlugin>>) -> Arc<Box<dyn PushTriggerPlugin>> {
+        Arc::new(Box::new(ScopedPushTrigger { plugin }))
+    }
+}
+
+impl PushTriggerPlugin for ScopedPushTrigger {
+    fn get_type(&self) -> &str {
+        self.plugin.get_type()
+    }
+
+    fn start(&self, cfg: &TriggerConfiguration, sink: Arc<dyn TriggerSink>) -> Result<(), Error> {
+        logging::scope(self.get_type(), Some(&cfg.rule), || {
+            self.plugin.start(cfg, sink)
+        })
+    }
+
+    fn stop(&self, cfg: &TriggerConfiguration) -> Result<(), Error> {
+        logging::scope(self.get_type(), Some(&cfg.rule), || self.plugin.stop(cfg))
+    }
+
+    fn metadata(&self) -> PluginMetadata {
+        self.plugin.metadata()
+    }
+
+    fn validate_config(&self, cfg: &TriggerConfiguration) -> Result<(), Error> {
+        logging::scope(self.get_type(), Some(&cfg.rule), || {
+            self.plugin.validate_config(cfg)
+        })
+    }
+
+    fn shutdown(&self) -> Result<(), Error> {
+        logging::scope(self.get_type(), None, || self.plugin.shutdown())
+    }
+}
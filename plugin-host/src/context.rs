@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use plugin_core::{Error as PlugError, PluginContext, PluginState, StateStore};
+
+use protocol::Payload;
+
+use toolkit::db::sled::{KvStore, SledStore};
+
+/// Plugin state store backed by a tree of the node's sled database.
+pub(crate) struct SledStateStore {
+    store: KvStore,
+}
+
+impl StateStore for SledStateStore {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, PlugError> {
+        self.store
+            .get(key)
+            .map_err(|e| PlugError::transient("failed to read plugin state").with_source(e))
+    }
+
+    fn set(&self, key: &str, value: &[u8]) -> Result<(), PlugError> {
+        self.store
+            .set(key, value)
+            .map_err(|e| PlugError::transient("failed to write plugin state").with_source(e))
+    }
+
+    fn remove(&self, key: &str) -> Result<(), PlugError> {
+        self.store
+            .remove(key)
+            .map_err(|e| PlugError::transient("failed to remove plugin state").with_source(e))
+    }
+
+    fn flush(&self) -> Result<(), PlugError> {
+        self.store
+            .flush()
+            .map_err(|e| PlugError::transient("failed to flush plugin state").with_source(e))
+    }
+}
+
+/// Builds the contexts plugins are initialized with.
+#[derive(Default)]
+pub(crate) struct ContextProvider {
+    settings: HashMap<String, Payload>,
+    state_store: Option<Arc<SledStore>>,
+
+    // Handed-out states, by plugin type.
+    states: HashMap<String, PluginState>,
+}
+
+impl ContextProvider {
+    pub fn new(settings: HashMap<String, Payload>, state_store: Option<Arc<SledStore>>) -> Self {
+        ContextProvider {
+            settings,
+            state_store,
+            states: HashMap::new(),
+        }
+    }
+
+    pub fn context(
+        &mut self,
+        plugin_type: &str,
+    ) -> Result<PluginContext, toolkit::db::sled::Error> {
+        let state = match self.states.get(plugin_type) {
+            Some(state) => state.clone(),
+            None => {
+                let state = match &self.state_store {
+                    Some(db) => PluginState::new(Arc::new(SledStateStore {
+                        store: db.kv(&format!("plugin_state.{}", plugin_type))?,
+                    })),
+                    None => PluginState::default(),
+                };
+                self.states.insert(String::from(plugin_type), state.clone());
+                state
+            }
+        };
+
+        Ok(PluginContext::new(
+            self.settings.get(plugin_type).cloned().unwrap_or_default(),
+            state,
+        ))
+    }
+
+    /// Flushes the state of every plugin.
+    pub fn flush(&self) {
+        for (plugin_type, state) in self.states.iter() {
+            if let Err(e) = state.flush() {
+                log::error!("failed to flush state of plugin [{}]: {}", plugin_type, e);
+            }
+        }
+    }
+}
@@ -1,13 +1,43 @@
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
+use std::future::Future;
 use std::io;
+use std::mem::ManuallyDrop;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::SystemTime;
 
 use libloading::Library;
 
-use snafu::{ensure, ResultExt, Snafu};
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
 
-use plugin_core::{ActionPlugin, Error as PlugError, Plugin, TriggerPlugin, PLUGIN_INIT_SYMBOL};
+use plugin_core::logging::{self, PluginLogger};
+use plugin_core::{
+    ActionPlugin, AsyncActionPlugin, AsyncTriggerPlugin, Error as PlugError, PluginDeclaration,
+    PluginMetadata, PushTriggerPlugin, TriggerPlugin, ABI_VERSION, CORE_VERSION,
+    PLUGIN_DECLARATION_SYMBOL, RUSTC_VERSION,
+};
+
+use protocol::rule::RenderError;
+use protocol::{Payload, Rule, TriggerConfiguration};
+
+use serde::Serialize;
+
+use tempfile::TempDir;
+
+use toolkit::db::sled::SledStore;
+
+use crate::adapter::{
+    BlockingAction, BlockingTrigger, ScopedAction, ScopedPushTrigger, ScopedTrigger,
+};
+use crate::context::ContextProvider;
+use crate::manifest::{self, AllowedPlugin, ManifestError, PluginManifest};
+use crate::precedence::{AnyPlugin, InstalledPlugin, PluginSource, PluginStatus, Precedence};
+use crate::sample;
+use crate::subprocess::{ProcessClient, ProcessPlugin, DEFAULT_CALL_TIMEOUT};
+use crate::wasm::{WasmLimits, WasmModule, WasmPlugin};
+use crate::watchdog::{CallTimeouts, UnhealthyPlugin, Watchdog};
 
 #[cfg(unix)]
 const PLUGIN_EXTENSION: &str = "so";
@@ -15,135 +45,1462 @@ const PLUGIN_EXTENSION: &str = "so";
 #[cfg(windows)]
 const PLUGIN_EXTENSION: &str = "dll";
 
-type PluginLoadFn = fn() -> Box<Plugin>;
+pub(crate) const WASM_EXTENSION: &str = "wasm";
 
 #[derive(Debug, Snafu)]
 pub enum Error {
-    LoadLibrary { source: libloading::Error },
-    OpenSearchPath { source: io::Error },
-    InternalPlugin { source: PlugError },
-    MissingSymbol { source: libloading::Error },
+    LoadLibrary {
+        source: libloading::Error,
+    },
+    #[snafu(display("failed to spawn plugin process {}: {}", command.display(), source))]
+    SpawnProcess {
+        command: PathBuf,
+        source: io::Error,
+    },
+    #[snafu(display("plugin process {} failed to describe itself: {}", command.display(), source))]
+    DescribeProcess {
+        command: PathBuf,
+        source: PlugError,
+    },
+    #[snafu(display("failed to load WASM plugin {}: {}", path.display(), source))]
+    LoadWasm {
+        path: PathBuf,
+        source: PlugError,
+    },
+    #[snafu(display("refused plugin library {}: {}", path.display(), source))]
+    UntrustedLibrary {
+        path: PathBuf,
+        source: ManifestError,
+    },
+    #[snafu(display("failed to copy plugin library: {}", source))]
+    ShadowCopy {
+        source: io::Error,
+    },
+    #[snafu(display("failed to open search path: {}", source))]
+    OpenSearchPath {
+        source: io::Error,
+    },
+    InternalPlugin {
+        source: PlugError,
+    },
+    MissingSymbol {
+        source: libloading::Error,
+    },
+    #[snafu(display(
+        "plugin ABI version {} is incompatible with host ABI version {}",
+        plugin,
+        host
+    ))]
+    IncompatibleAbi {
+        host: u32,
+        plugin: u32,
+    },
+    #[snafu(display("plugin was built with [{}], host was built with [{}]", plugin, host))]
+    IncompatibleCompiler {
+        host: String,
+        plugin: String,
+    },
+    #[snafu(display(
+        "plugin was built against plugin-core {}, host uses plugin-core {}",
+        plugin,
+        host
+    ))]
+    IncompatibleCore {
+        host: String,
+        plugin: String,
+    },
+    #[snafu(display("plugin initialization failed"))]
+    PluginInit,
+    #[snafu(display("failed to initialize plugin [{}]: {}", plugin_type, source))]
+    InitPlugin {
+        plugin_type: String,
+        source: PlugError,
+    },
+    #[snafu(display("plugin [{}] is already in use, and can't be initialized", plugin_type))]
+    SharedPlugin {
+        plugin_type: String,
+    },
+    #[snafu(display("failed to open state of plugin [{}]: {}", plugin_type, source))]
+    OpenState {
+        plugin_type: String,
+        source: toolkit::db::sled::Error,
+    },
+    #[snafu(display("no plugin handles type [{}]", plugin_type))]
+    UnknownPluginType {
+        plugin_type: String,
+    },
+    #[snafu(display("invalid [{}] config: {}", plugin_type, source))]
+    InvalidConfig {
+        plugin_type: String,
+        source: PlugError,
+    },
+    #[snafu(display("invalid [{}] config template: {}", plugin_type, source))]
+    InvalidTemplate {
+        plugin_type: String,
+        source: RenderError,
+    },
+    #[snafu(display("search path doesn't exist"))]
     SearchPathDoesNotExist,
+    #[snafu(display("search path is a file"))]
     SearchPathIsAFile,
 }
 
 type Result<T> = std::result::Result<T, Error>;
 
-struct PluginHandle {
-    plugin: Plugin,
-    _library: Library,
-    _path: PathBuf,
+/// Ensures a plugin declaration was built for this host.
+///
+/// The ABI version is checked first, as the rest of the declaration can't be trusted
+/// if it doesn't match.
+fn check_abi_version(abi_version: u32) -> Result<()> {
+    ensure!(
+        abi_version == ABI_VERSION,
+        IncompatibleAbiSnafu {
+            host: ABI_VERSION,
+            plugin: abi_version
+        }
+    );
+    Ok(())
+}
+
+pub(crate) fn check_declaration(declaration: &PluginDeclaration) -> Result<()> {
+    check_abi_version(declaration.abi_version)?;
+
+    let rustc_version = unsafe { declaration.rustc_version() };
+    ensure!(
+        rustc_version == RUSTC_VERSION,
+        IncompatibleCompilerSnafu {
+            host: RUSTC_VERSION,
+            plugin: rustc_version.as_ref()
+        }
+    );
+
+    let core_version = unsafe { declaration.core_version() };
+    ensure!(
+        core_version == CORE_VERSION,
+        IncompatibleCoreSnafu {
+            host: CORE_VERSION,
+            plugin: core_version.as_ref()
+        }
+    );
+
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginKind {
+    Action,
+    Trigger,
+    PushTrigger,
+}
+
+/// Description of a plugin installed in the host.
+#[derive(Clone, Debug, Serialize)]
+pub struct PluginInfo {
+    /// Type of the plugin, which rules and manifests refer to.
+    pub plugin_type: String,
+
+    pub kind: PluginKind,
+    pub metadata: PluginMetadata,
+
+    /// Library or executable the plugin was loaded from. `None` for in-memory plugins.
+    pub library: Option<PathBuf>,
+
+    pub source: PluginSource,
+
+    #[serde(flatten)]
+    pub status: PluginStatus,
+}
+
+/// Search path, library or WASM module of the search paths, or plugin process which failed to
+/// load.
+#[derive(Clone, Debug, Serialize)]
+pub struct RejectedLibrary {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+/// Outcome of the discovery of plugins.
+#[derive(Clone, Debug, Serialize)]
+pub struct LoadReport {
+    /// Every installed plugin, including the shadowed ones.
+    pub plugins: Vec<PluginInfo>,
+
+    pub rejected: Vec<RejectedLibrary>,
+}
+
+/// Health of a plugin process.
+#[derive(Clone, Debug, Serialize)]
+pub struct ProcessPluginHealth {
+    pub command: PathBuf,
+    pub plugin_type: String,
+
+    /// Number of times the process was restarted successfully after failing.
+    pub restarts: u64,
+
+    /// Why the last health check failed. `None` when the plugin is healthy.
+    pub error: Option<String>,
+}
+
+/// Initializes freshly loaded plugins, which aren't shared yet.
+macro_rules! init_plugins {
+    ($plugins:expr, $contexts:expr) => {
+        for plugin in $plugins.iter_mut() {
+            let plugin_type = String::from(plugin.get_type());
+            let ctx = $contexts.context(&plugin_type).context(OpenStateSnafu {
+                plugin_type: &plugin_type,
+            })?;
+            let plugin = Arc::get_mut(plugin).context(SharedPluginSnafu {
+                plugin_type: &plugin_type,
+            })?;
+            logging::scope(&plugin_type, None, || plugin.init(&ctx))
+                .context(InitPluginSnafu { plugin_type })?;
+        }
+    };
+}
+
+/// Calls the shutdown hook of plugins. Failures are logged, and don't prevent the remaining
+/// plugins from shutting down.
+fn shutdown_plugins(
+    actions: &[Arc<Box<dyn AsyncActionPlugin>>],
+    triggers: &[Arc<Box<dyn AsyncTriggerPlugin>>],
+    push_triggers: &[Arc<Box<dyn PushTriggerPlugin>>],
+) {
+    for action_plug in actions.iter() {
+        if let Err(e) = action_plug.shutdown() {
+            log::error!(
+                "failed to shut down plugin [{}]: {}",
+                action_plug.get_type(),
+                e
+            );
+        }
+    }
+
+    for trigger_plug in triggers.iter() {
+        if let Err(e) = trigger_plug.shutdown() {
+            log::error!(
+                "failed to shut down plugin [{}]: {}",
+                trigger_plug.get_type(),
+                e
+            );
+        }
+    }
+
+    for trigger_plug in push_triggers.iter() {
+        if let Err(e) = trigger_plug.shutdown() {
+            log::error!(
+                "failed to shut down plugin [{}]: {}",
+                trigger_plug.get_type(),
+                e
+            );
+        }
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Returns when a library or its manifest was last modified.
+fn library_modified(path: &Path) -> Option<SystemTime> {
+    modified_time(path).max(modified_time(&PluginManifest::path(path)))
+}
+
+fn read_search_path(path: &Path) -> Result<fs::ReadDir> {
+    ensure!(path.exists(), SearchPathDoesNotExistSnafu);
+    ensure!(path.is_dir(), SearchPathIsAFileSnafu);
+    fs::read_dir(path).context(OpenSearchPathSnafu)
+}
+
+fn display_origin(origin: Option<&Path>) -> String {
+    origin
+        .map(|p| p.display().to_string())
+        .unwrap_or_else(|| String::from("memory"))
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Copy of a library, loaded instead of the library itself. The copy is deleted when dropped.
+/// See `PluginHost::shadow_copy`.
+pub(crate) struct ShadowCopy {
+    path: PathBuf,
+}
+
+impl Drop for ShadowCopy {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            log::warn!(
+                "failed to delete library copy {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+pub(crate) struct PluginHandle {
+    actions: Vec<Arc<Box<dyn AsyncActionPlugin>>>,
+    triggers: Vec<Arc<Box<dyn AsyncTriggerPlugin>>>,
+    push_triggers: Vec<Arc<Box<dyn PushTriggerPlugin>>>,
+
+    // Libraries are never unloaded: code of a library may still run after its plugins are
+    // dropped (e.g. threads spawned by the plugins, or thread-local destructors). Only their
+    // copy is deleted. `None` for WASM modules.
+    _library: Option<ManuallyDrop<Library>>,
+    _shadow_copy: Option<ShadowCopy>,
+    path: PathBuf,
+
+    // Modification time of the library when it was loaded.
+    modified: Option<SystemTime>,
+
+    // Whether the library was added with `add_plugin` rather than found in a search path. Such
+    // libraries are kept when the search paths are reloaded.
+    added: bool,
+}
+
+/// Library which failed to load.
+pub(crate) struct Rejection {
+    // Modification time of the library at the time.
+    pub modified: Option<SystemTime>,
+    pub error: String,
+}
+
+/// Loggers handed to the plugin libraries, by library name.
+static PLUGIN_LOGGERS: Mutex<BTreeMap<String, &'static PluginLogger>> = Mutex::new(BTreeMap::new());
+
+/// Returns the logger of a library. Libraries hold on to their logger for as long as they stay
+/// loaded, so loggers are leaked, once per library name: reloads of a library share its logger.
+fn plugin_logger(library_name: String) -> &'static PluginLogger {
+    let mut loggers = PLUGIN_LOGGERS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    loggers
+        .entry(library_name)
+        .or_insert_with_key(|name| Box::leak(Box::new(PluginLogger::new(name.clone()))))
 }
 
 impl PluginHandle {
-    pub fn load<P: AsRef<Path>>(library_path: P) -> Result<PluginHandle> {
-        let library = Library::new(library_path.as_ref()).context(LoadLibrarySnafu)?;
+    /// Loads the library at `library_path` from a copy of it. The plugins of the library are
+    /// only initialized once they match its manifest, if any.
+    pub fn load<P: AsRef<Path>>(
+        library_path: P,
+        shadow_copy: ShadowCopy,
+        manifest: Option<&PluginManifest>,
+        contexts: &mut ContextProvider,
+    ) -> Result<PluginHandle> {
+        let modified = library_modified(library_path.as_ref());
+        let library = ManuallyDrop::new(Library::new(&shadow_copy.path).context(LoadLibrarySnafu)?);
 
-        let plugin_loader: libloading::Symbol<PluginLoadFn> = unsafe {
-            library
-                .get(PLUGIN_INIT_SYMBOL.as_bytes())
+        let declaration_ptr: *mut PluginDeclaration = unsafe {
+            *library
+                .get::<*mut PluginDeclaration>(PLUGIN_DECLARATION_SYMBOL.as_bytes())
                 .context(MissingSymbolSnafu)?
         };
 
-        let plugin_box = plugin_loader();
+        // Declarations of other ABI versions may have another layout, so only their leading
+        // ABI version is read until it is known to match.
+        check_abi_version(unsafe { declaration_ptr.cast::<u32>().read() })?;
+        let declaration = unsafe { declaration_ptr.read() };
+        check_declaration(&declaration)?;
+
+        // Forward the records of the plugin to our logger.
+        let library_name = library_path
+            .as_ref()
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        (declaration.set_logger)(plugin_logger(library_name), log::max_level());
+
+        let plugin_ptr = unsafe { (declaration.init)() };
+        ensure!(!plugin_ptr.is_null(), PluginInitSnafu);
+        let mut plugin = unsafe { Box::from_raw(plugin_ptr) };
+
+        if let Some(manifest) = manifest {
+            let types = plugin
+                .actions
+                .iter()
+                .map(|p| p.get_type())
+                .chain(plugin.triggers.iter().map(|p| p.get_type()))
+                .chain(plugin.async_actions.iter().map(|p| p.get_type()))
+                .chain(plugin.async_triggers.iter().map(|p| p.get_type()))
+                .chain(plugin.push_triggers.iter().map(|p| p.get_type()));
+            manifest
+                .verify_types(types)
+                .context(UntrustedLibrarySnafu {
+                    path: library_path.as_ref(),
+                })?;
+        }
+
+        init_plugins!(plugin.actions, contexts);
+        init_plugins!(plugin.triggers, contexts);
+        init_plugins!(plugin.async_actions, contexts);
+        init_plugins!(plugin.async_triggers, contexts);
+        init_plugins!(plugin.push_triggers, contexts);
+
+        let mut actions: Vec<Arc<Box<dyn AsyncActionPlugin>>> = plugin
+            .actions
+            .into_iter()
+            .map(BlockingAction::wrap)
+            .collect();
+        actions.extend(plugin.async_actions.into_iter().map(ScopedAction::wrap));
+
+        let mut triggers: Vec<Arc<Box<dyn AsyncTriggerPlugin>>> = plugin
+            .triggers
+            .into_iter()
+            .map(BlockingTrigger::wrap)
+            .collect();
+        triggers.extend(plugin.async_triggers.into_iter().map(ScopedTrigger::wrap));
 
         Ok(PluginHandle {
-            plugin: *plugin_box,
-            _library: library,
-            _path: PathBuf::from(library_path.as_ref()),
+            actions,
+            triggers,
+            push_triggers: plugin
+                .push_triggers
+                .into_iter()
+                .map(ScopedPushTrigger::wrap)
+                .collect(),
+            _library: Some(library),
+            _shadow_copy: Some(shadow_copy),
+            path: PathBuf::from(library_path.as_ref()),
+            modified,
+            added: false,
         })
     }
+
+    /// Loads the plugin implemented by a WASM module, read from `module_path`. Like libraries,
+    /// the plugin is only initialized once it matches the manifest of the module, if any.
+    pub fn load_wasm(
+        module_path: &Path,
+        data: &[u8],
+        limits: &WasmLimits,
+        manifest: Option<&PluginManifest>,
+        contexts: &mut ContextProvider,
+    ) -> Result<PluginHandle> {
+        let modified = library_modified(module_path);
+        let module = WasmModule::load(module_path, data, limits)
+            .context(LoadWasmSnafu { path: module_path })?;
+        let description = module
+            .describe()
+            .context(LoadWasmSnafu { path: module_path })?;
+
+        if let Some(manifest) = manifest {
+            manifest
+                .verify_types(std::iter::once(description.plugin_type.as_str()))
+                .context(UntrustedLibrarySnafu { path: module_path })?;
+        }
+
+        let ctx = contexts
+            .context(&description.plugin_type)
+            .context(OpenStateSnafu {
+                plugin_type: &description.plugin_type,
+            })?;
+        let plugin = WasmPlugin::new(module, description, ctx)
+            .context(LoadWasmSnafu { path: module_path })?;
+
+        let mut handle = PluginHandle {
+            actions: Vec::new(),
+            triggers: Vec::new(),
+            push_triggers: Vec::new(),
+            _library: None,
+            _shadow_copy: None,
+            path: PathBuf::from(module_path),
+            modified,
+            added: false,
+        };
+        match plugin {
+            WasmPlugin::Action(action) => handle
+                .actions
+                .push(BlockingAction::wrap(Arc::new(Box::new(action)))),
+            WasmPlugin::Trigger(trigger) => handle
+                .triggers
+                .push(BlockingTrigger::wrap(Arc::new(Box::new(trigger)))),
+        }
+
+        Ok(handle)
+    }
+
+    /// Returns whether plugins of the library are still referenced outside of the host.
+    fn in_use(&self) -> bool {
+        self.actions.iter().any(|p| Arc::strong_count(p) > 1)
+            || self.triggers.iter().any(|p| Arc::strong_count(p) > 1)
+            || self.push_triggers.iter().any(|p| Arc::strong_count(p) > 1)
+    }
+
+    fn shutdown(&self) {
+        shutdown_plugins(&self.actions, &self.triggers, &self.push_triggers);
+    }
+
+    /// Returns the types of the plugins of the library.
+    fn plugin_types(&self) -> impl Iterator<Item = &str> {
+        self.actions
+            .iter()
+            .map(|p| p.get_type())
+            .chain(self.triggers.iter().map(|p| p.get_type()))
+            .chain(self.push_triggers.iter().map(|p| p.get_type()))
+    }
+}
+
+/// Plugin implemented by a process, adapted like in-process plugins.
+struct ProcessPluginHandle {
+    client: Arc<ProcessClient>,
+    plugin_type: String,
+    action: Option<Arc<Box<dyn AsyncActionPlugin>>>,
+    trigger: Option<Arc<Box<dyn AsyncTriggerPlugin>>>,
+}
+
+#[derive(Default)]
+pub struct PluginHostConfig {
+    pub search_paths: Vec<PathBuf>,
+
+    /// Plugin executables, talking to the host over their standard input and output.
+    pub process_plugins: Vec<PathBuf>,
+
+    /// Resources available to the WASM plugins found in the search paths.
+    pub wasm_limits: WasmLimits,
+
+    /// Plugins allowed to be loaded from the search paths. No library is loaded from the
+    /// search paths when not set. See [`AllowedPlugin`].
+    pub allowlist: Option<Vec<AllowedPlugin>>,
+
+    /// Settings passed to plugins when they are initialized, keyed by plugin type.
+    pub settings: HashMap<String, Payload>,
+
+    /// Database holding plugin state. Plugin state is kept in memory when not provided.
+    pub state_store: Option<Arc<SledStore>>,
+
+    /// Order in which plugin sources take precedence when several plugins handle the same
+    /// type. Defaults to in-memory plugins, then libraries, WASM modules and processes.
+    /// Sources missing from the list come last.
+    ///
+    /// Among plugins of the same source, libraries of earlier search paths and processes
+    /// listed first take precedence.
+    pub precedence: Vec<PluginSource>,
+
+    /// Library, module or executable to use for a plugin type, taking precedence over every
+    /// other plugin of that type.
+    pub preferred: HashMap<String, PathBuf>,
+
+    /// How long calls into plugins may run. See [`PluginHost::watch`].
+    pub timeouts: CallTimeouts,
 }
 
 #[derive(Default)]
 pub struct PluginHost {
-    loaded_plugins: Vec<PluginHandle>,
+    loaded_plugins: RwLock<Vec<PluginHandle>>,
+
+    // Replaced or removed libraries, kept until their plugins are no longer used.
+    pub(crate) retired_plugins: Mutex<Vec<PluginHandle>>,
+
+    // Held while the search paths are loaded, so concurrent reloads don't load libraries twice.
+    reloading: Mutex<()>,
+
+    // Libraries which failed to load.
+    pub(crate) rejected_libraries: Mutex<HashMap<PathBuf, Rejection>>,
+
+    // Search paths which couldn't be read at the last reload.
+    unreadable_search_paths: Mutex<Vec<RejectedLibrary>>,
+
+    // Plugin processes which failed to start.
+    rejected_processes: Mutex<Vec<RejectedLibrary>>,
+
+    // Incremented whenever the set of loaded plugins changes.
+    generation: AtomicU64,
+
+    // Installed plugins, resolved at the generation they were listed at.
+    resolved: Mutex<Option<(u64, Vec<InstalledPlugin>)>>,
+
+    // Libraries are loaded from copies in this directory. See `PluginHost::shadow_copy`.
+    pub(crate) shadow_dir: Mutex<Option<TempDir>>,
+    shadow_count: AtomicU64,
+
+    is_shut_down: AtomicBool,
+
+    process_plugins: RwLock<Vec<ProcessPluginHandle>>,
+
     search_paths: Vec<PathBuf>,
+    wasm_limits: WasmLimits,
+    allowlist: Option<Vec<AllowedPlugin>>,
+    precedence: Precedence,
+    watchdog: Watchdog,
+    contexts: Mutex<ContextProvider>,
 
-    in_memory_action_plugins: Vec<Arc<Box<dyn ActionPlugin>>>,
-    in_memory_trigger_plugins: Vec<Arc<Box<dyn TriggerPlugin>>>,
+    in_memory_action_plugins: Vec<Arc<Box<dyn AsyncActionPlugin>>>,
+    in_memory_trigger_plugins: Vec<Arc<Box<dyn AsyncTriggerPlugin>>>,
+    in_memory_push_trigger_plugins: Vec<Arc<Box<dyn PushTriggerPlugin>>>,
 }
 
 impl PluginHost {
-    pub fn initialize(search_paths: &[PathBuf]) -> Result<PluginHost> {
-        let mut host = PluginHost {
-            loaded_plugins: Vec::new(),
-            search_paths: Vec::from(search_paths),
-
-            in_memory_action_plugins: Vec::new(),
-            in_memory_trigger_plugins: Vec::new(),
+    /// Loads the plugins found in the search paths, and starts the plugin processes.
+    ///
+    /// Libraries in the search paths must come with a [`PluginManifest`] matching them, and be
+    /// allowed by the allowlist of the host. Search paths which can't be read, and libraries
+    /// and processes failing to load are skipped, and listed in the [`LoadReport`] of the host.
+    ///
+    /// Each plugin is initialized with the settings registered under its type, if any,
+    /// and with a state handle scoped to its type.
+    pub fn initialize(cfg: PluginHostConfig) -> Result<PluginHost> {
+        let host = PluginHost {
+            search_paths: cfg.search_paths,
+            wasm_limits: cfg.wasm_limits,
+            allowlist: cfg.allowlist,
+            precedence: Precedence {
+                sources: cfg.precedence,
+                preferred: cfg.preferred,
+            },
+            watchdog: Watchdog::new(cfg.timeouts),
+            contexts: Mutex::new(ContextProvider::new(cfg.settings, cfg.state_store)),
+            ..Default::default()
         };
 
-        host.search()?;
+        log::info!("beginning plugin refresh");
+        host.load_search_paths();
+        log::info!("plugin refresh complete");
+
+        for command in cfg.process_plugins.iter() {
+            if let Err(e) = host.add_process_plugin(command) {
+                log::error!(
+                    "failed to start plugin process {}: {}",
+                    command.display(),
+                    e
+                );
+                lock(&host.rejected_processes).push(RejectedLibrary {
+                    path: command.clone(),
+                    error: e.to_string(),
+                });
+            }
+        }
+
+        host.log_conflicts();
 
         Ok(host)
     }
 
-    pub fn add_plugin<P: AsRef<Path>>(&mut self, library_path: P) -> Result<()> {
-        let plug_handle = PluginHandle::load(library_path.as_ref())?;
-        self.loaded_plugins.push(plug_handle);
+    /// Returns a counter incremented whenever the set of loaded plugins changes.
+    ///
+    /// Users of the plugins should fetch them again when it changes.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    /// Loads a plugin library. The library is trusted: unlike the libraries found in the
+    /// search paths, it doesn't need a manifest.
+    pub fn add_plugin<P: AsRef<Path>>(&self, library_path: P) -> Result<()> {
+        let plug_handle = self.load_library(library_path.as_ref(), true)?;
+        self.loaded_plugins_mut().push(plug_handle);
+        self.generation.fetch_add(1, Ordering::SeqCst);
         log::info!("loaded plugin: {}", library_path.as_ref().display());
         Ok(())
     }
 
-    pub fn add_in_memory_action_plugin(&mut self, action_plugin: Box<dyn ActionPlugin>) {
+    /// Spawns a plugin process, and adds the plugin it implements once it is initialized.
+    ///
+    /// Calls into the process may run as long as the [`CallTimeouts`] of its plugin type allow,
+    /// or 30 seconds without one. The process is killed when a call runs longer.
+    pub fn add_process_plugin<P: AsRef<Path>>(&self, command: P) -> Result<()> {
+        let command = command.as_ref();
+        let client =
+            Arc::new(ProcessClient::spawn(command).context(SpawnProcessSnafu { command })?);
+        let plugin =
+            ProcessPlugin::describe(client.clone()).context(DescribeProcessSnafu { command })?;
+
+        let plugin_type = String::from(plugin.get_type());
+        client.set_call_timeout(
+            self.watchdog
+                .timeouts()
+                .plugin(&plugin_type)
+                .unwrap_or(DEFAULT_CALL_TIMEOUT),
+        );
+        let ctx = lock(&self.contexts)
+            .context(&plugin_type)
+            .context(OpenStateSnafu {
+                plugin_type: &plugin_type,
+            })?;
+        logging::scope(&plugin_type, None, || client.init(&ctx))
+            .context(InitPluginSnafu { plugin_type })?;
+
+        let handle = match plugin {
+            ProcessPlugin::Action(action) => ProcessPluginHandle {
+                client,
+                plugin_type: String::from(action.get_type()),
+                action: Some(BlockingAction::wrap(Arc::new(Box::new(action)))),
+                trigger: None,
+            },
+            ProcessPlugin::Trigger(trigger) => ProcessPluginHandle {
+                client,
+                plugin_type: String::from(trigger.get_type()),
+                action: None,
+                trigger: Some(BlockingTrigger::wrap(Arc::new(Box::new(trigger)))),
+            },
+        };
+
+        log::info!(
+            "started plugin process {} ({})",
+            command.display(),
+            &handle.plugin_type
+        );
+        self.process_plugins
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(handle);
+        self.generation.fetch_add(1, Ordering::SeqCst);
+
+        Ok(())
+    }
+
+    /// Checks every plugin process responds. Crashed processes are restarted.
+    pub fn check_process_plugins(&self) -> Vec<ProcessPluginHealth> {
+        self.process_plugins()
+            .iter()
+            .map(|handle| ProcessPluginHealth {
+                command: PathBuf::from(handle.client.command()),
+                plugin_type: handle.plugin_type.clone(),
+                error: handle.client.health().err().map(|e| e.to_string()),
+                restarts: handle.client.restarts(),
+            })
+            .collect()
+    }
+
+    /// Runs a call into a plugin, made for a rule. The call fails with a timeout error if it
+    /// runs longer than the [`CallTimeouts`] of the host allow, and with a permanent error if
+    /// the plugin panics. Plugins which panic are marked unhealthy, and plugins which keep
+    /// panicking are suspended: calls into them are refused with a transient error for a while.
+    ///
+    /// Timed out calls are dropped. Calls into synchronous plugins keep running on the
+    /// blocking pool until they return, but no longer hold up their caller. New calls into
+    /// the plugin are refused with a transient error until they return.
+    pub async fn watch<T, F>(
+        &self,
+        plugin_type: &str,
+        rule: &str,
+        call: F,
+    ) -> std::result::Result<T, PlugError>
+    where
+        F: Future<Output = std::result::Result<T, PlugError>>,
+    {
+        self.watchdog.watch(plugin_type, rule, call).await
+    }
+
+    /// Runs a synchronous call into a plugin, turning a panic of the plugin into a permanent
+    /// error like [`PluginHost::watch`].
+    pub fn guard<T, F>(&self, plugin_type: &str, call: F) -> std::result::Result<T, PlugError>
+    where
+        F: FnOnce() -> std::result::Result<T, PlugError>,
+    {
+        self.watchdog.guard(plugin_type, call)
+    }
+
+    /// Lists the plugins which panicked since they were loaded, and whether they are suspended.
+    pub fn unhealthy_plugins(&self) -> Vec<UnhealthyPlugin> {
+        self.watchdog.unhealthy()
+    }
+
+    /// Returns the number of calls which timed out, by plugin type.
+    pub fn timed_out_calls(&self) -> HashMap<String, u64> {
+        self.watchdog.timed_out()
+    }
+
+    fn process_plugins(&self) -> RwLockReadGuard<'_, Vec<ProcessPluginHandle>> {
+        self.process_plugins
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Loads the libraries added to the search paths or modified since they were loaded, and
+    /// retires the libraries that were removed. Returns whether the set of plugins changed.
+    ///
+    /// Replaced libraries are shut down once their plugins are no longer used. A library that
+    /// fails to load is reported once, and the previous version of it is kept.
+    pub fn reload(&self) -> Result<bool> {
+        if self.is_shut_down.load(Ordering::SeqCst) {
+            return Ok(false);
+        }
+
+        let changed = self.load_search_paths();
+        if changed {
+            self.log_conflicts();
+        }
+
+        self.drop_retired();
+
+        Ok(changed)
+    }
+
+    /// Brings the loaded libraries in line with the search paths. Returns whether the set of
+    /// plugins changed.
+    ///
+    /// Libraries are loaded before the loaded plugins are locked, so users of the plugins
+    /// aren't held up while they load.
+    fn load_search_paths(&self) -> bool {
+        let _reloading = lock(&self.reloading);
+        let libraries = self.search();
+        lock(&self.rejected_libraries).retain(|path, _| libraries.contains(path));
+
+        let (loaded, removed) = {
+            let handles = self.loaded_plugins();
+            let loaded: Vec<(PathBuf, Option<SystemTime>)> = handles
+                .iter()
+                .map(|handle| (handle.path.clone(), handle.modified))
+                .collect();
+            let removed = handles
+                .iter()
+                .any(|handle| !handle.added && !libraries.contains(&handle.path));
+            (loaded, removed)
+        };
+
+        let mut new_handles = Vec::new();
+        for library_path in libraries.iter() {
+            let modified = library_modified(library_path);
+            if loaded.contains(&(library_path.clone(), modified)) {
+                continue;
+            }
+
+            if let Some(rejection) = lock(&self.rejected_libraries).get(library_path) {
+                if rejection.modified == modified {
+                    continue;
+                }
+            }
+
+            match self.load_library(library_path, false) {
+                Ok(handle) => {
+                    lock(&self.rejected_libraries).remove(library_path);
+                    self.watchdog.heal(handle.plugin_types());
+                    new_handles.push(handle);
+                }
+                Err(e) => {
+                    log::error!("failed to load plugin {}: {}", library_path.display(), e);
+                    lock(&self.rejected_libraries).insert(
+                        library_path.clone(),
+                        Rejection {
+                            modified,
+                            error: e.to_string(),
+                        },
+                    );
+                }
+            }
+        }
+
+        if !removed && new_handles.is_empty() {
+            return false;
+        }
+
+        {
+            let mut loaded = self.loaded_plugins_mut();
+            let mut retired = lock(&self.retired_plugins);
+
+            let (kept, removed): (Vec<PluginHandle>, Vec<PluginHandle>) = loaded
+                .drain(..)
+                .partition(|handle| handle.added || libraries.contains(&handle.path));
+            *loaded = kept;
+            for handle in removed.into_iter() {
+                log::info!("retiring plugin: {}", handle.path.display());
+                retired.push(handle);
+            }
+
+            for handle in new_handles.into_iter() {
+                match loaded.iter().position(|h| h.path == handle.path) {
+                    Some(idx) => {
+                        log::info!("reloaded plugin: {}", handle.path.display());
+                        let previous = std::mem::replace(&mut loaded[idx], handle);
+                        retired.push(previous);
+                    }
+                    None => {
+                        log::info!("loaded plugin: {}", handle.path.display());
+                        loaded.push(handle);
+                    }
+                }
+            }
+        }
+
+        self.generation.fetch_add(1, Ordering::SeqCst);
+
+        true
+    }
+
+    /// Shuts down the retired libraries whose plugins are no longer used, and drops them.
+    fn drop_retired(&self) {
+        let mut retired = lock(&self.retired_plugins);
+        let (in_use, unused): (Vec<PluginHandle>, Vec<PluginHandle>) =
+            retired.drain(..).partition(PluginHandle::in_use);
+        *retired = in_use;
+
+        for handle in unused.into_iter() {
+            handle.shutdown();
+            log::info!("dropped previous plugin: {}", handle.path.display());
+        }
+    }
+
+    /// Loads a library or WASM module. Unless it is trusted, it is verified against its
+    /// manifest and the allowlist.
+    pub(crate) fn load_library(&self, library_path: &Path, trusted: bool) -> Result<PluginHandle> {
+        let manifest = if trusted {
+            None
+        } else {
+            Some(
+                PluginManifest::read(library_path)
+                    .context(UntrustedLibrarySnafu { path: library_path })?,
+            )
+        };
+
+        // Checksums are verified on the data actually loaded, as the library may be replaced
+        // in the meantime.
+        let verify = |data: &[u8]| -> Result<()> {
+            if let Some(manifest) = &manifest {
+                manifest
+                    .verify_checksum(data)
+                    .and_then(|_| manifest::check_allowed(self.allowlist.as_deref(), manifest))
+                    .context(UntrustedLibrarySnafu { path: library_path })?;
+            }
+            Ok(())
+        };
+
+        let mut handle = if library_path.extension() == Some(WASM_EXTENSION.as_ref()) {
+            // Modules are read once and interpreted, so they can be loaded from their own path.
+            let data = manifest::read_library(library_path)
+                .context(UntrustedLibrarySnafu { path: library_path })?;
+            verify(&data)?;
+            PluginHandle::load_wasm(
+                library_path,
+                &data,
+                &self.wasm_limits,
+                manifest.as_ref(),
+                &mut lock(&self.contexts),
+            )?
+        } else {
+            let shadow_copy = self.shadow_copy(library_path)?;
+            if manifest.is_some() {
+                let data = manifest::read_library(&shadow_copy.path)
+                    .context(UntrustedLibrarySnafu { path: library_path })?;
+                verify(&data)?;
+            }
+            PluginHandle::load(
+                library_path,
+                shadow_copy,
+                manifest.as_ref(),
+                &mut lock(&self.contexts),
+            )?
+        };
+        handle.added = trusted;
+
+        Ok(handle)
+    }
+
+    /// Copies a library to a private directory, to load it from there.
+    ///
+    /// A path can't be loaded again while the library it points to is still loaded, and
+    /// overwriting a loaded library in place crashes the process. Loading private copies allows
+    /// replacing libraries while their previous version is still in use.
+    fn shadow_copy(&self, library_path: &Path) -> Result<ShadowCopy> {
+        let mut shadow_dir = lock(&self.shadow_dir);
+        if shadow_dir.is_none() {
+            *shadow_dir = Some(
+                tempfile::Builder::new()
+                    .prefix("shift3-plugins")
+                    .tempdir()
+                    .context(ShadowCopySnafu)?,
+            );
+        }
+
+        let copy_path = shadow_dir.as_ref().unwrap().path().join(format!(
+            "{}-{}",
+            self.shadow_count.fetch_add(1, Ordering::SeqCst),
+            library_path
+                .file_name()
+                .map(|f| f.to_string_lossy().to_string())
+                .unwrap_or_default()
+        ));
+        fs::copy(library_path, &copy_path).context(ShadowCopySnafu)?;
+
+        Ok(ShadowCopy { path: copy_path })
+    }
+
+    fn loaded_plugins(&self) -> RwLockReadGuard<'_, Vec<PluginHandle>> {
+        self.loaded_plugins
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn loaded_plugins_mut(&self) -> RwLockWriteGuard<'_, Vec<PluginHandle>> {
+        self.loaded_plugins
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn add_in_memory_action_plugin(
+        &mut self,
+        mut action_plugin: Box<dyn ActionPlugin>,
+    ) -> Result<()> {
+        let ctx = lock(&self.contexts)
+            .context(action_plugin.get_type())
+            .context(OpenStateSnafu {
+                plugin_type: action_plugin.get_type(),
+            })?;
+        action_plugin.init(&ctx).context(InitPluginSnafu {
+            plugin_type: action_plugin.get_type(),
+        })?;
+        self.in_memory_action_plugins
+            .push(BlockingAction::wrap(Arc::new(action_plugin)));
+        *self.generation.get_mut() += 1;
+        Ok(())
+    }
+
+    pub fn add_in_memory_trigger_plugin(
+        &mut self,
+        mut trigger_plugin: Box<dyn TriggerPlugin>,
+    ) -> Result<()> {
+        let ctx = lock(&self.contexts)
+            .context(trigger_plugin.get_type())
+            .context(OpenStateSnafu {
+                plugin_type: trigger_plugin.get_type(),
+            })?;
+        trigger_plugin.init(&ctx).context(InitPluginSnafu {
+            plugin_type: trigger_plugin.get_type(),
+        })?;
+        self.in_memory_trigger_plugins
+            .push(BlockingTrigger::wrap(Arc::new(trigger_plugin)));
+        *self.generation.get_mut() += 1;
+        Ok(())
+    }
+
+    pub fn add_in_memory_async_action_plugin(
+        &mut self,
+        mut action_plugin: Box<dyn AsyncActionPlugin>,
+    ) -> Result<()> {
+        let ctx = lock(&self.contexts)
+            .context(action_plugin.get_type())
+            .context(OpenStateSnafu {
+                plugin_type: action_plugin.get_type(),
+            })?;
+        action_plugin.init(&ctx).context(InitPluginSnafu {
+            plugin_type: action_plugin.get_type(),
+        })?;
         self.in_memory_action_plugins.push(Arc::new(action_plugin));
+        *self.generation.get_mut() += 1;
+        Ok(())
     }
 
-    pub fn add_in_memory_trigger_plugin(&mut self, trigger_plugin: Box<dyn TriggerPlugin>) {
+    pub fn add_in_memory_async_trigger_plugin(
+        &mut self,
+        mut trigger_plugin: Box<dyn AsyncTriggerPlugin>,
+    ) -> Result<()> {
+        let ctx = lock(&self.contexts)
+            .context(trigger_plugin.get_type())
+            .context(OpenStateSnafu {
+                plugin_type: trigger_plugin.get_type(),
+            })?;
+        trigger_plugin.init(&ctx).context(InitPluginSnafu {
+            plugin_type: trigger_plugin.get_type(),
+        })?;
         self.in_memory_trigger_plugins
             .push(Arc::new(trigger_plugin));
+        *self.generation.get_mut() += 1;
+        Ok(())
     }
 
-    fn search(&mut self) -> Result<()> {
-        log::info!("beginning plugin refresh");
-        self.loaded_plugins.clear();
+    pub fn add_in_memory_push_trigger_plugin(
+        &mut self,
+        mut trigger_plugin: Box<dyn PushTriggerPlugin>,
+    ) -> Result<()> {
+        let ctx = lock(&self.contexts)
+            .context(trigger_plugin.get_type())
+            .context(OpenStateSnafu {
+                plugin_type: trigger_plugin.get_type(),
+            })?;
+        trigger_plugin.init(&ctx).context(InitPluginSnafu {
+            plugin_type: trigger_plugin.get_type(),
+        })?;
+        self.in_memory_push_trigger_plugins
+            .push(Arc::new(trigger_plugin));
+        *self.generation.get_mut() += 1;
+        Ok(())
+    }
 
-        let paths_copy = self.search_paths.clone();
+    /// Calls the shutdown hook of every plugin. Failures are logged, and don't prevent
+    /// the remaining plugins from shutting down.
+    ///
+    /// Plugins are no longer reloaded afterwards.
+    pub fn shutdown(&self) {
+        log::info!("shutting down plugins");
+        self.is_shut_down.store(true, Ordering::SeqCst);
 
-        for path in paths_copy.into_iter() {
-            ensure!(path.exists(), SearchPathDoesNotExistSnafu);
-            ensure!(path.is_dir(), SearchPathIsAFileSnafu);
+        for installed in self.installed_plugins().iter() {
+            if let Err(e) = installed.plugin.shutdown() {
+                log::error!(
+                    "failed to shut down plugin [{}]: {}",
+                    installed.plugin.get_type(),
+                    e
+                );
+            }
+        }
+
+        for handle in lock(&self.retired_plugins).iter() {
+            handle.shutdown();
+        }
 
-            for entry in fs::read_dir(path)
-                .context(OpenSearchPathSnafu)?
-                .filter_map(|e| e.ok())
-            {
+        lock(&self.contexts).flush();
+
+        log::info!("plugin shutdown complete");
+    }
+
+    /// Lists the plugin libraries and WASM modules in the search paths, search path by
+    /// search path. Search paths which can't be read are skipped, and reported.
+    fn search(&self) -> Vec<PathBuf> {
+        let mut libraries = Vec::new();
+        let mut unreadable = Vec::new();
+
+        for path in self.search_paths.iter() {
+            let mut found = Vec::new();
+
+            let entries = match read_search_path(path) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    unreadable.push(RejectedLibrary {
+                        path: path.clone(),
+                        error: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+
+            for entry in entries.filter_map(|e| e.ok()) {
                 let entry_path = entry.path();
 
                 if let Some(ext) = entry_path.extension() {
-                    if ext.to_string_lossy().as_ref() == PLUGIN_EXTENSION {
-                        // Load plugin from library.
-                        self.add_plugin(&entry_path)?;
+                    let ext = ext.to_string_lossy();
+                    if ext == PLUGIN_EXTENSION || ext == WASM_EXTENSION {
+                        found.push(entry_path);
                     }
                 }
             }
+
+            found.sort();
+            libraries.extend(found);
+        }
+
+        // Search paths are only reported when they become unreadable, as they are read again on
+        // every reload.
+        let mut previous = lock(&self.unreadable_search_paths);
+        for rejected in unreadable.iter() {
+            if !previous.iter().any(|p| p.path == rejected.path) {
+                log::error!(
+                    "can't read plugin search path {}: {}",
+                    rejected.path.display(),
+                    rejected.error
+                );
+            }
+        }
+        *previous = unreadable;
+
+        libraries
+    }
+
+    /// Lists every installed plugin, marking the plugins shadowed by another plugin of the
+    /// same type.
+    ///
+    /// Plugins are resolved once per generation.
+    fn installed_plugins(&self) -> Vec<InstalledPlugin> {
+        let generation = self.generation();
+        let mut resolved = lock(&self.resolved);
+        match &*resolved {
+            Some((resolved_at, installed)) if *resolved_at == generation => installed.clone(),
+            _ => {
+                let installed = self.resolve_plugins();
+                *resolved = Some((generation, installed.clone()));
+                installed
+            }
         }
-        log::info!("plugin refresh complete");
-        Ok(())
     }
 
-    pub fn get_action_plugins(&self) -> Vec<Arc<Box<dyn ActionPlugin>>> {
-        let mut v: Vec<Arc<Box<dyn ActionPlugin>>> = self.in_memory_action_plugins.clone();
+    fn resolve_plugins(&self) -> Vec<InstalledPlugin> {
+        let mut v = Vec::new();
+        let mut push = |plugin: AnyPlugin, source, origin: Option<PathBuf>, position| {
+            v.push(InstalledPlugin {
+                plugin,
+                source,
+                origin,
+                position,
+                status: PluginStatus::Active,
+            })
+        };
+
+        for (i, action_plug) in self.in_memory_action_plugins.iter().enumerate() {
+            push(
+                AnyPlugin::Action(action_plug.clone()),
+                PluginSource::InMemory,
+                None,
+                i,
+            );
+        }
+
+        for (i, trigger_plug) in self.in_memory_trigger_plugins.iter().enumerate() {
+            push(
+                AnyPlugin::Trigger(trigger_plug.clone()),
+                PluginSource::InMemory,
+                None,
+                i,
+            );
+        }
+
+        for (i, trigger_plug) in self.in_memory_push_trigger_plugins.iter().enumerate() {
+            push(
+                AnyPlugin::PushTrigger(trigger_plug.clone()),
+                PluginSource::InMemory,
+                None,
+                i,
+            );
+        }
+
+        for plug_handle in self.loaded_plugins().iter() {
+            let source = PluginSource::of_library(&plug_handle.path);
+            // Libraries added outside of the search paths come after the ones in them.
+            let position = self
+                .search_paths
+                .iter()
+                .position(|p| plug_handle.path.parent() == Some(p.as_path()))
+                .unwrap_or(self.search_paths.len());
+            let origin = Some(plug_handle.path.clone());
+
+            for action_plug in plug_handle.actions.iter() {
+                push(
+                    AnyPlugin::Action(action_plug.clone()),
+                    source,
+                    origin.clone(),
+                    position,
+                );
+            }
+
+            for trigger_plug in plug_handle.triggers.iter() {
+                push(
+                    AnyPlugin::Trigger(trigger_plug.clone()),
+                    source,
+                    origin.clone(),
+                    position,
+                );
+            }
+
+            for trigger_plug in plug_handle.push_triggers.iter() {
+                push(
+                    AnyPlugin::PushTrigger(trigger_plug.clone()),
+                    source,
+                    origin.clone(),
+                    position,
+                );
+            }
+        }
+
+        for (i, process_handle) in self.process_plugins().iter().enumerate() {
+            let origin = Some(PathBuf::from(process_handle.client.command()));
+
+            if let Some(action_plug) = &process_handle.action {
+                push(
+                    AnyPlugin::Action(action_plug.clone()),
+                    PluginSource::Process,
+                    origin.clone(),
+                    i,
+                );
+            }
 
-        for plug_handle in self.loaded_plugins.iter() {
-            for action_plug in plug_handle.plugin.actions.iter() {
-                v.push(action_plug.clone());
+            if let Some(trigger_plug) = &process_handle.trigger {
+                push(
+                    AnyPlugin::Trigger(trigger_plug.clone()),
+                    PluginSource::Process,
+                    origin,
+                    i,
+                );
             }
         }
 
+        self.precedence.resolve(&mut v);
         v
     }
 
-    pub fn get_trigger_plugins(&self) -> Vec<Arc<Box<dyn TriggerPlugin>>> {
-        let mut v: Vec<Arc<Box<dyn TriggerPlugin>>> = self.in_memory_trigger_plugins.clone();
+    /// Warns about the plugins shadowed by another plugin of the same type.
+    fn log_conflicts(&self) {
+        for installed in self.installed_plugins().iter() {
+            if let PluginStatus::Shadowed { by } = &installed.status {
+                log::warn!(
+                    "plugin [{}] from {} is shadowed by the one from {}",
+                    installed.plugin.get_type(),
+                    display_origin(installed.origin.as_deref()),
+                    display_origin(by.as_deref()),
+                );
+            }
+        }
+    }
+
+    /// Returns the action plugins in use, one per action type. Synchronous plugins are adapted
+    /// to run on the blocking pool.
+    pub fn get_action_plugins(&self) -> Vec<Arc<Box<dyn AsyncActionPlugin>>> {
+        self.active_plugins()
+            .filter_map(|plugin| match plugin {
+                AnyPlugin::Action(p) => Some(p),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the trigger plugins in use, one per trigger type. Synchronous plugins are
+    /// adapted to run on the blocking pool.
+    pub fn get_trigger_plugins(&self) -> Vec<Arc<Box<dyn AsyncTriggerPlugin>>> {
+        self.active_plugins()
+            .filter_map(|plugin| match plugin {
+                AnyPlugin::Trigger(p) => Some(p),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the push trigger plugins in use, one per trigger type.
+    pub fn get_push_trigger_plugins(&self) -> Vec<Arc<Box<dyn PushTriggerPlugin>>> {
+        self.active_plugins()
+            .filter_map(|plugin| match plugin {
+                AnyPlugin::PushTrigger(p) => Some(p),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn active_plugins(&self) -> impl Iterator<Item = AnyPlugin> {
+        self.installed_plugins()
+            .into_iter()
+            .filter(|installed| installed.status == PluginStatus::Active)
+            .map(|installed| installed.plugin)
+    }
+
+    /// Lists the metadata of all plugins installed in the host, including the plugins shadowed
+    /// by another plugin of the same type.
+    pub fn list_plugins(&self) -> Vec<PluginInfo> {
+        self.installed_plugins()
+            .into_iter()
+            .map(|installed| PluginInfo {
+                plugin_type: String::from(installed.plugin.get_type()),
+                kind: installed.plugin.kind(),
+                metadata: installed.plugin.metadata(),
+                library: installed.origin,
+                source: installed.source,
+                status: installed.status,
+            })
+            .collect()
+    }
+
+    /// Reports the installed plugins, and the libraries of the search paths which failed to
+    /// load.
+    pub fn load_report(&self) -> LoadReport {
+        let mut rejected: Vec<RejectedLibrary> = lock(&self.rejected_libraries)
+            .iter()
+            .map(|(path, rejection)| RejectedLibrary {
+                path: path.clone(),
+                error: rejection.error.clone(),
+            })
+            .chain(lock(&self.unreadable_search_paths).iter().cloned())
+            .chain(lock(&self.rejected_processes).iter().cloned())
+            .collect();
+        rejected.sort_by(|a, b| a.path.cmp(&b.path));
+
+        LoadReport {
+            plugins: self.list_plugins(),
+            rejected,
+        }
+    }
+
+    /// Returns the metadata of the action plugin handling the given action type.
+    pub fn get_action_metadata(&self, action_type: &str) -> Option<PluginMetadata> {
+        self.get_action_plugins()
+            .into_iter()
+            .find(|p| p.get_type() == action_type)
+            .map(|p| p.metadata())
+    }
+
+    /// Returns the metadata of the trigger plugin handling the given trigger type.
+    pub fn get_trigger_metadata(&self, trigger_type: &str) -> Option<PluginMetadata> {
+        let pull_metadata = self
+            .get_trigger_plugins()
+            .into_iter()
+            .find(|p| p.get_type() == trigger_type)
+            .map(|p| p.metadata());
+
+        pull_metadata.or_else(|| {
+            self.get_push_trigger_plugins()
+                .into_iter()
+                .find(|p| p.get_type() == trigger_type)
+                .map(|p| p.metadata())
+        })
+    }
+
+    /// Validates a trigger configuration against the plugin handling its trigger type.
+    pub fn validate_trigger_config(&self, cfg: &TriggerConfiguration) -> Result<()> {
+        let result = if let Some(plugin) = self
+            .get_trigger_plugins()
+            .into_iter()
+            .find(|p| p.get_type() == cfg.trigger_type)
+        {
+            self.guard(&cfg.trigger_type, || plugin.validate_config(cfg))
+        } else {
+            let plugin = self
+                .get_push_trigger_plugins()
+                .into_iter()
+                .find(|p| p.get_type() == cfg.trigger_type)
+                .context(UnknownPluginTypeSnafu {
+                    plugin_type: &cfg.trigger_type,
+                })?;
+            self.guard(&cfg.trigger_type, || plugin.validate_config(cfg))
+        };
+
+        result.context(InvalidConfigSnafu {
+            plugin_type: &cfg.trigger_type,
+        })
+    }
+
+    /// Validates every action of a rule fired by triggers of the given type, against the plugins
+    /// handling them.
+    ///
+    /// Action templates are rendered with sample data built from the data schema of the trigger
+    /// plugin, and the rendered configurations are validated. Without a data schema, the shape
+    /// of the rendered configurations is unknown, so only the templates themselves are checked.
+    pub fn validate_rule(&self, rule: &Rule, trigger_type: &str) -> Result<()> {
+        let trigger_metadata =
+            self.get_trigger_metadata(trigger_type)
+                .context(UnknownPluginTypeSnafu {
+                    plugin_type: trigger_type,
+                })?;
+        let sample_data = trigger_metadata
+            .data_schema
+            .as_ref()
+            .map(|schema| Payload::new(sample::sample(schema)));
+
+        let action_plugins = self.get_action_plugins();
 
-        for plug_handle in self.loaded_plugins.iter() {
-            for trigger_plug in plug_handle.plugin.triggers.iter() {
-                v.push(trigger_plug.clone());
+        for action in rule.actions.iter() {
+            let plugin = action_plugins
+                .iter()
+                .find(|p| p.get_type() == action.action_type)
+                .context(UnknownPluginTypeSnafu {
+                    plugin_type: &action.action_type,
+                })?;
+
+            match &sample_data {
+                Some(sample_data) => {
+                    let config = action.render(sample_data).context(InvalidTemplateSnafu {
+                        plugin_type: &action.action_type,
+                    })?;
+                    self.guard(&action.action_type, || plugin.validate_config(&config))
+                        .context(InvalidConfigSnafu {
+                            plugin_type: &action.action_type,
+                        })?;
+                }
+                None => {
+                    if let Err(e @ RenderError::Template { .. }) =
+                        action.render(&Payload::default())
+                    {
+                        return Err(e).context(InvalidTemplateSnafu {
+                            plugin_type: &action.action_type,
+                        });
+                    }
+                }
             }
         }
 
-        v
+        Ok(())
     }
 }
@@ -1,6 +1,23 @@
+mod adapter;
+mod context;
 mod host;
+mod manifest;
+mod precedence;
+mod sample;
+mod subprocess;
+mod wasm;
+mod watch;
+mod watchdog;
 
-pub use host::PluginHost;
+pub use host::{
+    Error, LoadReport, PluginHost, PluginHostConfig, PluginInfo, PluginKind, ProcessPluginHealth,
+    RejectedLibrary,
+};
+pub use manifest::{sha256_hex, AllowedPlugin, ManifestError, PluginManifest};
+pub use precedence::{PluginSource, PluginStatus};
+pub use wasm::WasmLimits;
+pub use watch::PluginWatcher;
+pub use watchdog::{CallTimeouts, UnhealthyPlugin, DEFAULT_CALL_TIMEOUT_MS};
 
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,202 @@
+//! Manifests and allowlist of the plugin libraries found in the search paths.
+//!
+//! A library is only loaded from the search paths if it comes with a [`PluginManifest`]
+//! matching it, and if the allowlist of the node pins its checksum. Nodes without an allowlist
+//! don't load any library from their search paths.
+//!
+//! # Migrating
+//!
+//! Libraries used to be loaded from the search paths as is. To keep loading them:
+//!
+//! 1. Generate the manifest of every library, with the `shift3-manifest` tool of the `process`
+//!    crate (`just manifest <library>`). It writes the manifest next to the library, and prints
+//!    the allowlist entry pinning it.
+//! 2. Add the printed entries to the `plugin_allowlist` of the node configuration.
+//!
+//! Manifests and allowlist entries must be generated again whenever a library is rebuilt.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use sha2::{Digest, Sha256};
+
+use snafu::{ensure, ResultExt, Snafu};
+
+const MANIFEST_SUFFIX: &str = ".manifest.json";
+
+/// Reason a library found in the search paths was refused.
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub(crate)))]
+pub enum ManifestError {
+    #[snafu(display("failed to read manifest {}: {}", path.display(), source))]
+    ReadManifest { path: PathBuf, source: io::Error },
+    #[snafu(display("invalid manifest {}: {}", path.display(), source))]
+    ParseManifest {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[snafu(display("failed to read library: {}", source))]
+    ReadLibrary { source: io::Error },
+    #[snafu(display("checksum {} doesn't match the manifest ({})", actual, expected))]
+    ChecksumMismatch { expected: String, actual: String },
+    #[snafu(display("plugin {} {} isn't allowed on this node", name, version))]
+    NotAllowed { name: String, version: String },
+    #[snafu(display("no plugin allowlist is configured, libraries are only loaded once allowed"))]
+    NoAllowlist,
+    #[snafu(display(
+        "library provides types {:?}, manifest declares {:?}",
+        provided,
+        declared
+    ))]
+    UndeclaredTypes {
+        declared: BTreeSet<String>,
+        provided: BTreeSet<String>,
+    },
+}
+
+type Result<T> = std::result::Result<T, ManifestError>;
+
+/// Returns the hex-encoded SHA-256 of some data.
+pub fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// Sidecar file describing a plugin library, required to load it from the search paths.
+///
+/// The manifest of `libfoo.so` is `libfoo.so.manifest.json`.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PluginManifest {
+    pub name: String,
+    pub version: String,
+
+    /// Plugin types provided by the library.
+    pub types: Vec<String>,
+
+    /// Hex-encoded SHA-256 of the library.
+    pub sha256: String,
+}
+
+impl PluginManifest {
+    /// Describes a library, computing its checksum.
+    pub fn for_library<P: AsRef<Path>>(
+        library_path: P,
+        name: &str,
+        version: &str,
+        types: &[&str],
+    ) -> io::Result<PluginManifest> {
+        Ok(PluginManifest {
+            name: String::from(name),
+            version: String::from(version),
+            types: types.iter().map(|t| String::from(*t)).collect(),
+            sha256: sha256_hex(&fs::read(library_path)?),
+        })
+    }
+
+    /// Returns the path of the manifest of a library.
+    pub fn path<P: AsRef<Path>>(library_path: P) -> PathBuf {
+        let mut path = library_path.as_ref().as_os_str().to_owned();
+        path.push(MANIFEST_SUFFIX);
+        PathBuf::from(path)
+    }
+
+    /// Reads the manifest of a library.
+    pub fn read<P: AsRef<Path>>(library_path: P) -> Result<PluginManifest> {
+        let path = PluginManifest::path(library_path);
+        let data = fs::read(&path).context(ReadManifestSnafu { path: &path })?;
+        serde_json::from_slice(&data).context(ParseManifestSnafu { path })
+    }
+
+    /// Writes the manifest of a library next to it.
+    pub fn write<P: AsRef<Path>>(&self, library_path: P) -> io::Result<()> {
+        let data = serde_json::to_vec_pretty(self)?;
+        fs::write(PluginManifest::path(library_path), data)
+    }
+
+    /// Ensures the data of a library matches the manifest.
+    pub(crate) fn verify_checksum(&self, data: &[u8]) -> Result<()> {
+        let actual = sha256_hex(data);
+        ensure!(
+            actual.eq_ignore_ascii_case(&self.sha256),
+            ChecksumMismatchSnafu {
+                expected: &self.sha256,
+                actual
+            }
+        );
+        Ok(())
+    }
+
+    /// Ensures a library provides exactly the plugin types it declares.
+    pub(crate) fn verify_types<'a, I: Iterator<Item = &'a str>>(&self, provided: I) -> Result<()> {
+        let declared: BTreeSet<String> = self.types.iter().cloned().collect();
+        let provided: BTreeSet<String> = provided.map(String::from).collect();
+        ensure!(
+            declared == provided,
+            UndeclaredTypesSnafu { declared, provided }
+        );
+        Ok(())
+    }
+}
+
+/// Plugin allowed to run on a node.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct AllowedPlugin {
+    pub name: String,
+
+    /// Allowed version. Any version is allowed when not set.
+    #[serde(default)]
+    pub version: Option<String>,
+
+    /// Hex-encoded SHA-256 of the allowed library.
+    ///
+    /// Pinning the checksum is what prevents whoever can write to the search paths from
+    /// running their own code, as they could write a manifest too.
+    pub sha256: String,
+}
+
+impl AllowedPlugin {
+    /// Allows the library described by a manifest, and only it.
+    pub fn pinned(manifest: &PluginManifest) -> Self {
+        AllowedPlugin {
+            name: manifest.name.clone(),
+            version: Some(manifest.version.clone()),
+            sha256: manifest.sha256.clone(),
+        }
+    }
+
+    pub(crate) fn allows(&self, manifest: &PluginManifest) -> bool {
+        self.name == manifest.name
+            && self
+                .version
+                .as_ref()
+                .map(|v| v == &manifest.version)
+                .unwrap_or(true)
+            && self.sha256.eq_ignore_ascii_case(&manifest.sha256)
+    }
+}
+
+/// Ensures the allowlist of a node allows the plugin described by a manifest.
+///
+/// No plugin is allowed when the node has no allowlist.
+pub(crate) fn check_allowed(
+    allowlist: Option<&[AllowedPlugin]>,
+    manifest: &PluginManifest,
+) -> Result<()> {
+    let allowlist = allowlist.ok_or(ManifestError::NoAllowlist)?;
+    ensure!(
+        allowlist.iter().any(|allowed| allowed.allows(manifest)),
+        NotAllowedSnafu {
+            name: &manifest.name,
+            version: &manifest.version
+        }
+    );
+    Ok(())
+}
+
+/// Reads a library, for its checksum to be verified.
+pub(crate) fn read_library(path: &Path) -> Result<Vec<u8>> {
+    fs::read(path).context(ReadLibrarySnafu)
+}
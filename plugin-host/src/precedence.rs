@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use plugin_core::{
+    AsyncActionPlugin, AsyncTriggerPlugin, Error as PlugError, PluginMetadata, PushTriggerPlugin,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::PluginKind;
+
+/// Where a plugin comes from.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginSource {
+    /// Added with one of the `add_in_memory_*` methods of the host.
+    InMemory,
+
+    /// Native library.
+    Library,
+
+    /// WASM module.
+    Wasm,
+
+    /// Plugin process.
+    Process,
+}
+
+impl PluginSource {
+    /// Returns the source of the plugins of a library found in the search paths.
+    pub(crate) fn of_library(path: &Path) -> PluginSource {
+        if path.extension() == Some(crate::host::WASM_EXTENSION.as_ref()) {
+            PluginSource::Wasm
+        } else {
+            PluginSource::Library
+        }
+    }
+}
+
+/// Precedence of plugin sources used when none is configured.
+const DEFAULT_PRECEDENCE: [PluginSource; 4] = [
+    PluginSource::InMemory,
+    PluginSource::Library,
+    PluginSource::Wasm,
+    PluginSource::Process,
+];
+
+/// Whether the host uses a plugin.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum PluginStatus {
+    Active,
+
+    /// Another plugin handles the same type, and takes precedence. `by` is where it comes from.
+    Shadowed {
+        by: Option<PathBuf>,
+    },
+}
+
+/// Plugin of any kind.
+#[derive(Clone)]
+pub(crate) enum AnyPlugin {
+    Action(Arc<Box<dyn AsyncActionPlugin>>),
+    Trigger(Arc<Box<dyn AsyncTriggerPlugin>>),
+    PushTrigger(Arc<Box<dyn PushTriggerPlugin>>),
+}
+
+impl AnyPlugin {
+    pub fn kind(&self) -> PluginKind {
+        match self {
+            AnyPlugin::Action(_) => PluginKind::Action,
+            AnyPlugin::Trigger(_) => PluginKind::Trigger,
+            AnyPlugin::PushTrigger(_) => PluginKind::PushTrigger,
+        }
+    }
+
+    pub fn get_type(&self) -> &str {
+        match self {
+            AnyPlugin::Action(p) => p.get_type(),
+            AnyPlugin::Trigger(p) => p.get_type(),
+            AnyPlugin::PushTrigger(p) => p.get_type(),
+        }
+    }
+
+    pub fn metadata(&self) -> PluginMetadata {
+        match self {
+            AnyPlugin::Action(p) => p.metadata(),
+            AnyPlugin::Trigger(p) => p.metadata(),
+            AnyPlugin::PushTrigger(p) => p.metadata(),
+        }
+    }
+
+    pub fn shutdown(&self) -> Result<(), PlugError> {
+        match self {
+            AnyPlugin::Action(p) => p.shutdown(),
+            AnyPlugin::Trigger(p) => p.shutdown(),
+            AnyPlugin::PushTrigger(p) => p.shutdown(),
+        }
+    }
+
+    /// Returns whether the plugin handles actions. Trigger and push trigger plugins share the
+    /// trigger types, so plugins conflict when they are both actions or both triggers, and
+    /// handle the same type.
+    fn is_action(&self) -> bool {
+        matches!(self, AnyPlugin::Action(_))
+    }
+}
+
+/// Plugin installed in the host.
+#[derive(Clone)]
+pub(crate) struct InstalledPlugin {
+    pub plugin: AnyPlugin,
+    pub source: PluginSource,
+
+    /// Library, module or executable the plugin comes from. `None` for in-memory plugins.
+    pub origin: Option<PathBuf>,
+
+    /// Position of the plugin among the plugins of its source, lower first. For libraries,
+    /// this is the index of their search path.
+    pub position: usize,
+
+    pub status: PluginStatus,
+}
+
+/// How the host picks a plugin among the plugins handling the same type.
+#[derive(Default)]
+pub(crate) struct Precedence {
+    pub sources: Vec<PluginSource>,
+    pub preferred: HashMap<String, PathBuf>,
+}
+
+impl Precedence {
+    /// Ranks a plugin among the plugins of the same type. Lower ranks take precedence.
+    fn rank<'a>(&self, plugin: &'a InstalledPlugin) -> (bool, usize, usize, Option<&'a Path>) {
+        let preferred = match (self.preferred.get(plugin.plugin.get_type()), &plugin.origin) {
+            (Some(preferred), Some(origin)) => preferred == origin,
+            _ => false,
+        };
+
+        let sources = if self.sources.is_empty() {
+            &DEFAULT_PRECEDENCE[..]
+        } else {
+            &self.sources[..]
+        };
+        let source_rank = sources
+            .iter()
+            .position(|s| *s == plugin.source)
+            .unwrap_or(sources.len());
+
+        (
+            !preferred,
+            source_rank,
+            plugin.position,
+            plugin.origin.as_deref(),
+        )
+    }
+
+    /// Marks the plugins which lose to another plugin of the same type as shadowed.
+    ///
+    /// Plugins of the same rank are ordered by origin, and plugins of the same origin by the
+    /// order they were installed in.
+    pub fn resolve(&self, plugins: &mut [InstalledPlugin]) {
+        // Index of the plugin taking precedence, by kind and type.
+        let mut winners: HashMap<(bool, &str), usize> = HashMap::new();
+        for (i, plugin) in plugins.iter().enumerate() {
+            let key = (plugin.plugin.is_action(), plugin.plugin.get_type());
+            match winners.get(&key) {
+                Some(&winner) if self.rank(&plugins[winner]) <= self.rank(plugin) => {}
+                _ => {
+                    winners.insert(key, i);
+                }
+            }
+        }
+
+        let shadowed_by: Vec<Option<Option<PathBuf>>> = plugins
+            .iter()
+            .enumerate()
+            .map(|(i, plugin)| {
+                let winner = winners[&(plugin.plugin.is_action(), plugin.plugin.get_type())];
+                if winner == i {
+                    None
+                } else {
+                    Some(plugins[winner].origin.clone())
+                }
+            })
+            .collect();
+
+        for (plugin, shadowed_by) in plugins.iter_mut().zip(shadowed_by) {
+            if let Some(by) = shadowed_by {
+                plugin.status = PluginStatus::Shadowed { by };
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! Sample values of JSON schemas, used to check templates rendered with data of their shape.
+
+use serde_json::Value;
+
+// Recursive definitions are cut off at this depth.
+const MAX_DEPTH: usize = 8;
+
+/// Returns a value matching a schema generated by `schemars`.
+///
+/// Enums and constants sample to their first value, strings to `"sample"`, numbers to `0`,
+/// booleans to `false` and arrays to `[]`. Optional fields are sampled as if they were set.
+pub(crate) fn sample(schema: &Value) -> Value {
+    sample_in(schema, schema, 0)
+}
+
+fn is_null(schema: &Value) -> bool {
+    schema.get("type").and_then(Value::as_str) == Some("null")
+}
+
+fn sample_in(schema: &Value, root: &Value, depth: usize) -> Value {
+    if depth > MAX_DEPTH {
+        return Value::Null;
+    }
+
+    if let Some(value) = schema.get("const") {
+        return value.clone();
+    }
+    if let Some(value) = schema.get("enum").and_then(|values| values.get(0)) {
+        return value.clone();
+    }
+
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        // References point to the definitions of the root schema, e.g. `#/definitions/Name`.
+        return reference
+            .strip_prefix('#')
+            .and_then(|pointer| root.pointer(pointer))
+            .map(|schema| sample_in(schema, root, depth + 1))
+            .unwrap_or(Value::Null);
+    }
+
+    for key in ["allOf", "anyOf", "oneOf"].iter() {
+        let variant = schema
+            .get(*key)
+            .and_then(Value::as_array)
+            .and_then(|variants| variants.iter().find(|variant| !is_null(variant)));
+        if let Some(variant) = variant {
+            return sample_in(variant, root, depth + 1);
+        }
+    }
+
+    let schema_type = match schema.get("type") {
+        Some(Value::String(schema_type)) => schema_type.as_str(),
+        Some(Value::Array(types)) => types
+            .iter()
+            .filter_map(Value::as_str)
+            .find(|schema_type| *schema_type != "null")
+            .unwrap_or("null"),
+        _ if schema.get("properties").is_some() => "object",
+        _ => "null",
+    };
+
+    match schema_type {
+        "object" => {
+            let properties = schema
+                .get("properties")
+                .and_then(Value::as_object)
+                .map(|properties| {
+                    properties
+                        .iter()
+                        .map(|(name, property)| {
+                            (name.clone(), sample_in(property, root, depth + 1))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            Value::Object(properties)
+        }
+        "array" => Value::Array(Vec::new()),
+        "string" => Value::String(String::from("sample")),
+        "integer" | "number" => Value::from(0),
+        "boolean" => Value::Bool(false),
+        _ => Value::Null,
+    }
+}
@@ -0,0 +1,778 @@
+//! Out-of-process plugins.
+//!
+//! A process plugin is an executable, written in any language, which the host spawns and talks
+//! to over its standard input and output. A crash of the plugin process doesn't take the node
+//! down: the call that was in flight fails with a transient error, and the process is restarted.
+//! A process which doesn't answer a call in time is killed, and the call fails with a timeout
+//! error. Processes failing again right after being restarted are restarted with a growing
+//! delay.
+//!
+//! # Protocol
+//!
+//! Messages are [JSON-RPC 2.0](https://www.jsonrpc.org/specification) objects, one per line.
+//! The host writes requests to the standard input of the plugin, and reads exactly one response
+//! per request from its standard output, in order. The standard error of the plugin is
+//! inherited from the host, and can be used for logging.
+//!
+//! ```text
+//! --> {"jsonrpc":"2.0","id":1,"method":"get_type","params":null}
+//! <-- {"jsonrpc":"2.0","id":1,"result":{"type":"notify","kind":"action"}}
+//! ```
+//!
+//! Methods:
+//!
+//! | method            | params                  | result                                      |
+//! |-------------------|-------------------------|---------------------------------------------|
+//! | `get_type`        | `null`                  | `{"type": <string>, "kind": "action" \| "trigger", "metadata": <PluginMetadata>?, "protocol": <integer>?}` |
+//! | `init`            | `{"settings": <value>}` | ignored                                     |
+//! | `validate_config` | action config or trigger configuration | ignored                      |
+//! | `pull_trigger`    | trigger configuration   | array of trigger data                       |
+//! | `execute_action`  | action manifest         | see below                                   |
+//! | `health`          | `null`                  | ignored                                     |
+//! | `shutdown`        | `null`                  | ignored                                     |
+//!
+//! The `protocol` a plugin describes itself with sets how the host reads the answers of
+//! `execute_action`. Plugins of protocol 1, the default, answer with any value, which the host
+//! keeps as the data of the result of the action. Plugins of protocol 2 answer with
+//! `{"result": {"summary": <string>?, "data": <value>?}}`.
+//!
+//! `init`, `validate_config` and `shutdown` are optional: plugins which don't implement them
+//! must answer with a "method not found" (`-32601`) error. `init` is called once the plugin
+//! type is known, with the settings configured for it, and again whenever the process is
+//! restarted.
+//!
+//! While handling a call made after `init`, a plugin may make requests of its own to the host,
+//! to use the state of its plugin type. The host answers them on the standard input of the
+//! plugin, before reading the response to its call:
+//!
+//! | method      | params                             | result                                  |
+//! |-------------|------------------------------------|-----------------------------------------|
+//! | `state_get` | `{"key": <string>}`                | the value stored at the key, or `null`  |
+//! | `state_set` | `{"key": <string>, "value": <value>}` | `null`                               |
+//!
+//! ```text
+//! --> {"jsonrpc":"2.0","id":3,"method":"pull_trigger","params":{...}}
+//! <-- {"jsonrpc":"2.0","id":"cursor","method":"state_get","params":{"key":"cursor"}}
+//! --> {"jsonrpc":"2.0","id":"cursor","result":42}
+//! <-- {"jsonrpc":"2.0","id":3,"result":[]}
+//! ```
+//!
+//! Failures are reported with a JSON-RPC error object. Its `data` may describe the failure,
+//! with a `kind` (`invalid_config`, `transient`, `permanent` or `timeout`, defaults to
+//! `permanent`) and a `retry_after_ms` hint:
+//!
+//! ```text
+//! <-- {"jsonrpc":"2.0","id":2,"error":{"code":1,"message":"rate limited","data":{"kind":"transient","retry_after_ms":500}}}
+//! ```
+//!
+//! Plugins must exit when their standard input is closed.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use plugin_core::{ActionPlugin, Error, ErrorKind, PluginContext, PluginMetadata, TriggerPlugin};
+
+use protocol::{ActionManifest, ActionResult, Payload, Trigger, TriggerConfiguration};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use serde_json::Value;
+
+use crate::watchdog::DEFAULT_CALL_TIMEOUT_MS;
+
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Error code of the requests of a plugin the host failed to serve.
+const SERVER_ERROR: i64 = -32000;
+
+/// How long a call into a plugin process may run when the call timeouts of the host leave the
+/// calls of its plugin type unbounded. Calls into processes are always bounded, as the process
+/// is killed when a call runs longer.
+pub(crate) const DEFAULT_CALL_TIMEOUT: Duration = Duration::from_millis(DEFAULT_CALL_TIMEOUT_MS);
+
+/// Delay before restarting a process which failed again since its last restart, doubled on
+/// every subsequent failure.
+const BASE_RESTART_DELAY: Duration = Duration::from_secs(1);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(Serialize)]
+struct Request<'a, P: Serialize> {
+    jsonrpc: &'static str,
+    id: u64,
+    method: &'a str,
+    params: P,
+}
+
+/// Request made by a plugin to the host.
+#[derive(Deserialize)]
+struct PluginRequest {
+    id: Value,
+    method: String,
+
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Deserialize)]
+struct StateParams {
+    key: String,
+
+    #[serde(default)]
+    value: Value,
+}
+
+/// Answer of the host to a [`PluginRequest`].
+#[derive(Serialize)]
+struct Reply {
+    jsonrpc: &'static str,
+    id: Value,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ReplyError>,
+}
+
+#[derive(Serialize)]
+struct ReplyError {
+    code: i64,
+    message: String,
+}
+
+impl Reply {
+    fn new(id: Value, result: Result<Value, (i64, String)>) -> Self {
+        let (result, error) = match result {
+            Ok(value) => (Some(value), None),
+            Err((code, message)) => (None, Some(ReplyError { code, message })),
+        };
+        Reply {
+            jsonrpc: "2.0",
+            id,
+            result,
+            error,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct InitParams<'a> {
+    settings: &'a Payload,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    id: u64,
+
+    #[serde(default)]
+    result: Value,
+
+    #[serde(default)]
+    error: Option<RpcError>,
+}
+
+/// JSON-RPC error object, also used by WASM plugins to report failures.
+#[derive(Deserialize)]
+pub(crate) struct RpcError {
+    code: i64,
+    message: String,
+
+    #[serde(default)]
+    data: Option<RpcErrorData>,
+}
+
+#[derive(Default, Deserialize)]
+struct RpcErrorData {
+    #[serde(default)]
+    kind: Option<String>,
+
+    #[serde(default)]
+    retry_after_ms: Option<u64>,
+}
+
+impl From<RpcError> for Error {
+    fn from(error: RpcError) -> Self {
+        let data = error.data.unwrap_or_default();
+        let kind = match data.kind.as_deref() {
+            Some("invalid_config") => ErrorKind::InvalidConfig,
+            Some("transient") => ErrorKind::Transient,
+            Some("timeout") => ErrorKind::Timeout,
+            _ => ErrorKind::Permanent,
+        };
+
+        let mut plugin_error = Error::new(kind, error.message);
+        if let Some(retry_after) = data.retry_after_ms {
+            plugin_error = plugin_error.with_retry_after(Duration::from_millis(retry_after));
+        }
+        plugin_error
+    }
+}
+
+/// Kind of plugin implemented by a process or a WASM module.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum DescribedKind {
+    Action,
+    Trigger,
+}
+
+/// Latest version of the protocol. See the module documentation.
+const PROTOCOL_VERSION: u32 = 2;
+
+fn legacy_protocol() -> u32 {
+    1
+}
+
+/// Answer to `get_type`.
+#[derive(Deserialize)]
+pub(crate) struct PluginDescription {
+    #[serde(rename = "type")]
+    pub plugin_type: String,
+    pub kind: DescribedKind,
+
+    #[serde(default)]
+    pub metadata: Option<PluginMetadata>,
+
+    /// Version of the protocol the plugin speaks.
+    #[serde(default = "legacy_protocol")]
+    pub protocol: u32,
+}
+
+impl PluginDescription {
+    /// Ensures the host speaks the protocol of the plugin.
+    pub fn check_protocol(&self) -> Result<(), Error> {
+        if self.protocol > PROTOCOL_VERSION {
+            return Err(Error::permanent(format!(
+                "unsupported protocol version {}, the host speaks up to {}",
+                self.protocol, PROTOCOL_VERSION
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns the metadata of the plugin, defaulting to its type as name.
+    pub fn metadata(&self) -> PluginMetadata {
+        self.metadata
+            .clone()
+            .unwrap_or_else(|| PluginMetadata::new(self.plugin_type.as_str()))
+    }
+}
+
+struct PluginProcess {
+    child: Child,
+    stdin: ChildStdin,
+
+    // Lines written by the process, read on a thread of their own so reads can time out.
+    lines: Receiver<io::Result<String>>,
+}
+
+impl PluginProcess {
+    fn spawn(command: &Path) -> io::Result<PluginProcess> {
+        let mut child = Command::new(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()?;
+
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+
+        // The reader stops once the process closes its output, or once the process is dropped.
+        let (tx, lines) = mpsc::channel();
+        let reader = thread::Builder::new()
+            .name(format!("plugin-process-{}", child.id()))
+            .spawn(move || {
+                for line in stdout.lines() {
+                    let failed = line.is_err();
+                    if tx.send(line).is_err() || failed {
+                        break;
+                    }
+                }
+            });
+        if let Err(e) = reader {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(e);
+        }
+
+        Ok(PluginProcess {
+            child,
+            stdin,
+            lines,
+        })
+    }
+
+    fn send<M: Serialize>(&mut self, message: &M) -> io::Result<()> {
+        let mut line = serde_json::to_string(message)?;
+        line.push('\n');
+        self.stdin.write_all(line.as_bytes())?;
+        self.stdin.flush()
+    }
+
+    /// Reads the next line written by the process, waiting until `deadline` at most.
+    fn read_line(&self, deadline: Instant) -> io::Result<String> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match self.lines.recv_timeout(timeout) {
+            Ok(line) => line,
+            Err(RecvTimeoutError::Timeout) => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("plugin process didn't answer within {:?}", timeout),
+            )),
+            Err(RecvTimeoutError::Disconnected) => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "plugin process closed its output",
+            )),
+        }
+    }
+
+    /// Sends a request, and waits for its response until `deadline`. The requests the plugin
+    /// makes meanwhile are served from its context, if it has one.
+    fn exchange<P: Serialize>(
+        &mut self,
+        id: u64,
+        method: &str,
+        params: P,
+        deadline: Instant,
+        context: Option<&PluginContext>,
+    ) -> io::Result<Response> {
+        self.send(&Request {
+            jsonrpc: "2.0",
+            id,
+            method,
+            params,
+        })?;
+
+        loop {
+            let message: Value = serde_json::from_str(&self.read_line(deadline)?)?;
+            if message.get("method").is_some() {
+                let request: PluginRequest = serde_json::from_value(message)?;
+                let reply = serve(context, request);
+                self.send(&reply)?;
+                continue;
+            }
+
+            let response: Response = serde_json::from_value(message)?;
+            if response.id != id {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("expected response {}, got {}", id, response.id),
+                ));
+            }
+
+            return Ok(response);
+        }
+    }
+}
+
+impl Drop for PluginProcess {
+    fn drop(&mut self) {
+        // Killing fails if the process already exited, which is what we want anyway.
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Answers a request of a plugin.
+fn serve(context: Option<&PluginContext>, request: PluginRequest) -> Reply {
+    let result = match (request.method.as_str(), context) {
+        ("state_get" | "state_set", None) => Err((
+            SERVER_ERROR,
+            String::from("state is only available once the plugin is initialized"),
+        )),
+        ("state_get", Some(ctx)) => serde_json::from_value::<StateParams>(request.params)
+            .map_err(|e| (SERVER_ERROR, e.to_string()))
+            .and_then(|params| {
+                ctx.state
+                    .get::<Value>(&params.key)
+                    .map(Option::unwrap_or_default)
+                    .map_err(|e| (SERVER_ERROR, e.to_string()))
+            }),
+        ("state_set", Some(ctx)) => serde_json::from_value::<StateParams>(request.params)
+            .map_err(|e| (SERVER_ERROR, e.to_string()))
+            .and_then(|params| {
+                ctx.state
+                    .set(&params.key, &params.value)
+                    .map(|_| Value::Null)
+                    .map_err(|e| (SERVER_ERROR, e.to_string()))
+            }),
+        (method, _) => Err((METHOD_NOT_FOUND, format!("unknown method: {}", method))),
+    };
+
+    Reply::new(request.id, result)
+}
+
+/// Reads the response to a call of a method the plugin isn't required to implement.
+fn optional(response: Response) -> Result<(), Error> {
+    match response.error {
+        Some(error) if error.code == METHOD_NOT_FOUND => Ok(()),
+        Some(error) => Err(Error::from(error)),
+        None => Ok(()),
+    }
+}
+
+/// Returns how long to wait before restarting a process which failed `failures` times in a row.
+/// A process is restarted right away after its first failure.
+fn restart_delay(failures: u32) -> Duration {
+    match failures {
+        0 | 1 => Duration::ZERO,
+        n => BASE_RESTART_DELAY
+            .saturating_mul(2u32.saturating_pow(n - 2))
+            .min(MAX_RESTART_DELAY),
+    }
+}
+
+/// Process of a [`ProcessClient`], along with its failures.
+struct ProcessState {
+    // `None` once the process failed, until it is restarted.
+    process: Option<PluginProcess>,
+
+    // Failures since the last successful call.
+    failures: u32,
+    restart_at: Option<Instant>,
+}
+
+/// Connection to a plugin process, restarting it when it fails.
+pub(crate) struct ProcessClient {
+    command: PathBuf,
+    state: Mutex<ProcessState>,
+    next_id: AtomicU64,
+    restarts: AtomicU64,
+    call_timeout_ms: AtomicU64,
+
+    // Set once the plugin is initialized.
+    context: OnceLock<PluginContext>,
+}
+
+impl ProcessClient {
+    pub fn spawn(command: &Path) -> io::Result<ProcessClient> {
+        Ok(ProcessClient {
+            command: PathBuf::from(command),
+            state: Mutex::new(ProcessState {
+                process: Some(PluginProcess::spawn(command)?),
+                failures: 0,
+                restart_at: None,
+            }),
+            next_id: AtomicU64::new(1),
+            restarts: AtomicU64::new(0),
+            call_timeout_ms: AtomicU64::new(DEFAULT_CALL_TIMEOUT.as_millis() as u64),
+            context: OnceLock::new(),
+        })
+    }
+
+    pub fn command(&self) -> &Path {
+        &self.command
+    }
+
+    /// Returns how many times the process was restarted successfully.
+    pub fn restarts(&self) -> u64 {
+        self.restarts.load(Ordering::SeqCst)
+    }
+
+    /// Sets how long calls may run before the process is killed.
+    pub fn set_call_timeout(&self, timeout: Duration) {
+        self.call_timeout_ms
+            .store(timeout.as_millis() as u64, Ordering::SeqCst);
+    }
+
+    fn deadline(&self) -> Instant {
+        Instant::now() + Duration::from_millis(self.call_timeout_ms.load(Ordering::SeqCst))
+    }
+
+    fn state(&self) -> MutexGuard<'_, ProcessState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Initializes the plugin with its context: its settings are passed to `init`, and its state
+    /// is served to the process from then on.
+    pub fn init(&self, ctx: &PluginContext) -> Result<(), Error> {
+        if self.context.set(ctx.clone()).is_err() {
+            return Err(Error::permanent("plugin process is already initialized"));
+        }
+        optional(self.request(
+            "init",
+            InitParams {
+                settings: &ctx.settings,
+            },
+        )?)
+    }
+
+    /// Sends a request to the plugin, and returns its response.
+    ///
+    /// If the process crashed, broke the protocol or didn't answer in time, it is killed and the
+    /// request fails with a transient or timeout error. The process is restarted by the next
+    /// request.
+    fn request<P: Serialize>(&self, method: &str, params: P) -> Result<Response, Error> {
+        let mut state = self.state();
+
+        if state.process.is_none() {
+            let process = self.restart(&mut state)?;
+            state.process = Some(process);
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let deadline = self.deadline();
+        let exchange = state
+            .process
+            .as_mut()
+            .expect("process was started")
+            .exchange(id, method, params, deadline, self.context.get());
+
+        match exchange {
+            Ok(response) => {
+                state.failures = 0;
+                Ok(response)
+            }
+            Err(e) => {
+                log::error!(
+                    "plugin process {} failed, killing it: {}",
+                    self.command.display(),
+                    e
+                );
+                state.process = None;
+                self.failed(&mut state);
+
+                let error = if e.kind() == io::ErrorKind::TimedOut {
+                    Error::timeout(format!(
+                        "plugin process {} timed out",
+                        self.command.display()
+                    ))
+                } else {
+                    Error::transient(format!("plugin process {} failed", self.command.display()))
+                };
+                Err(error.with_source(e))
+            }
+        }
+    }
+
+    fn failed(&self, state: &mut ProcessState) {
+        state.failures += 1;
+        state.restart_at = Some(Instant::now() + restart_delay(state.failures));
+    }
+
+    /// Restarts the process once its restart delay is over, and initializes it again.
+    fn restart(&self, state: &mut ProcessState) -> Result<PluginProcess, Error> {
+        if let Some(restart_at) = state.restart_at {
+            let now = Instant::now();
+            if now < restart_at {
+                return Err(Error::transient(format!(
+                    "plugin process {} is waiting to be restarted",
+                    self.command.display()
+                ))
+                .with_retry_after(restart_at - now));
+            }
+        }
+
+        let restarted = PluginProcess::spawn(&self.command)
+            .map_err(|e| {
+                Error::transient(format!(
+                    "failed to restart plugin process {}",
+                    self.command.display()
+                ))
+                .with_source(e)
+            })
+            .and_then(|process| self.reinit(process));
+
+        match restarted {
+            Ok(process) => {
+                self.restarts.fetch_add(1, Ordering::SeqCst);
+                log::info!("restarted plugin process {}", self.command.display());
+                Ok(process)
+            }
+            Err(e) => {
+                log::error!(
+                    "failed to restart plugin process {}: {}",
+                    self.command.display(),
+                    e
+                );
+                self.failed(state);
+                Err(e)
+            }
+        }
+    }
+
+    /// Initializes a restarted process, if the plugin was initialized before.
+    fn reinit(&self, mut process: PluginProcess) -> Result<PluginProcess, Error> {
+        let ctx = match self.context.get() {
+            Some(ctx) => ctx,
+            None => return Ok(process),
+        };
+
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let response = process
+            .exchange(
+                id,
+                "init",
+                InitParams {
+                    settings: &ctx.settings,
+                },
+                self.deadline(),
+                Some(ctx),
+            )
+            .map_err(|e| {
+                Error::transient(format!(
+                    "failed to initialize plugin process {}",
+                    self.command.display()
+                ))
+                .with_source(e)
+            })?;
+        optional(response)?;
+
+        Ok(process)
+    }
+
+    /// Calls a method of the plugin.
+    pub fn call<P: Serialize, R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: P,
+    ) -> Result<R, Error> {
+        let response = self.request(method, params)?;
+        match response.error {
+            Some(error) => Err(Error::from(error)),
+            None => serde_json::from_value(response.result).map_err(|e| {
+                Error::permanent(format!("invalid [{}] result", method)).with_source(e)
+            }),
+        }
+    }
+
+    /// Calls a method the plugin isn't required to implement.
+    pub fn call_optional<P: Serialize>(&self, method: &str, params: P) -> Result<(), Error> {
+        optional(self.request(method, params)?)
+    }
+
+    /// Checks the plugin process responds.
+    pub fn health(&self) -> Result<(), Error> {
+        self.call::<_, Value>("health", Value::Null).map(|_| ())
+    }
+}
+
+/// Answer to `execute_action`, from protocol 2 on.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ActionAnswer {
+    result: ActionResult,
+}
+
+/// Reads the answer to an `execute_action` call, as the protocol of the plugin defines it.
+pub(crate) fn action_result(protocol: u32, value: Value) -> Result<ActionResult, Error> {
+    if protocol < 2 {
+        // Null is kept as null data, which is the empty result.
+        return Ok(ActionResult::new(Payload::new(value)));
+    }
+
+    serde_json::from_value::<ActionAnswer>(value)
+        .map(|answer| answer.result)
+        .map_err(|e| Error::permanent("invalid [execute_action] result").with_source(e))
+}
+
+/// Action plugin implemented by a process.
+pub(crate) struct ProcessAction {
+    client: Arc<ProcessClient>,
+    plugin_type: String,
+    metadata: PluginMetadata,
+    protocol: u32,
+}
+
+impl ActionPlugin for ProcessAction {
+    fn get_type(&self) -> &str {
+        &self.plugin_type
+    }
+
+    fn metadata(&self) -> PluginMetadata {
+        self.metadata.clone()
+    }
+
+    fn validate_config(&self, config: &Payload) -> Result<(), Error> {
+        self.client.call_optional("validate_config", config)
+    }
+
+    fn execute_action(&self, manifest: ActionManifest) -> Result<(), Error> {
+        self.execute_with_result(manifest).map(|_| ())
+    }
+
+    fn execute_with_result(&self, manifest: ActionManifest) -> Result<ActionResult, Error> {
+        let answer = self.client.call("execute_action", &manifest)?;
+        action_result(self.protocol, answer)
+    }
+
+    fn shutdown(&self) -> Result<(), Error> {
+        self.client.call_optional("shutdown", Value::Null)
+    }
+}
+
+/// Trigger plugin implemented by a process.
+pub(crate) struct ProcessTrigger {
+    client: Arc<ProcessClient>,
+    plugin_type: String,
+    metadata: PluginMetadata,
+}
+
+impl TriggerPlugin for ProcessTrigger {
+    fn get_type(&self) -> &str {
+        &self.plugin_type
+    }
+
+    fn metadata(&self) -> PluginMetadata {
+        self.metadata.clone()
+    }
+
+    fn validate_config(&self, cfg: &TriggerConfiguration) -> Result<(), Error> {
+        self.client.call_optional("validate_config", cfg)
+    }
+
+    fn pull_trigger(&self, cfg: &TriggerConfiguration) -> Result<Vec<Trigger>, Error> {
+        let data: Vec<Value> = self.client.call("pull_trigger", cfg)?;
+        Ok(data
+            .into_iter()
+            .map(|data| Trigger::new(cfg.rule.clone(), cfg.trigger_type.clone(), data.into()))
+            .collect())
+    }
+
+    fn shutdown(&self) -> Result<(), Error> {
+        self.client.call_optional("shutdown", Value::Null)
+    }
+}
+
+/// Plugin implemented by a process, as seen by the host.
+pub(crate) enum ProcessPlugin {
+    Action(ProcessAction),
+    Trigger(ProcessTrigger),
+}
+
+impl ProcessPlugin {
+    pub fn get_type(&self) -> &str {
+        match self {
+            ProcessPlugin::Action(action) => action.get_type(),
+            ProcessPlugin::Trigger(trigger) => trigger.get_type(),
+        }
+    }
+
+    /// Asks a freshly spawned process which plugin it implements.
+    pub fn describe(client: Arc<ProcessClient>) -> Result<ProcessPlugin, Error> {
+        let description: PluginDescription = client.call("get_type", Value::Null)?;
+        description.check_protocol()?;
+        let metadata = description.metadata();
+        let plugin_type = description.plugin_type;
+
+        Ok(match description.kind {
+            DescribedKind::Action => ProcessPlugin::Action(ProcessAction {
+                client,
+                plugin_type,
+                metadata,
+                protocol: description.protocol,
+            }),
+            DescribedKind::Trigger => ProcessPlugin::Trigger(ProcessTrigger {
+                client,
+                plugin_type,
+                metadata,
+            }),
+        })
+    }
+}
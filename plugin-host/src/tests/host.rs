@@ -1,30 +1,819 @@
+use std::collections::HashMap;
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
 use std::fs;
-use std::io::Write;
-use std::path::PathBuf;
+use std::os::raw::c_char;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Once};
+use std::time::{Duration, SystemTime};
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+use plugin_core::abi::{CORE_VERSION_CSTR, RUSTC_VERSION_CSTR};
+use plugin_core::{
+    ActionPlugin, Error as PlugError, Plugin, PluginContext, PluginDeclaration, PluginMetadata,
+    TriggerPlugin, ABI_VERSION,
+};
+
+use protocol::{ActionManifest, Payload, Rule, RuleAction, Trigger, TriggerConfiguration};
+
+use serde::Deserialize;
+
+use serde_json::json;
 
 use tempfile::tempdir;
 
-use crate::PluginHost;
+use toolkit::db::sled::SledStore;
 
-#[test]
-fn plugin_loading() {
-    #[cfg(target_os = "linux")]
-    const PLUGIN_SO_DATA: &[u8] = include_bytes!("test_data/libdirectory_watch.so");
+use crate::host::{check_declaration, Error};
+use crate::{
+    sha256_hex, AllowedPlugin, ManifestError, PluginHost, PluginHostConfig, PluginInfo, PluginKind,
+    PluginManifest, PluginStatus,
+};
 
-    #[cfg(target_os = "macos")]
-    const PLUGIN_SO_DATA: &[u8] = include_bytes!("test_data/libdirectory_watch.dylib");
+static TEST_LIBRARY: Once = Once::new();
+
+/// Returns the path of the `directory_watch` library, built from source by the toolchain of
+/// the tests so its compiler version matches.
+///
+/// Like `plugin_testkit::build_library`, which can't be used here as it depends on this crate.
+fn test_library() -> PathBuf {
+    let exe = std::env::current_exe().unwrap();
+    let target_dir = exe.ancestors().nth(3).unwrap().join("test-plugins");
+
+    TEST_LIBRARY.call_once(|| {
+        let cargo = std::env::var("CARGO").unwrap_or_else(|_| String::from("cargo"));
+        let status = Command::new(cargo)
+            .args(["build", "--package", "directory_watch", "--target-dir"])
+            .arg(&target_dir)
+            .current_dir(env!("CARGO_MANIFEST_DIR"))
+            .status()
+            .unwrap();
+        assert!(status.success(), "failed to build the test library");
+    });
+
+    target_dir
+        .join("debug")
+        .join(format!("{}directory_watch{}", DLL_PREFIX, DLL_SUFFIX))
+}
+
+fn test_manifest(plugin_path: &Path) -> PluginManifest {
+    PluginManifest::for_library(
+        plugin_path,
+        "directory_watch",
+        "0.1.0",
+        &["directory_watch"],
+    )
+    .unwrap()
+}
 
+/// Allowlist pinning the test plugin.
+fn test_allowlist() -> Option<Vec<AllowedPlugin>> {
+    Some(vec![AllowedPlugin::pinned(&test_manifest(&test_library()))])
+}
+
+/// Writes the test plugin to a file, along with its manifest.
+fn write_plugin(plugin_path: &Path) {
+    fs::copy(test_library(), plugin_path).unwrap();
+    test_manifest(plugin_path).write(plugin_path).unwrap();
+}
+
+#[test]
+fn plugin_loading() {
     // Write the plugin to a temp file.
     let temp_dir = tempdir().unwrap();
     let plugin_path = temp_dir.path().join("plugin.so");
-    {
-        let mut plugin_file = fs::File::create(&plugin_path).unwrap();
-        plugin_file.write_all(PLUGIN_SO_DATA).unwrap();
-    }
+    write_plugin(&plugin_path);
 
     // Create a plugin host.
-    let search_paths = vec![PathBuf::from(temp_dir.path())];
-    let host = PluginHost::initialize(&search_paths).unwrap();
+    let host = PluginHost::initialize(PluginHostConfig {
+        search_paths: vec![PathBuf::from(temp_dir.path())],
+        allowlist: test_allowlist(),
+        ..Default::default()
+    })
+    .unwrap();
     assert_eq!(host.get_action_plugins().len(), 0);
     assert_eq!(host.get_trigger_plugins().len(), 1);
+
+    let plugins = host.list_plugins();
+    assert_eq!(plugins.len(), 1);
+    assert_eq!(plugins[0].plugin_type, "directory_watch");
+    assert_eq!(plugins[0].kind, PluginKind::Trigger);
+    assert_eq!(plugins[0].library, Some(plugin_path));
+    assert_eq!(plugins[0].metadata.name, "directory_watch");
+
+    let config_schema = plugins[0].metadata.config_schema.as_ref().unwrap();
+    assert!(config_schema["properties"].get("directory").is_some());
+    assert!(plugins[0].metadata.data_schema.is_some());
+}
+
+struct InMemoryAction;
+
+impl ActionPlugin for InMemoryAction {
+    fn execute_action(&self, _manifest: ActionManifest) -> Result<(), PlugError> {
+        Ok(())
+    }
+
+    fn get_type(&self) -> &str {
+        "in_memory"
+    }
+}
+
+#[test]
+fn in_memory_metadata() {
+    let mut host = PluginHost::default();
+    host.add_in_memory_action_plugin(Box::new(InMemoryAction))
+        .unwrap();
+
+    let plugins = host.list_plugins();
+    assert_eq!(plugins.len(), 1);
+    assert_eq!(plugins[0].kind, PluginKind::Action);
+    assert_eq!(plugins[0].library, None);
+    assert_eq!(plugins[0].metadata, PluginMetadata::new("in_memory"));
+
+    assert!(host.get_action_metadata("in_memory").is_some());
+    assert!(host.get_action_metadata("notify").is_none());
+    assert!(host.get_trigger_metadata("in_memory").is_none());
+}
+
+unsafe extern "C" fn null_init() -> *mut Plugin {
+    std::ptr::null_mut()
+}
+
+fn declaration(
+    abi_version: u32,
+    rustc_version: &'static str,
+    core_version: &'static str,
+) -> PluginDeclaration {
+    PluginDeclaration {
+        abi_version,
+        rustc_version: rustc_version.as_ptr() as *const c_char,
+        core_version: core_version.as_ptr() as *const c_char,
+        init: null_init,
+        set_logger: plugin_core::logging::install,
+    }
+}
+
+#[test]
+fn handshake_compatible() {
+    let decl = declaration(ABI_VERSION, RUSTC_VERSION_CSTR, CORE_VERSION_CSTR);
+    assert!(check_declaration(&decl).is_ok());
+}
+
+#[test]
+fn handshake_abi_mismatch() {
+    let decl = declaration(ABI_VERSION + 1, RUSTC_VERSION_CSTR, CORE_VERSION_CSTR);
+    assert!(matches!(
+        check_declaration(&decl),
+        Err(Error::IncompatibleAbi { .. })
+    ));
+}
+
+#[test]
+fn handshake_compiler_mismatch() {
+    let decl = declaration(
+        ABI_VERSION,
+        "rustc 1.0.0 (a59de37e9 2015-05-13)\0",
+        CORE_VERSION_CSTR,
+    );
+    assert!(matches!(
+        check_declaration(&decl),
+        Err(Error::IncompatibleCompiler { .. })
+    ));
+}
+
+#[test]
+fn handshake_core_mismatch() {
+    let decl = declaration(ABI_VERSION, RUSTC_VERSION_CSTR, "0.0.0\0");
+    assert!(matches!(
+        check_declaration(&decl),
+        Err(Error::IncompatibleCore { .. })
+    ));
+}
+
+/// Trigger plugin describing its data, without emitting any.
+struct FileTrigger {
+    data_schema: Option<serde_json::Value>,
+}
+
+impl TriggerPlugin for FileTrigger {
+    fn get_type(&self) -> &str {
+        if self.data_schema.is_some() {
+            "file"
+        } else {
+            "opaque"
+        }
+    }
+
+    fn pull_trigger(&self, _cfg: &TriggerConfiguration) -> Result<Vec<Trigger>, PlugError> {
+        Ok(Vec::new())
+    }
+
+    fn metadata(&self) -> PluginMetadata {
+        PluginMetadata {
+            data_schema: self.data_schema.clone(),
+            ..PluginMetadata::new(self.get_type())
+        }
+    }
+}
+
+/// Action plugin requiring a string `name` and an integer `size`.
+struct SizedAction;
+
+impl ActionPlugin for SizedAction {
+    fn execute_action(&self, _manifest: ActionManifest) -> Result<(), PlugError> {
+        Ok(())
+    }
+
+    fn get_type(&self) -> &str {
+        "sized"
+    }
+
+    fn validate_config(&self, config: &Payload) -> Result<(), PlugError> {
+        if config["name"].is_string() && config["size"].is_u64() {
+            Ok(())
+        } else {
+            Err(PlugError::invalid_config("name and size are required"))
+        }
+    }
+}
+
+#[test]
+fn validate_rule() {
+    let mut host = PluginHost::default();
+    host.add_in_memory_action_plugin(Box::new(SizedAction))
+        .unwrap();
+    host.add_in_memory_trigger_plugin(Box::new(FileTrigger {
+        data_schema: Some(json!({
+            "type": "object",
+            "properties": {
+                "file_name": {"type": "string"},
+                "size": {"type": "integer"}
+            }
+        })),
+    }))
+    .unwrap();
+    host.add_in_memory_trigger_plugin(Box::new(FileTrigger { data_schema: None }))
+        .unwrap();
+
+    let rule = |action_type: &str, action_config: &str| Rule {
+        trigger_config_id: 1,
+        actions: vec![RuleAction {
+            action_type: String::from(action_type),
+            action_config: String::from(action_config),
+        }],
+        condition: None,
+    };
+
+    // Templates are validated once rendered, so placeholders can stand for any JSON value.
+    let valid = rule("sized", r#"{"name": "{{file_name}}", "size": {{size}}}"#);
+    assert!(host.validate_rule(&valid, "file").is_ok());
+    assert!(host.validate_rule(&valid, "opaque").is_ok());
+
+    assert!(matches!(
+        host.validate_rule(&rule("sized", r#"{"name": "{{file_name}}"}"#), "file"),
+        Err(Error::InvalidConfig { .. })
+    ));
+    assert!(matches!(
+        host.validate_rule(
+            &rule("sized", r#"{"name": {{file_name}}, "size": 1}"#),
+            "file"
+        ),
+        Err(Error::InvalidTemplate { .. })
+    ));
+    assert!(matches!(
+        host.validate_rule(&rule("sized", r#"{"name": "{{#if size}}"}"#), "opaque"),
+        Err(Error::InvalidTemplate { .. })
+    ));
+    assert!(matches!(
+        host.validate_rule(&rule("notify", "{}"), "file"),
+        Err(Error::UnknownPluginType { .. })
+    ));
+    assert!(matches!(
+        host.validate_rule(&valid, "directory_watch"),
+        Err(Error::UnknownPluginType { .. })
+    ));
+}
+
+#[derive(Default)]
+struct LifecycleAction {
+    greeting: String,
+    shut_down: Arc<AtomicBool>,
+}
+
+#[derive(Default, Deserialize)]
+struct LifecycleSettings {
+    greeting: String,
+}
+
+impl ActionPlugin for LifecycleAction {
+    fn execute_action(&self, _manifest: ActionManifest) -> Result<(), PlugError> {
+        Ok(())
+    }
+
+    fn get_type(&self) -> &str {
+        "lifecycle"
+    }
+
+    fn init(&mut self, ctx: &PluginContext) -> Result<(), PlugError> {
+        let settings: LifecycleSettings = ctx.settings()?;
+        if settings.greeting.is_empty() {
+            return Err(PlugError::invalid_config(String::from("missing greeting")));
+        }
+        self.greeting = settings.greeting;
+        Ok(())
+    }
+
+    fn shutdown(&self) -> Result<(), PlugError> {
+        self.shut_down.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[test]
+fn lifecycle() {
+    let mut settings = HashMap::new();
+    settings.insert(
+        String::from("lifecycle"),
+        Payload::from(json!({"greeting": "bing bong"})),
+    );
+    let mut host = PluginHost::initialize(PluginHostConfig {
+        settings,
+        ..Default::default()
+    })
+    .unwrap();
+
+    let shut_down = Arc::new(AtomicBool::new(false));
+    host.add_in_memory_action_plugin(Box::new(LifecycleAction {
+        greeting: String::new(),
+        shut_down: shut_down.clone(),
+    }))
+    .unwrap();
+
+    assert!(!shut_down.load(Ordering::SeqCst));
+    host.shutdown();
+    assert!(shut_down.load(Ordering::SeqCst));
+}
+
+#[test]
+fn lifecycle_init_failure() {
+    let mut host = PluginHost::default();
+    assert!(matches!(
+        host.add_in_memory_action_plugin(Box::new(LifecycleAction::default())),
+        Err(Error::InitPlugin { .. })
+    ));
+    assert!(host.list_plugins().is_empty());
+}
+
+#[test]
+fn persistent_state() {
+    let temp_dir = tempdir().unwrap();
+    let plugin_dir = temp_dir.path().join("plugins");
+    let watched_dir = temp_dir.path().join("watched");
+    fs::create_dir(&plugin_dir).unwrap();
+    fs::create_dir(&watched_dir).unwrap();
+    write_plugin(&plugin_dir.join("plugin.so"));
+
+    let cfg = TriggerConfiguration {
+        id: 1,
+        rule: "1".into(),
+        trigger_type: String::from("directory_watch"),
+        data: json!({ "directory": watched_dir }).into(),
+        schedule: Default::default(),
+    };
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+
+    let start_host = || {
+        PluginHost::initialize(PluginHostConfig {
+            search_paths: vec![plugin_dir.clone()],
+            allowlist: test_allowlist(),
+            state_store: Some(Arc::new(
+                SledStore::new(temp_dir.path().join("state")).unwrap(),
+            )),
+            ..Default::default()
+        })
+        .unwrap()
+    };
+
+    {
+        // First run snapshots the directory.
+        let host = start_host();
+        let watcher = host.get_trigger_plugins().pop().unwrap();
+        assert!(runtime
+            .block_on(watcher.pull_trigger(&cfg))
+            .unwrap()
+            .is_empty());
+        host.shutdown();
+    }
+
+    // A file arrives while the node is down.
+    fs::write(watched_dir.join("some_file.txt"), "bing bong").unwrap();
+
+    let host = start_host();
+    let watcher = host.get_trigger_plugins().pop().unwrap();
+    let triggers = runtime.block_on(watcher.pull_trigger(&cfg)).unwrap();
+    assert_eq!(triggers.len(), 1);
+    assert_eq!(
+        triggers[0].data,
+        json!({"file_name": "some_file.txt"}).into()
+    );
+}
+
+/// Logger keeping the messages of every record. It is installed once for all the tests, which
+/// only look at the records of their own rule.
+struct CaptureLogger {
+    messages: Mutex<Vec<String>>,
+}
+
+impl Log for CaptureLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        self.messages
+            .lock()
+            .unwrap()
+            .push(record.args().to_string());
+    }
+
+    fn flush(&self) {}
+}
+
+static CAPTURE_LOGGER: CaptureLogger = CaptureLogger {
+    messages: Mutex::new(Vec::new()),
+};
+
+fn install_capture_logger() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        log::set_logger(&CAPTURE_LOGGER).unwrap();
+        log::set_max_level(LevelFilter::Info);
+    });
+}
+
+/// Returns the messages logged by plugins while processing the given rule.
+fn captured_logs(rule: &str) -> Vec<String> {
+    let tag = format!(" rule={}] ", rule);
+    CAPTURE_LOGGER
+        .messages
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|m| m.contains(&tag))
+        .cloned()
+        .collect()
+}
+
+#[test]
+fn plugin_logs_are_forwarded() {
+    install_capture_logger();
+
+    let temp_dir = tempdir().unwrap();
+    let plugin_dir = temp_dir.path().join("plugins");
+    fs::create_dir(&plugin_dir).unwrap();
+    write_plugin(&plugin_dir.join("plugin.so"));
+
+    let host = PluginHost::initialize(PluginHostConfig {
+        search_paths: vec![plugin_dir],
+        allowlist: test_allowlist(),
+        ..Default::default()
+    })
+    .unwrap();
+
+    let cfg = TriggerConfiguration {
+        id: 1,
+        rule: "plugin_logs_are_forwarded".into(),
+        trigger_type: String::from("directory_watch"),
+        data: json!({ "directory": temp_dir.path() }).into(),
+        schedule: Default::default(),
+    };
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let watcher = host.get_trigger_plugins().pop().unwrap();
+    runtime.block_on(watcher.pull_trigger(&cfg)).unwrap();
+
+    let messages = captured_logs("plugin_logs_are_forwarded");
+    assert!(messages
+        .iter()
+        .any(|m| m.starts_with("[directory_watch rule=plugin_logs_are_forwarded] watching ")));
+}
+
+#[test]
+fn hot_reload() {
+    let temp_dir = tempdir().unwrap();
+    let plugin_path = temp_dir.path().join("plugin.so");
+
+    let host = PluginHost::initialize(PluginHostConfig {
+        search_paths: vec![PathBuf::from(temp_dir.path())],
+        allowlist: test_allowlist(),
+        ..Default::default()
+    })
+    .unwrap();
+    assert!(host.get_trigger_plugins().is_empty());
+    assert!(!host.reload().unwrap());
+
+    let shadow_copies = |host: &PluginHost| {
+        let shadow_dir = host.shadow_dir.lock().unwrap();
+        fs::read_dir(shadow_dir.as_ref().unwrap().path())
+            .unwrap()
+            .count()
+    };
+
+    // New library.
+    write_plugin(&plugin_path);
+    let generation = host.generation();
+    assert!(host.reload().unwrap());
+    assert!(host.generation() > generation);
+    let previous = host.get_trigger_plugins().pop().unwrap();
+
+    // Unchanged library.
+    assert!(!host.reload().unwrap());
+
+    // Libraries failing to load are reported once.
+    fs::write(temp_dir.path().join("broken.so"), "bing bong").unwrap();
+    assert!(!host.reload().unwrap());
+    assert_eq!(host.rejected_libraries.lock().unwrap().len(), 1);
+
+    // Modified library. The previous version stays loaded as long as it is used.
+    fs::File::options()
+        .write(true)
+        .open(&plugin_path)
+        .unwrap()
+        .set_modified(SystemTime::now() + Duration::from_secs(10))
+        .unwrap();
+    assert!(host.reload().unwrap());
+    let current = host.get_trigger_plugins().pop().unwrap();
+    assert!(!Arc::ptr_eq(&previous, &current));
+    assert_eq!(host.retired_plugins.lock().unwrap().len(), 1);
+    assert_eq!(shadow_copies(&host), 2);
+
+    let cfg = TriggerConfiguration {
+        id: 1,
+        rule: "1".into(),
+        trigger_type: String::from("directory_watch"),
+        data: json!({ "directory": temp_dir.path() }).into(),
+        schedule: Default::default(),
+    };
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    assert!(runtime.block_on(previous.pull_trigger(&cfg)).is_ok());
+
+    // The previous version is dropped once unused, along with its copy.
+    drop(previous);
+    assert!(!host.reload().unwrap());
+    assert!(host.retired_plugins.lock().unwrap().is_empty());
+    assert_eq!(shadow_copies(&host), 1);
+
+    // Removed library.
+    drop(current);
+    fs::remove_file(&plugin_path).unwrap();
+    assert!(host.reload().unwrap());
+    assert!(host.get_trigger_plugins().is_empty());
+    assert!(!host.reload().unwrap());
+    assert_eq!(shadow_copies(&host), 0);
+}
+
+#[test]
+fn added_libraries_survive_reload() {
+    let search_dir = tempdir().unwrap();
+    let temp_dir = tempdir().unwrap();
+    let plugin_path = temp_dir.path().join("plugin.so");
+    fs::copy(test_library(), &plugin_path).unwrap();
+
+    let host = PluginHost::initialize(PluginHostConfig {
+        search_paths: vec![PathBuf::from(search_dir.path())],
+        allowlist: test_allowlist(),
+        ..Default::default()
+    })
+    .unwrap();
+    host.add_plugin(&plugin_path).unwrap();
+    assert_eq!(host.get_trigger_plugins().len(), 1);
+
+    // Libraries added outside of the search paths aren't retired by a reload.
+    assert!(!host.reload().unwrap());
+    assert_eq!(host.get_trigger_plugins().len(), 1);
+    assert!(host.retired_plugins.lock().unwrap().is_empty());
+}
+
+#[test]
+fn untrusted_libraries() {
+    let temp_dir = tempdir().unwrap();
+    let plugin_path = temp_dir.path().join("plugin.so");
+    let start_host = |allowlist: Option<Vec<AllowedPlugin>>| {
+        PluginHost::initialize(PluginHostConfig {
+            search_paths: vec![PathBuf::from(temp_dir.path())],
+            allowlist,
+            ..Default::default()
+        })
+        .unwrap()
+    };
+    let load = |host: &PluginHost| host.load_library(&plugin_path, false).map(|_| ());
+
+    // Libraries without a manifest are refused.
+    fs::copy(test_library(), &plugin_path).unwrap();
+    let host = start_host(test_allowlist());
+    assert!(host.get_trigger_plugins().is_empty());
+    assert!(matches!(
+        load(&host),
+        Err(Error::UntrustedLibrary {
+            source: ManifestError::ReadManifest { .. },
+            ..
+        })
+    ));
+
+    // So are libraries which don't match their manifest.
+    let mut manifest = test_manifest(&plugin_path);
+    let checksum = manifest.sha256.clone();
+    manifest.sha256 = sha256_hex(b"bing bong");
+    manifest.write(&plugin_path).unwrap();
+    assert!(matches!(
+        load(&host),
+        Err(Error::UntrustedLibrary {
+            source: ManifestError::ChecksumMismatch { .. },
+            ..
+        })
+    ));
+
+    // Declared types are verified before the plugins are initialized.
+    manifest.sha256 = checksum.clone();
+    manifest.types.push(String::from("notify"));
+    manifest.write(&plugin_path).unwrap();
+    assert!(matches!(
+        load(&host),
+        Err(Error::UntrustedLibrary {
+            source: ManifestError::UndeclaredTypes { .. },
+            ..
+        })
+    ));
+
+    // Libraries must be allowed by the allowlist of the node, and none are without one.
+    test_manifest(&plugin_path).write(&plugin_path).unwrap();
+    let host = start_host(None);
+    assert!(host.get_trigger_plugins().is_empty());
+    assert!(matches!(
+        load(&host),
+        Err(Error::UntrustedLibrary {
+            source: ManifestError::NoAllowlist,
+            ..
+        })
+    ));
+
+    let allowed = |sha256: &str| AllowedPlugin {
+        name: String::from("directory_watch"),
+        version: None,
+        sha256: String::from(sha256),
+    };
+    let host = start_host(Some(vec![allowed("deadbeef")]));
+    assert!(host.get_trigger_plugins().is_empty());
+    assert!(matches!(
+        load(&host),
+        Err(Error::UntrustedLibrary {
+            source: ManifestError::NotAllowed { .. },
+            ..
+        })
+    ));
+    let host = start_host(Some(vec![allowed(&checksum)]));
+    assert_eq!(host.get_trigger_plugins().len(), 1);
+}
+
+#[test]
+fn broken_library_report() {
+    let temp_dir = tempdir().unwrap();
+    let plugin_path = temp_dir.path().join("plugin.so");
+    write_plugin(&plugin_path);
+    fs::write(temp_dir.path().join("broken.so"), "bing bong").unwrap();
+
+    // Broken libraries don't prevent the others from loading.
+    let host = PluginHost::initialize(PluginHostConfig {
+        search_paths: vec![PathBuf::from(temp_dir.path())],
+        allowlist: test_allowlist(),
+        ..Default::default()
+    })
+    .unwrap();
+    assert_eq!(host.get_trigger_plugins().len(), 1);
+
+    let report = host.load_report();
+    assert_eq!(report.plugins.len(), 1);
+    assert_eq!(report.plugins[0].status, PluginStatus::Active);
+    assert_eq!(report.rejected.len(), 1);
+    assert_eq!(report.rejected[0].path, temp_dir.path().join("broken.so"));
+    assert!(report.rejected[0].error.contains("manifest"));
+
+    // Removed libraries leave the report.
+    fs::remove_file(temp_dir.path().join("broken.so")).unwrap();
+    assert!(!host.reload().unwrap());
+    assert!(host.load_report().rejected.is_empty());
+}
+
+#[test]
+fn unreadable_search_path() {
+    let temp_dir = tempdir().unwrap();
+    let missing_dir = temp_dir.path().join("missing");
+    let plugin_dir = temp_dir.path().join("plugins");
+    fs::create_dir(&plugin_dir).unwrap();
+    write_plugin(&plugin_dir.join("plugin.so"));
+
+    // Search paths which can't be read don't prevent the others from loading.
+    let host = PluginHost::initialize(PluginHostConfig {
+        search_paths: vec![missing_dir.clone(), plugin_dir],
+        allowlist: test_allowlist(),
+        ..Default::default()
+    })
+    .unwrap();
+    assert_eq!(host.get_trigger_plugins().len(), 1);
+
+    let report = host.load_report();
+    assert_eq!(report.rejected.len(), 1);
+    assert_eq!(report.rejected[0].path, missing_dir);
+    assert_eq!(report.rejected[0].error, "search path doesn't exist");
+
+    // They leave the report once they can be read.
+    fs::create_dir(&missing_dir).unwrap();
+    assert!(!host.reload().unwrap());
+    assert!(host.load_report().rejected.is_empty());
+}
+
+#[test]
+fn duplicate_types() {
+    let dirs = [tempdir().unwrap(), tempdir().unwrap()];
+    let paths: Vec<PathBuf> = dirs.iter().map(|d| d.path().join("plugin.so")).collect();
+    for path in paths.iter() {
+        write_plugin(path);
+    }
+    let start_host = |preferred: HashMap<String, PathBuf>| {
+        PluginHost::initialize(PluginHostConfig {
+            search_paths: dirs.iter().map(|d| PathBuf::from(d.path())).collect(),
+            allowlist: test_allowlist(),
+            preferred,
+            ..Default::default()
+        })
+        .unwrap()
+    };
+
+    // Libraries of earlier search paths take precedence.
+    let host = start_host(HashMap::new());
+    assert_eq!(host.get_trigger_plugins().len(), 1);
+    let plugins = host.list_plugins();
+    assert_eq!(plugins.len(), 2);
+    assert_eq!(plugins[0].library.as_ref(), Some(&paths[0]));
+    assert_eq!(plugins[0].status, PluginStatus::Active);
+    assert_eq!(
+        plugins[1].status,
+        PluginStatus::Shadowed {
+            by: Some(paths[0].clone())
+        }
+    );
+
+    // Unless another library is preferred for the type.
+    let host = start_host(
+        vec![(String::from("directory_watch"), paths[1].clone())]
+            .into_iter()
+            .collect(),
+    );
+    let active: Vec<PluginInfo> = host
+        .list_plugins()
+        .into_iter()
+        .filter(|p| p.status == PluginStatus::Active)
+        .collect();
+    assert_eq!(active.len(), 1);
+    assert_eq!(active[0].library.as_ref(), Some(&paths[1]));
+
+    // Shadowed plugins are shut down too.
+    host.shutdown();
+}
+
+#[test]
+fn untrusted_library_reload() {
+    let temp_dir = tempdir().unwrap();
+    let plugin_path = temp_dir.path().join("plugin.so");
+    let host = PluginHost::initialize(PluginHostConfig {
+        search_paths: vec![PathBuf::from(temp_dir.path())],
+        allowlist: test_allowlist(),
+        ..Default::default()
+    })
+    .unwrap();
+
+    fs::copy(test_library(), &plugin_path).unwrap();
+    assert!(!host.reload().unwrap());
+    assert_eq!(host.rejected_libraries.lock().unwrap().len(), 1);
+
+    // Adding the manifest gives the library another chance.
+    let manifest = test_manifest(&plugin_path);
+    manifest.write(&plugin_path).unwrap();
+    fs::File::options()
+        .write(true)
+        .open(PluginManifest::path(&plugin_path))
+        .unwrap()
+        .set_modified(SystemTime::now() + Duration::from_secs(10))
+        .unwrap();
+    assert!(host.reload().unwrap());
+    assert_eq!(host.get_trigger_plugins().len(), 1);
 }
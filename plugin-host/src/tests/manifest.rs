@@ -0,0 +1,70 @@
+use crate::manifest::{check_allowed, sha256_hex, AllowedPlugin, ManifestError, PluginManifest};
+
+fn manifest() -> PluginManifest {
+    PluginManifest {
+        name: String::from("directory_watch"),
+        version: String::from("0.1.0"),
+        types: vec![String::from("directory_watch")],
+        sha256: sha256_hex(b"bing bong"),
+    }
+}
+
+#[test]
+fn checksum() {
+    assert!(manifest().verify_checksum(b"bing bong").is_ok());
+    assert!(matches!(
+        manifest().verify_checksum(b"bing bang"),
+        Err(ManifestError::ChecksumMismatch { .. })
+    ));
+}
+
+#[test]
+fn types() {
+    assert!(manifest()
+        .verify_types(["directory_watch"].iter().copied())
+        .is_ok());
+    assert!(manifest()
+        .verify_types(["directory_watch", "notify"].iter().copied())
+        .is_err());
+}
+
+#[test]
+fn allowlist() {
+    let manifest = manifest();
+    let allowed = |name: &str, version: Option<&str>, sha256: &str| AllowedPlugin {
+        name: String::from(name),
+        version: version.map(String::from),
+        sha256: String::from(sha256),
+    };
+
+    assert!(matches!(
+        check_allowed(None, &manifest),
+        Err(ManifestError::NoAllowlist)
+    ));
+    assert!(check_allowed(Some(&[]), &manifest).is_err());
+    assert!(check_allowed(Some(&[AllowedPlugin::pinned(&manifest)]), &manifest).is_ok());
+    assert!(check_allowed(
+        Some(&[allowed("directory_watch", None, &manifest.sha256)]),
+        &manifest
+    )
+    .is_ok());
+    assert!(check_allowed(
+        Some(&[allowed("directory_watch", Some("0.2.0"), &manifest.sha256)]),
+        &manifest
+    )
+    .is_err());
+    assert!(check_allowed(
+        Some(&[allowed(
+            "directory_watch",
+            Some("0.1.0"),
+            &manifest.sha256.to_uppercase()
+        )]),
+        &manifest
+    )
+    .is_ok());
+    assert!(check_allowed(
+        Some(&[allowed("directory_watch", None, "deadbeef")]),
+        &manifest
+    )
+    .is_err());
+}
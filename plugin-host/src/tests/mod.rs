@@ -1 +1,8 @@
 mod host;
+mod manifest;
+
+#[cfg(unix)]
+mod subprocess;
+
+mod wasm;
+mod watchdog;
@@ -0,0 +1,321 @@
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use plugin_core::ErrorKind;
+
+use protocol::{ActionManifest, ActionResult, Envelope, Payload, TriggerConfiguration};
+
+use serde_json::{json, Value};
+
+use tempfile::tempdir;
+
+use crate::subprocess::action_result;
+use crate::{CallTimeouts, PluginHost, PluginHostConfig, PluginKind};
+
+/// Action plugin failing transiently on "busy", crashing on "crash", hanging on "hang", and
+/// reporting a result on "created".
+const ECHO_ACTION: &str = r#"#!/bin/sh
+while IFS= read -r line; do
+  id=$(printf '%s\n' "$line" | sed 's/^{"jsonrpc":"2.0","id":\([0-9]*\).*/\1/')
+  case "$line" in
+    *'"method":"get_type"'*) result='{"type":"echo","kind":"action","protocol":2}' ;;
+    *'"method":"execute_action"'*'"data":"crash"'*) exit 1 ;;
+    *'"method":"execute_action"'*'"data":"hang"'*) exec sleep 5 ;;
+    *'"method":"execute_action"'*'"data":"busy"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"error":{"code":1,"message":"busy","data":{"kind":"transient","retry_after_ms":50}}}\n' "$id"
+      continue ;;
+    *'"method":"execute_action"'*'"data":"created"'*) result='{"result":{"summary":"201 Created","data":{"id":7}}}' ;;
+    *'"method":"execute_action"'*) result='{"result":{}}' ;;
+    *'"method":"health"'*) result='null' ;;
+    *)
+      printf '{"jsonrpc":"2.0","id":%s,"error":{"code":-32601,"message":"method not found"}}\n' "$id"
+      continue ;;
+  esac
+  printf '{"jsonrpc":"2.0","id":%s,"result":%s}\n' "$id" "$result"
+done
+"#;
+
+/// Action plugin counting its executions in its state, and reporting them along with its
+/// settings.
+const STATEFUL_ACTION: &str = r#"#!/bin/sh
+settings=null
+while IFS= read -r line; do
+  id=$(printf '%s\n' "$line" | sed 's/^{"jsonrpc":"2.0","id":\([0-9]*\).*/\1/')
+  case "$line" in
+    *'"method":"get_type"'*) result='{"type":"stateful","kind":"action","protocol":2}' ;;
+    *'"method":"init"'*)
+      settings=$(printf '%s\n' "$line" | sed 's/.*"params":{"settings":\(.*\)}}$/\1/')
+      result='null' ;;
+    *'"method":"execute_action"'*)
+      printf '{"jsonrpc":"2.0","id":"get","method":"state_get","params":{"key":"calls"}}\n'
+      IFS= read -r reply
+      calls=$(printf '%s\n' "$reply" | sed 's/.*"result":\([0-9]*\).*/\1/')
+      calls=$((${calls:-0} + 1))
+      printf '{"jsonrpc":"2.0","id":"set","method":"state_set","params":{"key":"calls","value":%s}}\n' "$calls"
+      IFS= read -r reply
+      result="{\"result\":{\"data\":{\"settings\":$settings,\"calls\":$calls}}}" ;;
+    *) result='null' ;;
+  esac
+  printf '{"jsonrpc":"2.0","id":%s,"result":%s}\n' "$id" "$result"
+done
+"#;
+
+/// Trigger plugin firing once per poll.
+const TICK_TRIGGER: &str = r#"#!/bin/sh
+while IFS= read -r line; do
+  id=$(printf '%s\n' "$line" | sed 's/^{"jsonrpc":"2.0","id":\([0-9]*\).*/\1/')
+  case "$line" in
+    *'"method":"get_type"'*) result='{"type":"tick","kind":"trigger","metadata":{"name":"tick","version":"1.0.0"}}' ;;
+    *'"method":"pull_trigger"'*) result='[{"tick":true}]' ;;
+    *) result='null' ;;
+  esac
+  printf '{"jsonrpc":"2.0","id":%s,"result":%s}\n' "$id" "$result"
+done
+"#;
+
+fn write_script(dir: &Path, name: &str, script: &str) -> PathBuf {
+    let path = dir.join(name);
+    fs::write(&path, script).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+fn manifest(data: &str) -> ActionManifest {
+    ActionManifest {
+        rule: "1".into(),
+        action_type: String::from("echo"),
+        data: json!(data).into(),
+        envelope: Envelope::new(),
+    }
+}
+
+#[test]
+fn process_action() {
+    let temp_dir = tempdir().unwrap();
+    let command = write_script(temp_dir.path(), "echo.sh", ECHO_ACTION);
+
+    let host = PluginHost::initialize(PluginHostConfig {
+        process_plugins: vec![command.clone()],
+        ..Default::default()
+    })
+    .unwrap();
+
+    let plugins = host.list_plugins();
+    assert_eq!(plugins.len(), 1);
+    assert_eq!(plugins[0].plugin_type, "echo");
+    assert_eq!(plugins[0].kind, PluginKind::Action);
+    assert_eq!(plugins[0].metadata.name, "echo");
+    assert_eq!(plugins[0].library, Some(command));
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let echo = host.get_action_plugins().pop().unwrap();
+
+    // Optional methods may be left unimplemented.
+    assert!(echo.validate_config(&Payload::default()).is_ok());
+
+    assert!(runtime
+        .block_on(echo.execute_action(manifest("bing")))
+        .is_ok());
+
+    let result = runtime
+        .block_on(echo.execute_with_result(manifest("created")))
+        .unwrap();
+    assert_eq!(result.summary.as_deref(), Some("201 Created"));
+    assert_eq!(result.data, json!({"id": 7}).into());
+
+    let err = runtime
+        .block_on(echo.execute_action(manifest("busy")))
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Transient);
+    assert_eq!(err.retry_after, Some(Duration::from_millis(50)));
+
+    // A crash fails the call in flight, and the process is restarted.
+    let err = runtime
+        .block_on(echo.execute_action(manifest("crash")))
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Transient);
+    assert!(runtime
+        .block_on(echo.execute_action(manifest("bong")))
+        .is_ok());
+
+    let health = host.check_process_plugins();
+    assert_eq!(health.len(), 1);
+    assert_eq!(health[0].restarts, 1);
+    assert_eq!(health[0].error, None);
+
+    host.shutdown();
+}
+
+#[test]
+fn process_timeouts_and_restarts() {
+    let temp_dir = tempdir().unwrap();
+    let command = write_script(temp_dir.path(), "echo.sh", ECHO_ACTION);
+
+    let host = PluginHost::initialize(PluginHostConfig {
+        process_plugins: vec![command],
+        timeouts: CallTimeouts {
+            plugins: vec![(String::from("echo"), 200)].into_iter().collect(),
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .unwrap();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let echo = host.get_action_plugins().pop().unwrap();
+
+    // A hung process is killed once the call times out, and restarted by the next call.
+    let started = Instant::now();
+    let err = runtime
+        .block_on(echo.execute_action(manifest("hang")))
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Timeout);
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert!(runtime
+        .block_on(echo.execute_action(manifest("bing")))
+        .is_ok());
+    assert_eq!(host.check_process_plugins()[0].restarts, 1);
+
+    // A process failing again right after its restart isn't restarted right away.
+    for data in ["crash", "crash"] {
+        let err = runtime
+            .block_on(echo.execute_action(manifest(data)))
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Transient);
+    }
+    let err = runtime
+        .block_on(echo.execute_action(manifest("bong")))
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Transient);
+    assert!(err.retry_after.is_some());
+
+    let health = host.check_process_plugins();
+    assert_eq!(health[0].restarts, 2);
+    assert!(health[0].error.is_some());
+}
+
+#[test]
+fn process_init_and_state() {
+    let temp_dir = tempdir().unwrap();
+    let command = write_script(temp_dir.path(), "stateful.sh", STATEFUL_ACTION);
+
+    let host = PluginHost::initialize(PluginHostConfig {
+        process_plugins: vec![command],
+        settings: vec![(
+            String::from("stateful"),
+            json!({"greeting": "bing bong"}).into(),
+        )]
+        .into_iter()
+        .collect(),
+        ..Default::default()
+    })
+    .unwrap();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let stateful = host.get_action_plugins().pop().unwrap();
+
+    for calls in 1..=2 {
+        let result = runtime
+            .block_on(stateful.execute_with_result(manifest("bing")))
+            .unwrap();
+        assert_eq!(
+            result.data,
+            json!({"settings": {"greeting": "bing bong"}, "calls": calls}).into()
+        );
+    }
+}
+
+#[test]
+fn action_results() {
+    // Answers of protocol 1 plugins are kept as data, whatever their shape.
+    assert_eq!(
+        action_result(1, Value::Null).unwrap(),
+        ActionResult::default()
+    );
+    assert_eq!(
+        action_result(1, json!({"data": [1, 2]})).unwrap(),
+        ActionResult::new(json!({"data": [1, 2]}).into())
+    );
+    assert_eq!(
+        action_result(1, json!(true)).unwrap(),
+        ActionResult::new(json!(true).into())
+    );
+
+    // Protocol 2 plugins answer with their result.
+    assert_eq!(
+        action_result(2, json!({"result": {"data": [1, 2]}})).unwrap(),
+        ActionResult::new(json!([1, 2]).into())
+    );
+    assert_eq!(
+        action_result(2, json!({"result": {}})).unwrap(),
+        ActionResult::default()
+    );
+    for invalid in [Value::Null, json!({"data": [1, 2]}), json!({"result": 42})] {
+        assert_eq!(
+            action_result(2, invalid).unwrap_err().kind,
+            ErrorKind::Permanent
+        );
+    }
+}
+
+#[test]
+fn process_trigger() {
+    let temp_dir = tempdir().unwrap();
+    let command = write_script(temp_dir.path(), "tick.sh", TICK_TRIGGER);
+
+    let host = PluginHost::initialize(PluginHostConfig {
+        process_plugins: vec![command],
+        ..Default::default()
+    })
+    .unwrap();
+    assert_eq!(
+        host.get_trigger_metadata("tick")
+            .unwrap()
+            .version
+            .as_deref(),
+        Some("1.0.0")
+    );
+
+    let cfg = TriggerConfiguration {
+        id: 1,
+        rule: "42".into(),
+        trigger_type: String::from("tick"),
+        data: json!({}).into(),
+        schedule: Default::default(),
+    };
+    assert!(host.validate_trigger_config(&cfg).is_ok());
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let tick = host.get_trigger_plugins().pop().unwrap();
+    let triggers = runtime.block_on(tick.pull_trigger(&cfg)).unwrap();
+    assert_eq!(triggers.len(), 1);
+    assert_eq!(triggers[0].rule, "42");
+    assert_eq!(triggers[0].trigger_type, "tick");
+    assert_eq!(triggers[0].data, json!({"tick": true}).into());
+}
+
+#[test]
+fn process_spawn_failure() {
+    // Processes which fail to start are reported, and don't prevent the host from starting.
+    let host = PluginHost::initialize(PluginHostConfig {
+        process_plugins: vec![PathBuf::from("/does/not/exist")],
+        ..Default::default()
+    })
+    .unwrap();
+    assert!(host.list_plugins().is_empty());
+
+    let report = host.load_report();
+    assert_eq!(report.rejected.len(), 1);
+    assert_eq!(report.rejected[0].path, PathBuf::from("/does/not/exist"));
+    assert!(report.rejected[0]
+        .error
+        .starts_with("failed to spawn plugin process"));
+}
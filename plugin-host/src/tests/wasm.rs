@@ -0,0 +1,346 @@
+use std::fs;
+use std::path::Path;
+
+use plugin_core::{ActionPlugin, Error as PlugError, ErrorKind};
+
+use protocol::{ActionManifest, Envelope, Payload, TriggerConfiguration};
+
+use serde_json::json;
+
+use tempfile::tempdir;
+
+use crate::{
+    AllowedPlugin, PluginHost, PluginHostConfig, PluginKind, PluginManifest, PluginSource,
+    PluginStatus, WasmLimits,
+};
+
+/// Compiles a module made of `funcs`, with `strings` laid out in its memory.
+///
+/// String `N` is returned packed by `$strN`, and its location is `($ptrN) ($lenN)`.
+fn compile(memory_pages: u32, strings: &[&str], funcs: &str) -> Vec<u8> {
+    let mut wat = format!(
+        r#"(module
+  (import "shift3" "log" (func $log (param i32 i32 i32)))
+  (import "shift3" "now_ms" (func $now_ms (result i64)))
+  (import "shift3" "state_get" (func $state_get (param i32 i32) (result i64)))
+  (import "shift3" "state_set" (func $state_set (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") {})
+  (global $heap (mut i32) (i32.const 4096))
+  (func (export "alloc") (param $len i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $heap))
+    (global.set $heap (i32.add (global.get $heap) (local.get $len)))
+    (local.get $ptr))
+  (func $pack (param $ptr i32) (param $len i32) (result i64)
+    (i64.or
+      (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+      (i64.extend_i32_u (local.get $len))))
+"#,
+        memory_pages
+    );
+
+    for (i, string) in strings.iter().enumerate() {
+        let offset = i * 256;
+        wat.push_str(&format!(
+            r#"  (data (i32.const {offset}) "{escaped}")
+  (func $str{i} (result i64) (call $pack (i32.const {offset}) (i32.const {len})))
+  (func $ptr{i} (result i32) (i32.const {offset}))
+  (func $len{i} (result i32) (i32.const {len}))
+"#,
+            offset = offset,
+            escaped = string.replace('"', "\\\""),
+            i = i,
+            len = string.len()
+        ));
+    }
+
+    wat.push_str(funcs);
+    wat.push(')');
+    wat::parse_str(&wat).unwrap()
+}
+
+fn echo_action() -> Vec<u8> {
+    compile(
+        1,
+        &[
+            r#"{"type":"wasm_echo","kind":"action","metadata":{"name":"wasm_echo","version":"1.0.0"}}"#,
+            r#"{"result":null}"#,
+            r#"{"error":{"code":1,"message":"no config expected","data":{"kind":"invalid_config"}}}"#,
+        ],
+        r#"
+  (func (export "get_type") (result i64) (call $str0))
+  (func (export "validate_config") (param $ptr i32) (param $len i32) (result i64)
+    (if (i32.eq (local.get $len) (i32.const 4))
+      (then (return (call $str1))))
+    (call $str2))
+  (func (export "execute_action") (param $ptr i32) (param $len i32) (result i64)
+    (call $log (i32.const 3) (local.get $ptr) (local.get $len))
+    (call $str1))
+"#,
+    )
+}
+
+/// Trigger firing once, remembering it did in its state.
+fn once_trigger() -> Vec<u8> {
+    compile(
+        1,
+        &[
+            r#"{"type":"wasm_once","kind":"trigger"}"#,
+            r#"{"result":[{"first":true}]}"#,
+            r#"{"result":[]}"#,
+            "fired",
+            "true",
+        ],
+        r#"
+  (func (export "get_type") (result i64) (call $str0))
+  (func (export "pull_trigger") (param $ptr i32) (param $len i32) (result i64)
+    (if (i64.eqz (call $now_ms))
+      (then unreachable))
+    (if (i64.eqz (call $state_get (call $ptr3) (call $len3)))
+      (then
+        (drop (call $state_set (call $ptr3) (call $len3) (call $ptr4) (call $len4)))
+        (return (call $str1))))
+    (call $str2))
+"#,
+    )
+}
+
+/// Action spinning forever, and trapping on validation.
+fn spin_action() -> Vec<u8> {
+    compile(
+        1,
+        &[r#"{"type":"wasm_spin","kind":"action"}"#],
+        r#"
+  (func (export "get_type") (result i64) (call $str0))
+  (func (export "validate_config") (param $ptr i32) (param $len i32) (result i64)
+    unreachable)
+  (func (export "execute_action") (param $ptr i32) (param $len i32) (result i64)
+    (loop $spin (br $spin))
+    unreachable)
+"#,
+    )
+}
+
+/// Writes a module along with its manifest, and returns the allowlist pinning it.
+fn write_module(
+    dir: &Path,
+    name: &str,
+    plugin_type: &str,
+    module: &[u8],
+) -> Option<Vec<AllowedPlugin>> {
+    let path = dir.join(name);
+    fs::write(&path, module).unwrap();
+    let manifest =
+        PluginManifest::for_library(&path, plugin_type, "1.0.0", &[plugin_type]).unwrap();
+    manifest.write(&path).unwrap();
+    Some(vec![AllowedPlugin::pinned(&manifest)])
+}
+
+fn manifest(action_type: &str) -> ActionManifest {
+    ActionManifest {
+        rule: "1".into(),
+        action_type: String::from(action_type),
+        data: json!({"bing": "bong"}).into(),
+        envelope: Envelope::new(),
+    }
+}
+
+#[test]
+fn wasm_action() {
+    let temp_dir = tempdir().unwrap();
+    let allowlist = write_module(temp_dir.path(), "echo.wasm", "wasm_echo", &echo_action());
+
+    let host = PluginHost::initialize(PluginHostConfig {
+        search_paths: vec![temp_dir.path().to_path_buf()],
+        allowlist,
+        ..Default::default()
+    })
+    .unwrap();
+
+    let plugins = host.list_plugins();
+    assert_eq!(plugins.len(), 1);
+    assert_eq!(plugins[0].kind, PluginKind::Action);
+    assert_eq!(plugins[0].metadata.version.as_deref(), Some("1.0.0"));
+    assert_eq!(plugins[0].library, Some(temp_dir.path().join("echo.wasm")));
+
+    let echo = host.get_action_plugins().pop().unwrap();
+    assert!(echo.validate_config(&json!(null).into()).is_ok());
+    assert_eq!(
+        echo.validate_config(&json!({"a": 1}).into())
+            .unwrap_err()
+            .kind,
+        ErrorKind::InvalidConfig
+    );
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    runtime
+        .block_on(echo.execute_action(manifest("wasm_echo")))
+        .unwrap();
+}
+
+#[test]
+fn wasm_trigger_state() {
+    let temp_dir = tempdir().unwrap();
+    let allowlist = write_module(temp_dir.path(), "once.wasm", "wasm_once", &once_trigger());
+
+    let host = PluginHost::initialize(PluginHostConfig {
+        search_paths: vec![temp_dir.path().to_path_buf()],
+        allowlist,
+        ..Default::default()
+    })
+    .unwrap();
+
+    let cfg = TriggerConfiguration {
+        id: 1,
+        rule: "42".into(),
+        trigger_type: String::from("wasm_once"),
+        data: Payload::default(),
+        schedule: Default::default(),
+    };
+
+    // Modules without a validate_config export accept every config.
+    assert!(host.validate_trigger_config(&cfg).is_ok());
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let once = host.get_trigger_plugins().pop().unwrap();
+
+    let triggers = runtime.block_on(once.pull_trigger(&cfg)).unwrap();
+    assert_eq!(triggers.len(), 1);
+    assert_eq!(triggers[0].rule, "42");
+    assert_eq!(triggers[0].data, json!({"first": true}).into());
+
+    assert!(runtime
+        .block_on(once.pull_trigger(&cfg))
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn wasm_limits() {
+    let temp_dir = tempdir().unwrap();
+    let allowlist = write_module(temp_dir.path(), "spin.wasm", "wasm_spin", &spin_action());
+
+    let host = PluginHost::initialize(PluginHostConfig {
+        search_paths: vec![temp_dir.path().to_path_buf()],
+        allowlist,
+        wasm_limits: WasmLimits {
+            fuel: Some(100_000),
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .unwrap();
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let spin = host.get_action_plugins().pop().unwrap();
+
+    // Every call gets its own fuel, and the module is usable again after running out.
+    for _ in 0..2 {
+        let err = runtime
+            .block_on(spin.execute_action(manifest("wasm_spin")))
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Timeout);
+    }
+
+    let err = spin.validate_config(&Payload::default()).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Permanent);
+}
+
+#[test]
+fn wasm_memory_limit() {
+    let temp_dir = tempdir().unwrap();
+    let allowlist = write_module(
+        temp_dir.path(),
+        "hog.wasm",
+        "wasm_hog",
+        &compile(
+            32,
+            &[r#"{"type":"wasm_hog","kind":"action"}"#],
+            r#"(func (export "get_type") (result i64) (call $str0))"#,
+        ),
+    );
+
+    // 32 pages are 2MiB.
+    let host = PluginHost::initialize(PluginHostConfig {
+        search_paths: vec![temp_dir.path().to_path_buf()],
+        allowlist,
+        wasm_limits: WasmLimits {
+            max_memory: 1024 * 1024,
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .unwrap();
+    assert!(host.list_plugins().is_empty());
+
+    let report = host.load_report();
+    assert_eq!(report.rejected.len(), 1);
+    assert!(report.rejected[0]
+        .error
+        .starts_with("failed to load WASM plugin"));
+}
+
+struct InMemoryEcho;
+
+impl ActionPlugin for InMemoryEcho {
+    fn execute_action(&self, _manifest: ActionManifest) -> Result<(), PlugError> {
+        Ok(())
+    }
+
+    fn get_type(&self) -> &str {
+        "wasm_echo"
+    }
+}
+
+#[test]
+fn source_precedence() {
+    let temp_dir = tempdir().unwrap();
+    let allowlist = write_module(temp_dir.path(), "echo.wasm", "wasm_echo", &echo_action());
+    let start_host = |precedence: Vec<PluginSource>| {
+        let mut host = PluginHost::initialize(PluginHostConfig {
+            search_paths: vec![temp_dir.path().to_path_buf()],
+            allowlist: allowlist.clone(),
+            precedence,
+            ..Default::default()
+        })
+        .unwrap();
+        host.add_in_memory_action_plugin(Box::new(InMemoryEcho))
+            .unwrap();
+        host
+    };
+    let active_source = |host: &PluginHost| {
+        let active: Vec<PluginSource> = host
+            .list_plugins()
+            .into_iter()
+            .filter(|p| p.status == PluginStatus::Active)
+            .map(|p| p.source)
+            .collect();
+        assert_eq!(active.len(), 1);
+        active[0]
+    };
+
+    // In-memory plugins take precedence by default.
+    let host = start_host(Vec::new());
+    assert_eq!(host.get_action_plugins().len(), 1);
+    assert_eq!(active_source(&host), PluginSource::InMemory);
+
+    // Sources missing from the precedence come last.
+    let host = start_host(vec![PluginSource::Wasm]);
+    assert_eq!(active_source(&host), PluginSource::Wasm);
+    assert_eq!(
+        host.list_plugins()
+            .into_iter()
+            .find(|p| p.source == PluginSource::InMemory)
+            .unwrap()
+            .status,
+        PluginStatus::Shadowed {
+            by: Some(temp_dir.path().join("echo.wasm"))
+        }
+    );
+}
@@ -0,0 +1,189 @@
+use std::thread;
+use std::time::Duration;
+
+use plugin_core::{ActionPlugin, Error as PlugError, ErrorKind};
+
+use protocol::{ActionManifest, Envelope};
+
+use crate::watchdog::PANICS_BEFORE_SUSPENSION;
+use crate::{CallTimeouts, PluginHost, PluginHostConfig, DEFAULT_CALL_TIMEOUT_MS};
+
+fn timeouts() -> CallTimeouts {
+    CallTimeouts {
+        default: Some(1000),
+        plugins: vec![(String::from("notify"), 100)].into_iter().collect(),
+        rules: vec![(String::from("42"), 10)].into_iter().collect(),
+    }
+}
+
+#[test]
+fn most_specific_timeout() {
+    let timeouts = timeouts();
+    assert_eq!(
+        timeouts.get("notify", "42"),
+        Some(Duration::from_millis(10))
+    );
+    assert_eq!(
+        timeouts.get("notify", "1"),
+        Some(Duration::from_millis(100))
+    );
+    assert_eq!(timeouts.get("email", "1"), Some(Duration::from_secs(1)));
+    assert_eq!(
+        CallTimeouts::default().get("notify", "42"),
+        Some(Duration::from_millis(DEFAULT_CALL_TIMEOUT_MS))
+    );
+
+    // Calls are only unbounded when asked.
+    let unbounded = CallTimeouts {
+        default: None,
+        ..Default::default()
+    };
+    assert_eq!(unbounded.get("notify", "42"), None);
+}
+
+#[test]
+fn watch() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap();
+    let host = PluginHost::initialize(PluginHostConfig {
+        timeouts: timeouts(),
+        ..Default::default()
+    })
+    .unwrap();
+
+    let err = runtime
+        .block_on(host.watch(
+            "notify",
+            "42",
+            std::future::pending::<Result<(), PlugError>>(),
+        ))
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Timeout);
+
+    runtime
+        .block_on(host.watch("notify", "1", async { Ok(()) }))
+        .unwrap();
+    assert_eq!(host.timed_out_calls()["notify"], 1);
+}
+
+async fn panicking() -> Result<(), PlugError> {
+    panic!("bing bong")
+}
+
+#[test]
+fn panics_are_caught() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap();
+    let host = PluginHost::default();
+
+    let err = runtime
+        .block_on(host.watch("notify", "42", panicking()))
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Permanent);
+
+    let err = host
+        .guard("notify", || -> Result<(), PlugError> {
+            panic!("bing {}", "bang")
+        })
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "permanent error: plugin panicked: bing bang"
+    );
+
+    let unhealthy = host.unhealthy_plugins();
+    assert_eq!(unhealthy.len(), 1);
+    assert_eq!(unhealthy[0].panics, 2);
+    assert_eq!(unhealthy[0].last_panic, "bing bang");
+
+    // Returning normally resets the panics in a row.
+    host.guard("notify", || Ok(())).unwrap();
+    let unhealthy = host.unhealthy_plugins();
+    assert_eq!(unhealthy[0].panics, 2);
+    assert_eq!(unhealthy[0].consecutive_panics, 0);
+    assert!(!unhealthy[0].suspended);
+}
+
+#[test]
+fn panicking_plugins_are_suspended() {
+    let host = PluginHost::default();
+    let panicking = || -> Result<(), PlugError> { panic!("bing bong") };
+
+    for _ in 0..PANICS_BEFORE_SUSPENSION {
+        let err = host.guard("notify", panicking).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Permanent);
+    }
+
+    // Calls are refused without reaching the plugin.
+    let err = host
+        .guard("notify", || -> Result<(), PlugError> { unreachable!() })
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Transient);
+    assert!(err.retry_after.is_some());
+
+    let unhealthy = host.unhealthy_plugins();
+    assert_eq!(unhealthy[0].consecutive_panics, PANICS_BEFORE_SUSPENSION);
+    assert!(unhealthy[0].suspended);
+
+    // Other plugins aren't affected.
+    host.guard("email", || Ok(())).unwrap();
+}
+
+/// Synchronous action plugin taking a while to execute.
+struct SlowAction;
+
+impl ActionPlugin for SlowAction {
+    fn execute_action(&self, _manifest: ActionManifest) -> Result<(), PlugError> {
+        thread::sleep(Duration::from_millis(300));
+        Ok(())
+    }
+
+    fn get_type(&self) -> &str {
+        "slow"
+    }
+}
+
+#[test]
+fn abandoned_calls_are_refused() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap();
+    let mut host = PluginHost::initialize(PluginHostConfig {
+        timeouts: CallTimeouts {
+            plugins: vec![(String::from("slow"), 50)].into_iter().collect(),
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .unwrap();
+    host.add_in_memory_action_plugin(Box::new(SlowAction))
+        .unwrap();
+    let slow = host.get_action_plugins().pop().unwrap();
+    let execute = |rule: &str| {
+        let manifest = ActionManifest {
+            rule: rule.into(),
+            action_type: String::from("slow"),
+            data: Default::default(),
+            envelope: Envelope::new(),
+        };
+        runtime.block_on(host.watch("slow", rule, slow.execute_action(manifest)))
+    };
+
+    assert_eq!(execute("1").unwrap_err().kind, ErrorKind::Timeout);
+
+    // The timed out call still runs, so its rule is left alone meanwhile.
+    let err = execute("1").unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Transient);
+    assert!(err.to_string().contains("abandoned"));
+
+    // Other rules still reach the plugin.
+    assert_eq!(execute("2").unwrap_err().kind, ErrorKind::Timeout);
+
+    thread::sleep(Duration::from_millis(300));
+    assert_eq!(execute("1").unwrap_err().kind, ErrorKind::Timeout);
+}
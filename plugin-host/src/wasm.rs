@@ -0,0 +1,581 @@
+//! WebAssembly plugins.
+//!
+//! A WASM plugin is a module implementing a single action or trigger plugin. Modules run in an
+//! interpreter embedded in the host: they only reach the outside world through the host API
+//! below, and every call into them is bounded by the [`WasmLimits`] of the host.
+//!
+//! # Guest interface
+//!
+//! Data crosses the boundary as UTF-8 JSON written to the linear memory of the module.
+//! Functions returning data return its location packed in an `i64`, as `(ptr << 32) | len`.
+//!
+//! The module must export:
+//!
+//! | export           | signature           | description                                    |
+//! |------------------|---------------------|------------------------------------------------|
+//! | `memory`         | memory              | Linear memory of the module.                   |
+//! | `alloc`          | `(len: i32) -> i32` | Allocates `len` bytes for the host to write to. |
+//! | `get_type`       | `() -> i64`         | Same description as the `get_type` answer of process plugins. |
+//! | `validate_config`| `(ptr, len) -> i64` | Optional. Takes the action config or the trigger configuration. |
+//! | `pull_trigger`   | `(ptr, len) -> i64` | Takes the trigger configuration, returns an array of trigger data. |
+//! | `execute_action` | `(ptr, len) -> i64` | Takes the action manifest, returns the same result as process plugins. |
+//!
+//! Except for `get_type`, functions return `{"result": <value>}` on success, and
+//! `{"error": <error>}` on failure, where the error is a JSON-RPC error object like the ones
+//! returned by process plugins.
+//!
+//! The host API is imported from the `shift3` module:
+//!
+//! | import      | signature                                  | description                  |
+//! |-------------|--------------------------------------------|------------------------------|
+//! | `log`       | `(level: i32, ptr: i32, len: i32)`         | Logs a message, from `1` (error) to `5` (trace). |
+//! | `now_ms`    | `() -> i64`                                | Milliseconds since the Unix epoch. |
+//! | `state_get` | `(key_ptr, key_len) -> i64`                | JSON value stored at a key, written to memory obtained from `alloc`. `0` when missing, `-1` on failure. |
+//! | `state_set` | `(key_ptr, key_len, value_ptr, value_len) -> i32` | Stores a JSON value at a key. `0` on success, `-1` on failure. |
+//!
+//! A call which traps or runs out of fuel fails, and the module is instantiated again from
+//! scratch for the next call. Its state is kept, as it lives in the host.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::UNIX_EPOCH;
+
+use log::Level;
+
+use plugin_core::{ActionPlugin, Error, PluginContext, PluginMetadata, TriggerPlugin};
+
+use protocol::{ActionManifest, ActionResult, Payload, Trigger, TriggerConfiguration};
+
+use serde::{Deserialize, Serialize};
+
+use serde_json::Value;
+
+use wasmi::core::{Trap, TrapCode};
+use wasmi::{
+    AsContext, AsContextMut, Caller, Config, Engine, Extern, Func, Instance, Linker, Memory,
+    Module, Store, StoreLimits, StoreLimitsBuilder,
+};
+
+use crate::subprocess::{action_result, DescribedKind, PluginDescription, RpcError};
+
+const HOST_MODULE: &str = "shift3";
+
+/// Resources a WASM plugin may use.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WasmLimits {
+    /// Fuel available to each call into a plugin, roughly one unit per instruction executed.
+    /// Calls are unbounded when `None`.
+    pub fuel: Option<u64>,
+
+    /// Maximum size of the linear memory of a plugin, in bytes.
+    pub max_memory: usize,
+}
+
+impl Default for WasmLimits {
+    fn default() -> Self {
+        WasmLimits {
+            fuel: Some(1_000_000_000),
+            max_memory: 64 * 1024 * 1024,
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn pack(ptr: i32, len: i32) -> i64 {
+    ((ptr as u32 as i64) << 32) | len as u32 as i64
+}
+
+fn unpack(packed: i64) -> (usize, usize) {
+    ((packed as u64 >> 32) as usize, packed as u32 as usize)
+}
+
+/// Data of the store a module is instantiated in.
+struct HostState {
+    plugin_type: String,
+    context: PluginContext,
+    limits: StoreLimits,
+}
+
+fn guest_memory<T>(caller: &Caller<'_, T>) -> Result<Memory, Trap> {
+    caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| Trap::new("module doesn't export its memory"))
+}
+
+fn read_guest(
+    ctx: impl AsContext,
+    memory: Memory,
+    ptr: usize,
+    len: usize,
+) -> Result<Vec<u8>, String> {
+    memory
+        .data(&ctx)
+        .get(ptr..ptr.saturating_add(len))
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| format!("{} bytes at {} are out of bounds", len, ptr))
+}
+
+/// Copies data to memory allocated by the `alloc` export of the module.
+fn write_guest(
+    mut ctx: impl AsContextMut,
+    alloc: Func,
+    memory: Memory,
+    data: &[u8],
+) -> Result<i32, String> {
+    let ptr = alloc
+        .typed::<i32, i32>(&ctx)
+        .and_then(|alloc| Ok(alloc.call(&mut ctx, data.len() as i32)?))
+        .map_err(|e| format!("alloc failed: {}", e))?;
+    memory
+        .write(&mut ctx, ptr as u32 as usize, data)
+        .map_err(|e| format!("failed to write to module memory: {}", e))?;
+    Ok(ptr)
+}
+
+fn read_key(caller: &Caller<'_, HostState>, ptr: i32, len: i32) -> Result<String, Trap> {
+    let memory = guest_memory(caller)?;
+    let key =
+        read_guest(caller, memory, ptr as u32 as usize, len as u32 as usize).map_err(Trap::new)?;
+    String::from_utf8(key).map_err(|e| Trap::new(e.to_string()))
+}
+
+/// Defines the host API modules may import.
+fn host_api(engine: &Engine) -> Result<Linker<HostState>, wasmi::Error> {
+    let mut linker = Linker::new(engine);
+
+    linker.func_wrap(
+        HOST_MODULE,
+        "log",
+        |caller: Caller<'_, HostState>, level: i32, ptr: i32, len: i32| -> Result<(), Trap> {
+            let memory = guest_memory(&caller)?;
+            let message = read_guest(&caller, memory, ptr as u32 as usize, len as u32 as usize)
+                .map_err(Trap::new)?;
+            let level = match level {
+                1 => Level::Error,
+                2 => Level::Warn,
+                3 => Level::Info,
+                4 => Level::Debug,
+                _ => Level::Trace,
+            };
+            log::log!(
+                level,
+                "[{}] {}",
+                caller.data().plugin_type,
+                String::from_utf8_lossy(&message)
+            );
+            Ok(())
+        },
+    )?;
+
+    linker.func_wrap(
+        HOST_MODULE,
+        "now_ms",
+        |caller: Caller<'_, HostState>| -> i64 {
+            caller
+                .data()
+                .context
+                .clock
+                .now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as i64)
+                .unwrap_or_default()
+        },
+    )?;
+
+    linker.func_wrap(
+        HOST_MODULE,
+        "state_get",
+        |mut caller: Caller<'_, HostState>, key_ptr: i32, key_len: i32| -> Result<i64, Trap> {
+            let key = read_key(&caller, key_ptr, key_len)?;
+            let value = match caller.data().context.state.get::<Value>(&key) {
+                Ok(Some(value)) => value.to_string(),
+                Ok(None) => return Ok(0),
+                Err(e) => {
+                    log::warn!(
+                        "[{}] failed to read state [{}]: {}",
+                        caller.data().plugin_type,
+                        key,
+                        e
+                    );
+                    return Ok(-1);
+                }
+            };
+
+            let memory = guest_memory(&caller)?;
+            let alloc = caller
+                .get_export("alloc")
+                .and_then(Extern::into_func)
+                .ok_or_else(|| Trap::new("module doesn't export alloc"))?;
+            let ptr =
+                write_guest(&mut caller, alloc, memory, value.as_bytes()).map_err(Trap::new)?;
+            Ok(pack(ptr, value.len() as i32))
+        },
+    )?;
+
+    linker.func_wrap(
+        HOST_MODULE,
+        "state_set",
+        |caller: Caller<'_, HostState>,
+         key_ptr: i32,
+         key_len: i32,
+         value_ptr: i32,
+         value_len: i32|
+         -> Result<i32, Trap> {
+            let key = read_key(&caller, key_ptr, key_len)?;
+            let memory = guest_memory(&caller)?;
+            let value = read_guest(
+                &caller,
+                memory,
+                value_ptr as u32 as usize,
+                value_len as u32 as usize,
+            )
+            .map_err(Trap::new)?;
+
+            let result = serde_json::from_slice::<Value>(&value)
+                .map_err(|e| Error::permanent(format!("invalid JSON: {}", e)))
+                .and_then(|value| caller.data().context.state.set(&key, &value));
+            match result {
+                Ok(()) => Ok(0),
+                Err(e) => {
+                    log::warn!(
+                        "[{}] failed to write state [{}]: {}",
+                        caller.data().plugin_type,
+                        key,
+                        e
+                    );
+                    Ok(-1)
+                }
+            }
+        },
+    )?;
+
+    Ok(linker)
+}
+
+/// Compiled WASM module.
+pub(crate) struct WasmModule {
+    path: PathBuf,
+    engine: Engine,
+    module: Module,
+    linker: Linker<HostState>,
+    limits: WasmLimits,
+}
+
+impl WasmModule {
+    /// Compiles a module, read from `path`.
+    pub fn load(path: &Path, data: &[u8], limits: &WasmLimits) -> Result<WasmModule, Error> {
+        let mut config = Config::default();
+        config.consume_fuel(limits.fuel.is_some());
+        let engine = Engine::new(&config);
+
+        let module = Module::new(&engine, data)
+            .map_err(|e| Error::permanent(format!("invalid module: {}", e)))?;
+        let linker = host_api(&engine)
+            .map_err(|e| Error::permanent(format!("failed to define host API: {}", e)))?;
+
+        Ok(WasmModule {
+            path: PathBuf::from(path),
+            engine,
+            module,
+            linker,
+            limits: limits.clone(),
+        })
+    }
+
+    /// Asks the module which plugin it implements.
+    pub fn describe(&self) -> Result<PluginDescription, Error> {
+        let mut instance = self.instantiate(HostState {
+            plugin_type: self.path.display().to_string(),
+            context: PluginContext::default(),
+            limits: StoreLimits::default(),
+        })?;
+
+        let description = instance
+            .invoke("get_type", None)?
+            .ok_or_else(|| Error::permanent("module doesn't export get_type"))?;
+        serde_json::from_slice(&description)
+            .map_err(|e| Error::permanent("invalid [get_type] result").with_source(e))
+    }
+
+    fn instantiate(&self, mut state: HostState) -> Result<ModuleInstance, Error> {
+        state.limits = StoreLimitsBuilder::new()
+            .memory_size(self.limits.max_memory)
+            .build();
+        let mut store = Store::new(&self.engine, state);
+        store.limiter(|state| &mut state.limits);
+
+        let mut instance = ModuleInstance {
+            store,
+            instance: None,
+            fuel: self.limits.fuel,
+            fuel_added: 0,
+        };
+        instance.refuel()?;
+
+        let started = self
+            .linker
+            .instantiate(&mut instance.store, &self.module)
+            .and_then(|pre| pre.start(&mut instance.store))
+            .map_err(|e| Error::permanent(format!("failed to instantiate module: {}", e)))?;
+        instance.instance = Some(started);
+
+        Ok(instance)
+    }
+}
+
+/// Instance of a module, with the store it lives in.
+struct ModuleInstance {
+    store: Store<HostState>,
+    instance: Option<Instance>,
+
+    // Fuel available to each call, and fuel added to the store so far.
+    fuel: Option<u64>,
+    fuel_added: u64,
+}
+
+impl ModuleInstance {
+    /// Tops the fuel of the store back up to the budget of a call.
+    fn refuel(&mut self) -> Result<(), Error> {
+        if let Some(fuel) = self.fuel {
+            let consumed = self.store.fuel_consumed().unwrap_or_default();
+            let remaining = self.fuel_added.saturating_sub(consumed);
+            if remaining < fuel {
+                self.store
+                    .add_fuel(fuel - remaining)
+                    .map_err(|e| Error::permanent(e.to_string()))?;
+                self.fuel_added += fuel - remaining;
+            }
+        }
+        Ok(())
+    }
+
+    /// Calls an export with the given input, and returns its output. Returns `None` when the
+    /// module doesn't export the function.
+    fn invoke(&mut self, export: &str, input: Option<&[u8]>) -> Result<Option<Vec<u8>>, Error> {
+        let instance = self.instance.expect("module is instantiated");
+        let func = match instance
+            .get_export(&self.store, export)
+            .and_then(Extern::into_func)
+        {
+            Some(func) => func,
+            None => return Ok(None),
+        };
+        let memory = instance
+            .get_memory(&self.store, "memory")
+            .ok_or_else(|| Error::permanent("module doesn't export its memory"))?;
+
+        self.refuel()?;
+
+        let invalid_signature =
+            |e: wasmi::Error| Error::permanent(format!("invalid [{}] signature: {}", export, e));
+        let packed = match input {
+            Some(input) => {
+                let alloc = instance
+                    .get_export(&self.store, "alloc")
+                    .and_then(Extern::into_func)
+                    .ok_or_else(|| Error::permanent("module doesn't export alloc"))?;
+                let ptr =
+                    write_guest(&mut self.store, alloc, memory, input).map_err(Error::permanent)?;
+                func.typed::<(i32, i32), i64>(&self.store)
+                    .map_err(invalid_signature)?
+                    .call(&mut self.store, (ptr, input.len() as i32))
+            }
+            None => func
+                .typed::<(), i64>(&self.store)
+                .map_err(invalid_signature)?
+                .call(&mut self.store, ()),
+        }
+        .map_err(|trap| match trap.trap_code() {
+            Some(TrapCode::OutOfFuel) => Error::timeout(format!("[{}] ran out of fuel", export)),
+            _ => Error::permanent(format!("[{}] trapped: {}", export, trap)),
+        })?;
+
+        let (ptr, len) = unpack(packed);
+        read_guest(&self.store, memory, ptr, len)
+            .map(Some)
+            .map_err(|e| Error::permanent(format!("invalid [{}] result: {}", export, e)))
+    }
+}
+
+/// Result of a call into a module.
+#[derive(Deserialize)]
+struct WasmResponse {
+    #[serde(default)]
+    result: Value,
+
+    #[serde(default)]
+    error: Option<RpcError>,
+}
+
+/// Runs the calls of a plugin in its module, instantiating it again after failures.
+struct WasmRunner {
+    module: WasmModule,
+    plugin_type: String,
+    context: PluginContext,
+
+    // `None` after a call failed, until the next call.
+    instance: Mutex<Option<ModuleInstance>>,
+}
+
+impl WasmRunner {
+    fn new(
+        module: WasmModule,
+        plugin_type: String,
+        context: PluginContext,
+    ) -> Result<WasmRunner, Error> {
+        let runner = WasmRunner {
+            module,
+            plugin_type,
+            context,
+            instance: Mutex::new(None),
+        };
+        *lock(&runner.instance) = Some(runner.instantiate()?);
+        Ok(runner)
+    }
+
+    fn instantiate(&self) -> Result<ModuleInstance, Error> {
+        self.module.instantiate(HostState {
+            plugin_type: self.plugin_type.clone(),
+            context: self.context.clone(),
+            limits: StoreLimits::default(),
+        })
+    }
+
+    /// Calls an export of the module with `params` as JSON, and returns its result. Returns
+    /// `None` when the module doesn't export the function.
+    fn call<P: Serialize>(&self, export: &str, params: &P) -> Result<Option<Value>, Error> {
+        let input = serde_json::to_vec(params)
+            .map_err(|e| Error::permanent("failed to serialize params").with_source(e))?;
+
+        let output = {
+            let mut instance = lock(&self.instance);
+            if instance.is_none() {
+                *instance = Some(self.instantiate()?);
+            }
+
+            let output = instance.as_mut().unwrap().invoke(export, Some(&input));
+            if output.is_err() {
+                // The module may have been left in an inconsistent state.
+                *instance = None;
+            }
+            output?
+        };
+
+        match output {
+            Some(output) => {
+                let response: WasmResponse = serde_json::from_slice(&output).map_err(|e| {
+                    Error::permanent(format!("invalid [{}] result", export)).with_source(e)
+                })?;
+                match response.error {
+                    Some(error) => Err(Error::from(error)),
+                    None => Ok(Some(response.result)),
+                }
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn call_required<P: Serialize>(&self, export: &str, params: &P) -> Result<Value, Error> {
+        self.call(export, params)?.ok_or_else(|| {
+            Error::permanent(format!(
+                "module {} doesn't export {}",
+                self.module.path.display(),
+                export
+            ))
+        })
+    }
+}
+
+/// Action plugin implemented by a WASM module.
+pub(crate) struct WasmAction {
+    runner: WasmRunner,
+    metadata: PluginMetadata,
+    protocol: u32,
+}
+
+impl ActionPlugin for WasmAction {
+    fn get_type(&self) -> &str {
+        &self.runner.plugin_type
+    }
+
+    fn metadata(&self) -> PluginMetadata {
+        self.metadata.clone()
+    }
+
+    fn validate_config(&self, config: &Payload) -> Result<(), Error> {
+        self.runner.call("validate_config", config).map(|_| ())
+    }
+
+    fn execute_action(&self, manifest: ActionManifest) -> Result<(), Error> {
+        self.execute_with_result(manifest).map(|_| ())
+    }
+
+    fn execute_with_result(&self, manifest: ActionManifest) -> Result<ActionResult, Error> {
+        let answer = self.runner.call_required("execute_action", &manifest)?;
+        action_result(self.protocol, answer)
+    }
+}
+
+/// Trigger plugin implemented by a WASM module.
+pub(crate) struct WasmTrigger {
+    runner: WasmRunner,
+    metadata: PluginMetadata,
+}
+
+impl TriggerPlugin for WasmTrigger {
+    fn get_type(&self) -> &str {
+        &self.runner.plugin_type
+    }
+
+    fn metadata(&self) -> PluginMetadata {
+        self.metadata.clone()
+    }
+
+    fn validate_config(&self, cfg: &TriggerConfiguration) -> Result<(), Error> {
+        self.runner.call("validate_config", cfg).map(|_| ())
+    }
+
+    fn pull_trigger(&self, cfg: &TriggerConfiguration) -> Result<Vec<Trigger>, Error> {
+        let data: Vec<Value> =
+            serde_json::from_value(self.runner.call_required("pull_trigger", cfg)?)
+                .map_err(|e| Error::permanent("invalid [pull_trigger] result").with_source(e))?;
+        Ok(data
+            .into_iter()
+            .map(|data| Trigger::new(cfg.rule.clone(), cfg.trigger_type.clone(), data.into()))
+            .collect())
+    }
+}
+
+/// Plugin implemented by a WASM module, as seen by the host.
+pub(crate) enum WasmPlugin {
+    Action(WasmAction),
+    Trigger(WasmTrigger),
+}
+
+impl WasmPlugin {
+    /// Instantiates the plugin described by a module, with the context of its plugin type.
+    pub fn new(
+        module: WasmModule,
+        description: PluginDescription,
+        context: PluginContext,
+    ) -> Result<WasmPlugin, Error> {
+        description.check_protocol()?;
+        let metadata = description.metadata();
+        let protocol = description.protocol;
+        let runner = WasmRunner::new(module, description.plugin_type, context)?;
+
+        Ok(match description.kind {
+            DescribedKind::Action => WasmPlugin::Action(WasmAction {
+                runner,
+                metadata,
+                protocol,
+            }),
+            DescribedKind::Trigger => WasmPlugin::Trigger(WasmTrigger { runner, metadata }),
+        })
+    }
+}
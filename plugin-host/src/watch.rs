@@ -0,0 +1,74 @@
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::PluginHost;
+
+/// Reloads the plugins of a host periodically, checks its plugin processes are healthy, and
+/// reports its suspended plugins, from a background thread.
+///
+/// Stops when dropped, or once the host is dropped.
+pub struct PluginWatcher {
+    stop_tx: Option<mpsc::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl PluginWatcher {
+    pub fn start(host: &Arc<PluginHost>, interval: Duration) -> Self {
+        let host: Weak<PluginHost> = Arc::downgrade(host);
+        let (stop_tx, stop_rx) = mpsc::channel();
+
+        let handle = thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stop_rx.recv_timeout(interval) {
+                let host = match host.upgrade() {
+                    Some(host) => host,
+                    None => break,
+                };
+
+                if let Err(e) = host.reload() {
+                    log::error!("failed to reload plugins: {}", e);
+                }
+
+                for unhealthy in host.unhealthy_plugins().into_iter() {
+                    if unhealthy.suspended {
+                        log::warn!(
+                            "plugin [{}] is suspended after panicking {} times in a row: {}",
+                            &unhealthy.plugin_type,
+                            unhealthy.consecutive_panics,
+                            &unhealthy.last_panic
+                        );
+                    }
+                }
+
+                for health in host.check_process_plugins().into_iter() {
+                    if let Some(error) = health.error {
+                        log::warn!(
+                            "plugin process {} ({}) is unhealthy: {}",
+                            health.command.display(),
+                            &health.plugin_type,
+                            error
+                        );
+                    }
+                }
+            }
+        });
+
+        PluginWatcher {
+            stop_tx: Some(stop_tx),
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for PluginWatcher {
+    fn drop(&mut self) {
+        // Dropping the sender wakes the watcher up.
+        self.stop_tx.take();
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                log::error!("plugin watcher panicked");
+            }
+        }
+    }
+}
@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use plugin_core::{logging, Error as PlugError};
+
+use serde::{Deserialize, Serialize};
+
+/// Timeout of the calls into plugins without a more specific timeout, in milliseconds.
+pub const DEFAULT_CALL_TIMEOUT_MS: u64 = 30_000;
+
+/// Number of consecutive panics after which calls into a plugin are refused for a while.
+pub(crate) const PANICS_BEFORE_SUSPENSION: u64 = 3;
+
+/// How long calls into a plugin are refused once it reached [`PANICS_BEFORE_SUSPENSION`],
+/// doubled on every subsequent panic.
+const BASE_SUSPENSION: Duration = Duration::from_secs(10);
+const MAX_SUSPENSION: Duration = Duration::from_secs(600);
+
+/// How long calls into plugins may run, in milliseconds.
+///
+/// The most specific timeout applies: the timeout of the rule the call is made for, then the
+/// timeout of the plugin type, then the default one.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
+pub struct CallTimeouts {
+    /// Defaults to [`DEFAULT_CALL_TIMEOUT_MS`]. Calls without a more specific timeout are
+    /// unbounded when explicitly set to `None`.
+    pub default: Option<u64>,
+
+    /// Timeouts by plugin type.
+    pub plugins: HashMap<String, u64>,
+
+    /// Timeouts by rule.
+    pub rules: HashMap<String, u64>,
+}
+
+impl Default for CallTimeouts {
+    fn default() -> Self {
+        CallTimeouts {
+            default: Some(DEFAULT_CALL_TIMEOUT_MS),
+            plugins: HashMap::new(),
+            rules: HashMap::new(),
+        }
+    }
+}
+
+impl CallTimeouts {
+    /// Returns the timeout of a call made to a plugin for a rule, if any.
+    pub fn get(&self, plugin_type: &str, rule: &str) -> Option<Duration> {
+        self.rules
+            .get(rule)
+            .or_else(|| self.plugins.get(plugin_type))
+            .copied()
+            .or(self.default)
+            .map(Duration::from_millis)
+    }
+
+    /// Returns the timeout of the calls made to a plugin without a rule specific timeout, if any.
+    pub fn plugin(&self, plugin_type: &str) -> Option<Duration> {
+        self.plugins
+            .get(plugin_type)
+            .copied()
+            .or(self.default)
+            .map(Duration::from_millis)
+    }
+}
+
+/// Plugin which panicked since it was loaded.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct UnhealthyPlugin {
+    pub plugin_type: String,
+    pub panics: u64,
+
+    /// Panics since the plugin last returned normally.
+    pub consecutive_panics: u64,
+
+    pub last_panic: String,
+
+    /// Whether calls into the plugin are refused for now, as it kept panicking.
+    pub suspended: bool,
+}
+
+/// Panics of a plugin.
+struct PanicRecord {
+    plugin: UnhealthyPlugin,
+
+    // Calls into the plugin are refused until then.
+    suspended_until: Option<Instant>,
+}
+
+/// Future catching the panics of the future it wraps.
+struct CatchUnwind<F> {
+    inner: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for CatchUnwind<F> {
+    type Output = std::thread::Result<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let inner = self.inner.as_mut();
+        match panic::catch_unwind(AssertUnwindSafe(|| inner.poll(cx))) {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Enforces the call timeouts, and turns the panics of plugins into errors, keeping count of
+/// both.
+#[derive(Default)]
+pub(crate) struct Watchdog {
+    timeouts: CallTimeouts,
+
+    // Timed out calls, by plugin type.
+    timed_out: Mutex<HashMap<String, u64>>,
+
+    // Plugins which panicked, by plugin type.
+    unhealthy: Mutex<HashMap<String, PanicRecord>>,
+}
+
+impl Watchdog {
+    pub fn new(timeouts: CallTimeouts) -> Self {
+        Watchdog {
+            timeouts,
+            ..Default::default()
+        }
+    }
+
+    pub async fn watch<T, F>(&self, plugin_type: &str, rule: &str, call: F) -> Result<T, PlugError>
+    where
+        F: Future<Output = Result<T, PlugError>>,
+    {
+        self.check_suspended(plugin_type)?;
+        let call = CatchUnwind {
+            inner: Box::pin(call),
+        };
+
+        let result = match self.timeouts.get(plugin_type, rule) {
+            Some(timeout) => match tokio::time::timeout(timeout, call).await {
+                Ok(result) => result,
+                Err(_) => {
+                    *lock(&self.timed_out)
+                        .entry(String::from(plugin_type))
+                        .or_default() += 1;
+                    return Err(PlugError::timeout(format!(
+                        "[{}] call for rule {} timed out after {:?}",
+                        plugin_type, rule, timeout
+                    )));
+                }
+            },
+            None => call.await,
+        };
+
+        self.outcome(plugin_type, result)
+    }
+
+    pub fn guard<T, F>(&self, plugin_type: &str, call: F) -> Result<T, PlugError>
+    where
+        F: FnOnce() -> Result<T, PlugError>,
+    {
+        self.check_suspended(plugin_type)?;
+        self.outcome(plugin_type, panic::catch_unwind(AssertUnwindSafe(call)))
+    }
+
+    /// Refuses the calls into a plugin suspended after panicking too many times in a row.
+    fn check_suspended(&self, plugin_type: &str) -> Result<(), PlugError> {
+        let unhealthy = lock(&self.unhealthy);
+        let suspended_until = unhealthy
+            .get(plugin_type)
+            .and_then(|record| record.suspended_until);
+        match suspended_until {
+            Some(until) if until > Instant::now() => Err(PlugError::transient(format!(
+                "[{}] suspended after panicking {} times in a row",
+                plugin_type, unhealthy[plugin_type].plugin.consecutive_panics
+            ))
+            .with_retry_after(until - Instant::now())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the result of a call, turning a panic into an error.
+    fn outcome<T>(
+        &self,
+        plugin_type: &str,
+        result: std::thread::Result<Result<T, PlugError>>,
+    ) -> Result<T, PlugError> {
+        match result {
+            Ok(result) => {
+                if let Some(record) = lock(&self.unhealthy).get_mut(plugin_type) {
+                    record.plugin.consecutive_panics = 0;
+                }
+                result
+            }
+            Err(payload) => Err(self.panicked(plugin_type, payload)),
+        }
+    }
+
+    fn panicked(&self, plugin_type: &str, payload: Box<dyn std::any::Any + Send>) -> PlugError {
+        // The payload was allocated by the plugin, so it is dropped right away rather than kept
+        // around: its drop code may be gone once the library of the plugin is retired.
+        let message = String::from(logging::panic_message(payload.as_ref()));
+        drop(payload);
+        log::error!("plugin [{}] panicked: {}", plugin_type, message);
+
+        let mut unhealthy = lock(&self.unhealthy);
+        let record = unhealthy
+            .entry(String::from(plugin_type))
+            .or_insert_with(|| PanicRecord {
+                plugin: UnhealthyPlugin {
+                    plugin_type: String::from(plugin_type),
+                    panics: 0,
+                    consecutive_panics: 0,
+                    last_panic: String::new(),
+                    suspended: false,
+                },
+                suspended_until: None,
+            });
+        record.plugin.panics += 1;
+        record.plugin.consecutive_panics += 1;
+
+        let consecutive_panics = record.plugin.consecutive_panics;
+        if consecutive_panics >= PANICS_BEFORE_SUSPENSION {
+            let exponent = (consecutive_panics - PANICS_BEFORE_SUSPENSION).min(31) as u32;
+            let suspension = BASE_SUSPENSION
+                .saturating_mul(2u32.saturating_pow(exponent))
+                .min(MAX_SUSPENSION);
+            log::error!(
+                "plugin [{}] panicked {} times in a row, suspended for {:?}",
+                plugin_type,
+                consecutive_panics,
+                suspension
+            );
+            record.suspended_until = Some(Instant::now() + suspension);
+        }
+
+        let error = PlugError::permanent(format!("plugin panicked: {}", message));
+        record.plugin.last_panic = message;
+        error
+    }
+
+    pub fn timeouts(&self) -> &CallTimeouts {
+        &self.timeouts
+    }
+
+    pub fn timed_out(&self) -> HashMap<String, u64> {
+        lock(&self.timed_out).clone()
+    }
+
+    pub fn unhealthy(&self) -> Vec<UnhealthyPlugin> {
+        let now = Instant::now();
+        let mut unhealthy: Vec<UnhealthyPlugin> = lock(&self.unhealthy)
+            .values()
+            .map(|record| UnhealthyPlugin {
+                suspended: record
+                    .suspended_until
+                    .map(|until| until > now)
+                    .unwrap_or(false),
+                ..record.plugin.clone()
+            })
+            .collect();
+        unhealthy.sort_by(|a, b| a.plugin_type.cmp(&b.plugin_type));
+        unhealthy
+    }
+
+    /// Forgets the panics of plugins, and lifts their suspension, once they were replaced.
+    pub fn heal<'a, I: Iterator<Item = &'a str>>(&self, plugin_types: I) {
+        let mut unhealthy = lock(&self.unhealthy);
+        for plugin_type in plugin_types {
+            unhealthy.remove(plugin_type);
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use protocol::{ActionManifest, Payload, Trigger};
+
+use serde::Serialize;
+use serde_json::Value;
+
+fn to_values<T: Serialize>(expected: &[T]) -> Vec<Value> {
+    expected
+        .iter()
+        .map(|e| serde_json::to_value(e).expect("expected data must be serializable"))
+        .collect()
+}
+
+#[track_caller]
+fn assert_same_data(kind: &str, actual: Vec<&Payload>, expected: Vec<Value>) {
+    let mut unmatched: Vec<&Payload> = actual.clone();
+    for value in expected.iter() {
+        match unmatched.iter().position(|p| p.as_value() == value) {
+            Some(idx) => {
+                unmatched.remove(idx);
+            }
+            None => panic!(
+                "no {} has data {} (got: {:?})",
+                kind,
+                value,
+                actual.iter().map(|p| p.to_string()).collect::<Vec<_>>()
+            ),
+        }
+    }
+
+    assert!(
+        unmatched.is_empty(),
+        "unexpected {}(s) with data {:?}",
+        kind,
+        unmatched.iter().map(|p| p.to_string()).collect::<Vec<_>>()
+    );
+}
+
+/// Asserts the triggers carry exactly the expected data, in any order.
+#[track_caller]
+pub fn assert_trigger_data<T: Serialize>(triggers: &[Trigger], expected: &[T]) {
+    assert_same_data(
+        "trigger",
+        triggers.iter().map(|t| &t.data).collect(),
+        to_values(expected),
+    );
+}
+
+/// Asserts the manifests carry exactly the expected data, in any order.
+#[track_caller]
+pub fn assert_manifest_data<T: Serialize>(manifests: &[ActionManifest], expected: &[T]) {
+    assert_same_data(
+        "manifest",
+        manifests.iter().map(|m| &m.data).collect(),
+        to_values(expected),
+    );
+}
@@ -0,0 +1,43 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use plugin_core::{Clock, PluginClock};
+
+/// Clock only moving when told to. Starts at the Unix epoch.
+#[derive(Clone)]
+pub struct FakeClock {
+    now: Arc<Mutex<SystemTime>>,
+}
+
+impl FakeClock {
+    pub fn new(now: SystemTime) -> Self {
+        FakeClock {
+            now: Arc::new(Mutex::new(now)),
+        }
+    }
+
+    pub fn set(&self, now: SystemTime) {
+        *self.now.lock().unwrap() = now;
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+
+    /// Returns a plugin clock reading this clock.
+    pub fn plugin_clock(&self) -> PluginClock {
+        PluginClock::new(Arc::new(self.clone()))
+    }
+}
+
+impl Default for FakeClock {
+    fn default() -> Self {
+        FakeClock::new(SystemTime::UNIX_EPOCH)
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock().unwrap()
+    }
+}
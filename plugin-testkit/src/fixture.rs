@@ -0,0 +1,89 @@
+use protocol::{ActionManifest, Envelope, Payload, Schedule, TriggerConfiguration};
+
+use serde::Serialize;
+
+fn encode<T: Serialize>(data: T) -> Payload {
+    Payload::encode(&data).expect("fixture data must be serializable")
+}
+
+/// Builds trigger configurations. Defaults to config `1` of rule `1`, with null data.
+#[derive(Clone, Debug)]
+pub struct TriggerConfigBuilder {
+    cfg: TriggerConfiguration,
+}
+
+impl TriggerConfigBuilder {
+    pub fn new(trigger_type: &str) -> Self {
+        TriggerConfigBuilder {
+            cfg: TriggerConfiguration {
+                id: 1,
+                rule: String::from("1"),
+                trigger_type: String::from(trigger_type),
+                data: Payload::default(),
+                schedule: Default::default(),
+            },
+        }
+    }
+
+    pub fn id(mut self, id: i64) -> Self {
+        self.cfg.id = id;
+        self
+    }
+
+    pub fn rule(mut self, rule: &str) -> Self {
+        self.cfg.rule = String::from(rule);
+        self
+    }
+
+    pub fn data<T: Serialize>(mut self, data: T) -> Self {
+        self.cfg.data = encode(data);
+        self
+    }
+
+    pub fn schedule(mut self, schedule: Schedule) -> Self {
+        self.cfg.schedule = schedule;
+        self
+    }
+
+    pub fn build(self) -> TriggerConfiguration {
+        self.cfg
+    }
+}
+
+/// Builds action manifests. Defaults to a first attempt for rule `1`, with null data.
+#[derive(Clone, Debug)]
+pub struct ManifestBuilder {
+    manifest: ActionManifest,
+}
+
+impl ManifestBuilder {
+    pub fn new(action_type: &str) -> Self {
+        ManifestBuilder {
+            manifest: ActionManifest {
+                rule: String::from("1"),
+                action_type: String::from(action_type),
+                data: Payload::default(),
+                envelope: Envelope::new(),
+            },
+        }
+    }
+
+    pub fn rule(mut self, rule: &str) -> Self {
+        self.manifest.rule = String::from(rule);
+        self
+    }
+
+    pub fn data<T: Serialize>(mut self, data: T) -> Self {
+        self.manifest.data = encode(data);
+        self
+    }
+
+    pub fn attempt(mut self, attempt: u32) -> Self {
+        self.manifest.envelope.attempt = attempt;
+        self
+    }
+
+    pub fn build(self) -> ActionManifest {
+        self.manifest
+    }
+}
@@ -0,0 +1,132 @@
+use std::sync::Arc;
+
+use plugin_core::{ActionPlugin, Error, PluginContext, PluginState, TriggerPlugin};
+
+use protocol::{ActionManifest, ActionResult, Payload, Trigger, TriggerConfiguration};
+
+use serde::Serialize;
+
+use crate::{FakeClock, FakeStateStore};
+
+/// Context the harnesses initialize plugins with.
+#[derive(Default)]
+pub struct TestContext {
+    pub settings: Payload,
+    pub store: Arc<FakeStateStore>,
+    pub clock: FakeClock,
+}
+
+impl TestContext {
+    pub fn new() -> Self {
+        TestContext::default()
+    }
+
+    pub fn settings<T: Serialize>(mut self, settings: T) -> Self {
+        self.settings = Payload::encode(&settings).expect("settings must be serializable");
+        self
+    }
+
+    /// Returns the context handed to plugins.
+    pub fn plugin_context(&self) -> PluginContext {
+        PluginContext::new(self.settings.clone(), PluginState::new(self.store.clone()))
+            .with_clock(self.clock.plugin_clock())
+    }
+}
+
+/// Drives a trigger plugin in-process.
+pub struct TriggerHarness<P> {
+    plugin: P,
+    ctx: TestContext,
+}
+
+impl<P: TriggerPlugin> TriggerHarness<P> {
+    /// Initializes a plugin with a default context. Panics if the plugin fails to initialize.
+    pub fn new(plugin: P) -> Self {
+        TriggerHarness::with_context(plugin, TestContext::default())
+            .expect("failed to initialize plugin")
+    }
+
+    pub fn with_context(mut plugin: P, ctx: TestContext) -> Result<Self, Error> {
+        plugin.init(&ctx.plugin_context())?;
+        Ok(TriggerHarness { plugin, ctx })
+    }
+
+    pub fn plugin(&self) -> &P {
+        &self.plugin
+    }
+
+    pub fn state(&self) -> &FakeStateStore {
+        &self.ctx.store
+    }
+
+    pub fn clock(&self) -> &FakeClock {
+        &self.ctx.clock
+    }
+
+    pub fn validate(&self, cfg: &TriggerConfiguration) -> Result<(), Error> {
+        self.plugin.validate_config(cfg)
+    }
+
+    pub fn pull(&self, cfg: &TriggerConfiguration) -> Result<Vec<Trigger>, Error> {
+        self.plugin.pull_trigger(cfg)
+    }
+
+    pub fn shutdown(self) -> Result<(), Error> {
+        self.plugin.shutdown()
+    }
+}
+
+/// Drives an action plugin in-process, keeping the manifests it executed successfully.
+pub struct ActionHarness<P> {
+    plugin: P,
+    ctx: TestContext,
+    executed: Vec<ActionManifest>,
+}
+
+impl<P: ActionPlugin> ActionHarness<P> {
+    /// Initializes a plugin with a default context. Panics if the plugin fails to initialize.
+    pub fn new(plugin: P) -> Self {
+        ActionHarness::with_context(plugin, TestContext::default())
+            .expect("failed to initialize plugin")
+    }
+
+    pub fn with_context(mut plugin: P, ctx: TestContext) -> Result<Self, Error> {
+        plugin.init(&ctx.plugin_context())?;
+        Ok(ActionHarness {
+            plugin,
+            ctx,
+            executed: Vec::new(),
+        })
+    }
+
+    pub fn plugin(&self) -> &P {
+        &self.plugin
+    }
+
+    pub fn state(&self) -> &FakeStateStore {
+        &self.ctx.store
+    }
+
+    pub fn clock(&self) -> &FakeClock {
+        &self.ctx.clock
+    }
+
+    pub fn validate(&self, config: &Payload) -> Result<(), Error> {
+        self.plugin.validate_config(config)
+    }
+
+    pub fn execute(&mut self, manifest: ActionManifest) -> Result<ActionResult, Error> {
+        let result = self.plugin.execute_with_result(manifest.clone())?;
+        self.executed.push(manifest);
+        Ok(result)
+    }
+
+    /// Returns the manifests executed successfully so far.
+    pub fn executed(&self) -> &[ActionManifest] {
+        &self.executed
+    }
+
+    pub fn shutdown(self) -> Result<(), Error> {
+        self.plugin.shutdown()
+    }
+}
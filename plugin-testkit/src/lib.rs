@@ -0,0 +1,24 @@
+//! Helpers for testing plugins.
+//!
+//! Plugins can be tested in-process with [`TriggerHarness`] and [`ActionHarness`], which
+//! initialize them with a [`FakeStateStore`] and a [`FakeClock`], or through the compiled
+//! library with [`LibraryHarness`].
+
+mod assert;
+mod clock;
+mod fixture;
+mod harness;
+mod library;
+mod sink;
+mod state;
+
+pub use assert::{assert_manifest_data, assert_trigger_data};
+pub use clock::FakeClock;
+pub use fixture::{ManifestBuilder, TriggerConfigBuilder};
+pub use harness::{ActionHarness, TestContext, TriggerHarness};
+pub use library::{artifact_path, build_library, LibraryHarness};
+pub use sink::RecordingSink;
+pub use state::FakeStateStore;
+
+#[cfg(test)]
+mod tests;
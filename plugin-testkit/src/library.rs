@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+
+use plugin_core::Error;
+
+use plugin_host::{Error as HostError, PluginHost, PluginHostConfig};
+
+use protocol::{ActionManifest, ActionResult, Payload, Trigger, TriggerConfiguration};
+
+use tokio::runtime::Runtime;
+
+// Libraries built by `build_library`, by package.
+static BUILT_LIBRARIES: Mutex<Vec<(String, PathBuf)>> = Mutex::new(Vec::new());
+
+/// Returns the directory of the profile the running test was built with.
+fn profile_dir() -> PathBuf {
+    // Test binaries live in `target/<profile>/deps`.
+    let exe = std::env::current_exe().expect("failed to locate test binary");
+    exe.parent()
+        .and_then(Path::parent)
+        .map(PathBuf::from)
+        .expect("test binary is not in a target directory")
+}
+
+fn library_file_name(crate_name: &str) -> String {
+    format!(
+        "{}{}{}",
+        DLL_PREFIX,
+        crate_name.replace('-', "_"),
+        DLL_SUFFIX
+    )
+}
+
+/// Returns the path of a plugin library built in the target directory of the running test.
+///
+/// The library must be built beforehand (e.g. with `cargo build -p directory_watch`), see
+/// [`build_library`] to build it from the test.
+pub fn artifact_path(crate_name: &str) -> PathBuf {
+    profile_dir().join(library_file_name(crate_name))
+}
+
+/// Builds the plugin library of a package of the workspace under test, and returns its path.
+///
+/// The library is built by the same toolchain as the test, so the host accepts it. It is built
+/// in its own target directory, as the target directory of the test is locked by the running
+/// `cargo test`. Each package is only built once per test binary.
+pub fn build_library(package: &str) -> PathBuf {
+    let mut built = BUILT_LIBRARIES.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((_, path)) = built.iter().find(|(name, _)| name == package) {
+        return path.clone();
+    }
+
+    let target_dir = profile_dir()
+        .parent()
+        .map(|target| target.join("test-plugins"))
+        .expect("test binary is not in a target directory");
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| String::from("cargo"));
+    let status = Command::new(cargo)
+        .args(["build", "--package", package, "--target-dir"])
+        .arg(&target_dir)
+        .current_dir(std::env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| String::from(".")))
+        .status()
+        .expect("failed to run cargo");
+    assert!(
+        status.success(),
+        "failed to build plugin library {}",
+        package
+    );
+
+    let path = target_dir.join("debug").join(library_file_name(package));
+    built.push((String::from(package), path.clone()));
+    path
+}
+
+/// Loads a compiled plugin library through a [`PluginHost`], to test the real artifact.
+pub struct LibraryHarness {
+    host: PluginHost,
+    runtime: Runtime,
+}
+
+impl LibraryHarness {
+    pub fn load<P: AsRef<Path>>(library_path: P) -> Result<Self, HostError> {
+        LibraryHarness::load_with_settings(library_path, HashMap::new())
+    }
+
+    /// Loads a library, initializing its plugins with the given settings, keyed by plugin type.
+    pub fn load_with_settings<P: AsRef<Path>>(
+        library_path: P,
+        settings: HashMap<String, Payload>,
+    ) -> Result<Self, HostError> {
+        let host = PluginHost::initialize(PluginHostConfig {
+            settings,
+            ..Default::default()
+        })?;
+        host.add_plugin(library_path)?;
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .expect("failed to build runtime");
+
+        Ok(LibraryHarness { host, runtime })
+    }
+
+    pub fn host(&self) -> &PluginHost {
+        &self.host
+    }
+
+    /// Polls the trigger plugin handling the type of a config. Panics if there is none.
+    pub fn pull(&self, cfg: &TriggerConfiguration) -> Result<Vec<Trigger>, Error> {
+        let plugin = self
+            .host
+            .get_trigger_plugins()
+            .into_iter()
+            .find(|p| p.get_type() == cfg.trigger_type)
+            .unwrap_or_else(|| panic!("no trigger plugin handles [{}]", cfg.trigger_type));
+        self.runtime.block_on(plugin.pull_trigger(cfg))
+    }
+
+    /// Executes a manifest with the action plugin handling its type. Panics if there is none.
+    pub fn execute(&self, manifest: ActionManifest) -> Result<ActionResult, Error> {
+        let plugin = self
+            .host
+            .get_action_plugins()
+            .into_iter()
+            .find(|p| p.get_type() == manifest.action_type)
+            .unwrap_or_else(|| panic!("no action plugin handles [{}]", manifest.action_type));
+        self.runtime.block_on(plugin.execute_with_result(manifest))
+    }
+}
@@ -0,0 +1,58 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use plugin_core::{Error, TriggerSink};
+
+use protocol::Trigger;
+
+/// Trigger sink keeping the triggers emitted by push trigger plugins.
+#[derive(Default)]
+pub struct RecordingSink {
+    triggers: Mutex<Vec<Trigger>>,
+    emitted: Condvar,
+    closed: AtomicBool,
+}
+
+impl RecordingSink {
+    /// Returns the triggers emitted so far.
+    pub fn triggers(&self) -> Vec<Trigger> {
+        self.triggers.lock().unwrap().clone()
+    }
+
+    /// Waits until at least `count` triggers were emitted, or until the timeout expires.
+    /// Returns the triggers emitted so far.
+    pub fn wait_for(&self, count: usize, timeout: Duration) -> Vec<Trigger> {
+        let deadline = Instant::now() + timeout;
+        let mut triggers = self.triggers.lock().unwrap();
+        while triggers.len() < count {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            triggers = self
+                .emitted
+                .wait_timeout(triggers, deadline - now)
+                .unwrap()
+                .0;
+        }
+        triggers.clone()
+    }
+
+    /// Rejects subsequent triggers, like the trigger system does once stopped.
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+    }
+}
+
+impl TriggerSink for RecordingSink {
+    fn emit(&self, trigger: Trigger) -> Result<(), Error> {
+        if self.closed.load(Ordering::SeqCst) {
+            return Err(Error::permanent("trigger sink is closed"));
+        }
+
+        self.triggers.lock().unwrap().push(trigger);
+        self.emitted.notify_all();
+        Ok(())
+    }
+}
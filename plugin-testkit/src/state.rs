@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use plugin_core::{Error, StateStore};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+/// In-memory state store which can be inspected, seeded, and made to fail.
+#[derive(Default)]
+pub struct FakeStateStore {
+    data: Mutex<HashMap<String, Vec<u8>>>,
+    failing: AtomicBool,
+}
+
+impl FakeStateStore {
+    /// Stores a value, as the plugin would have in a previous run.
+    pub fn seed<T: Serialize>(&self, key: &str, value: &T) {
+        let data = serde_json::to_vec(value).expect("seeded state must be serializable");
+        self.data.lock().unwrap().insert(String::from(key), data);
+    }
+
+    /// Returns the value stored by the plugin at a key.
+    pub fn value<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.data
+            .lock()
+            .unwrap()
+            .get(key)
+            .map(|data| serde_json::from_slice(data).expect("stored state must be valid JSON"))
+    }
+
+    /// Returns the stored keys, sorted.
+    pub fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.data.lock().unwrap().keys().cloned().collect();
+        keys.sort();
+        keys
+    }
+
+    /// Makes every subsequent operation fail with a transient error, until reset.
+    pub fn set_failing(&self, failing: bool) {
+        self.failing.store(failing, Ordering::SeqCst);
+    }
+
+    fn check(&self) -> Result<(), Error> {
+        if self.failing.load(Ordering::SeqCst) {
+            Err(Error::transient("state store is failing"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl StateStore for FakeStateStore {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        self.check()?;
+        Ok(self.data.lock().unwrap().get(key).cloned())
+    }
+
+    fn set(&self, key: &str, value: &[u8]) -> Result<(), Error> {
+        self.check()?;
+        self.data
+            .lock()
+            .unwrap()
+            .insert(String::from(key), Vec::from(value));
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> Result<(), Error> {
+        self.check()?;
+        self.data.lock().unwrap().remove(key);
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<(), Error> {
+        self.check()
+    }
+}
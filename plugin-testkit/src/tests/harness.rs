@@ -0,0 +1,91 @@
+use std::thread;
+use std::time::Duration;
+
+use plugin_core::{ErrorKind, TriggerSink};
+
+use protocol::{Payload, Trigger};
+
+use serde_json::json;
+
+use crate::{
+    assert_manifest_data, assert_trigger_data, ActionHarness, ManifestBuilder, RecordingSink,
+    TestContext, TriggerConfigBuilder, TriggerHarness,
+};
+
+use super::mock::{Echo, Ticker};
+
+#[test]
+fn trigger_harness() {
+    let harness = TriggerHarness::new(Ticker::default());
+    let cfg = TriggerConfigBuilder::new("ticker").rule("42").build();
+
+    assert!(harness.pull(&cfg).unwrap().is_empty());
+    assert_eq!(harness.state().keys(), vec!["last_poll.42"]);
+
+    harness.clock().advance(Duration::from_secs(10));
+    let triggers = harness.pull(&cfg).unwrap();
+    assert_trigger_data(&triggers, &[json!({ "elapsed": 10 })]);
+    assert_eq!(triggers[0].rule, "42");
+    assert_eq!(harness.state().value::<u64>("last_poll.42"), Some(10));
+}
+
+#[test]
+fn seeded_and_failing_state() {
+    let harness = TriggerHarness::new(Ticker::default());
+    let cfg = TriggerConfigBuilder::new("ticker").build();
+
+    harness.state().seed("last_poll.1", &0u64);
+    harness.clock().advance(Duration::from_secs(3));
+    assert_trigger_data(&harness.pull(&cfg).unwrap(), &[json!({ "elapsed": 3 })]);
+
+    harness.state().set_failing(true);
+    assert_eq!(harness.pull(&cfg).unwrap_err().kind, ErrorKind::Transient);
+}
+
+#[test]
+fn action_harness() {
+    let ctx = TestContext::new().settings(json!({ "refuse": ["bong"] }));
+    let mut harness = ActionHarness::with_context(Echo::default(), ctx).unwrap();
+
+    assert!(harness.validate(&json!("hello").into()).is_ok());
+    assert!(harness.validate(&Payload::default()).is_err());
+
+    let result = harness
+        .execute(ManifestBuilder::new("echo").data("bing").build())
+        .unwrap();
+    assert_eq!(result.summary.as_deref(), Some("echoed"));
+    assert_eq!(result.data, json!("bing").into());
+    let err = harness
+        .execute(ManifestBuilder::new("echo").data("bong").build())
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Permanent);
+
+    assert_manifest_data(harness.executed(), &["bing"]);
+}
+
+#[test]
+#[should_panic(expected = "no trigger has data")]
+fn assert_trigger_data_mismatch() {
+    let trigger = Trigger::new("1".into(), "ticker".into(), json!(1).into());
+    assert_trigger_data(&[trigger], &[json!(2)]);
+}
+
+#[test]
+fn recording_sink() {
+    let sink = std::sync::Arc::new(RecordingSink::default());
+
+    let thread_sink = sink.clone();
+    thread::spawn(move || {
+        thread_sink
+            .emit(Trigger::new("1".into(), "push".into(), json!(1).into()))
+            .unwrap();
+    });
+
+    assert_eq!(sink.wait_for(1, Duration::from_secs(5)).len(), 1);
+
+    sink.close();
+    assert!(sink
+        .emit(Trigger::new("1".into(), "push".into(), json!(2).into()))
+        .is_err());
+    assert_eq!(sink.triggers().len(), 1);
+}
@@ -0,0 +1,25 @@
+use std::fs;
+
+use serde_json::json;
+
+use tempfile::tempdir;
+
+use crate::{assert_trigger_data, build_library, LibraryHarness, TriggerConfigBuilder};
+
+#[test]
+fn library_harness() {
+    let watched_dir = tempdir().unwrap();
+    let harness = LibraryHarness::load(build_library("directory_watch")).unwrap();
+
+    let cfg = TriggerConfigBuilder::new("directory_watch")
+        .data(json!({ "directory": watched_dir.path() }))
+        .build();
+    assert!(harness.host().validate_trigger_config(&cfg).is_ok());
+
+    assert!(harness.pull(&cfg).unwrap().is_empty());
+    fs::write(watched_dir.path().join("some_file.txt"), "bing bong").unwrap();
+    assert_trigger_data(
+        &harness.pull(&cfg).unwrap(),
+        &[json!({ "file_name": "some_file.txt" })],
+    );
+}
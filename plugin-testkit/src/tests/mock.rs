@@ -0,0 +1,97 @@
+use std::time::UNIX_EPOCH;
+
+use plugin_core::{ActionPlugin, Error, PluginClock, PluginContext, PluginState, TriggerPlugin};
+
+use protocol::{ActionManifest, ActionResult, Payload, Trigger, TriggerConfiguration};
+
+use serde::Deserialize;
+
+use serde_json::json;
+
+/// Trigger firing with the seconds elapsed since the previous poll of a rule.
+#[derive(Default)]
+pub struct Ticker {
+    state: PluginState,
+    clock: PluginClock,
+}
+
+impl TriggerPlugin for Ticker {
+    fn get_type(&self) -> &str {
+        "ticker"
+    }
+
+    fn init(&mut self, ctx: &PluginContext) -> Result<(), Error> {
+        self.state = ctx.state.clone();
+        self.clock = ctx.clock.clone();
+        Ok(())
+    }
+
+    fn pull_trigger(&self, cfg: &TriggerConfiguration) -> Result<Vec<Trigger>, Error> {
+        let now = self
+            .clock
+            .now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| Error::permanent("clock is before epoch").with_source(e))?
+            .as_secs();
+
+        let key = format!("last_poll.{}", cfg.rule);
+        let last_poll: Option<u64> = self.state.get(&key)?;
+        self.state.set(&key, &now)?;
+
+        Ok(match last_poll {
+            Some(last_poll) => vec![Trigger::new(
+                cfg.rule.clone(),
+                cfg.trigger_type.clone(),
+                json!({ "elapsed": now - last_poll }).into(),
+            )],
+            None => Vec::new(),
+        })
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct EchoSettings {
+    refuse: Vec<String>,
+}
+
+/// Action refusing the messages listed in its settings.
+#[derive(Default)]
+pub struct Echo {
+    refuse: Vec<String>,
+}
+
+impl ActionPlugin for Echo {
+    fn get_type(&self) -> &str {
+        "echo"
+    }
+
+    fn init(&mut self, ctx: &PluginContext) -> Result<(), Error> {
+        let settings: EchoSettings = ctx.settings()?;
+        self.refuse = settings.refuse;
+        Ok(())
+    }
+
+    fn validate_config(&self, config: &Payload) -> Result<(), Error> {
+        config
+            .decode::<String>()
+            .map_err(|e| Error::invalid_config("echo config must be a string").with_source(e))?;
+        Ok(())
+    }
+
+    fn execute_action(&self, manifest: ActionManifest) -> Result<(), Error> {
+        self.execute_with_result(manifest).map(|_| ())
+    }
+
+    fn execute_with_result(&self, manifest: ActionManifest) -> Result<ActionResult, Error> {
+        let message: String = manifest
+            .data
+            .decode()
+            .map_err(|e| Error::invalid_config("echo data must be a string").with_source(e))?;
+
+        if self.refuse.contains(&message) {
+            return Err(Error::permanent(format!("refusing [{}]", message)));
+        }
+        Ok(ActionResult::new(manifest.data).with_summary("echoed"))
+    }
+}
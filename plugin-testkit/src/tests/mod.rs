@@ -0,0 +1,3 @@
+mod harness;
+mod library;
+mod mock;
@@ -0,0 +1,85 @@
+use std::env::consts::DLL_PREFIX;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, ensure, Result};
+
+use clap::Parser;
+
+use plugin_host::{AllowedPlugin, PluginHost, PluginManifest};
+
+#[derive(Parser, Debug)]
+#[clap(
+    version = "0.1.0",
+    author = "William Dussault",
+    author = "Laurent Leclerc-Poulin"
+)]
+/// Writes the manifest of a plugin library next to it, and prints the allowlist entry pinning
+/// it.
+pub struct CLIManifest {
+    /// Path to the plugin library or WASM module.
+    library: PathBuf,
+
+    /// Name of the plugin. Defaults to the file name of the library.
+    #[clap(long = "name")]
+    name: Option<String>,
+
+    /// Version of the plugin. Defaults to the version the plugins of the library report.
+    #[clap(long = "plugin-version")]
+    plugin_version: Option<String>,
+}
+
+/// Name of a plugin, from the file name of its library.
+fn library_name(library: &Path) -> Option<String> {
+    let stem = library.file_stem()?.to_str()?;
+    Some(String::from(stem.strip_prefix(DLL_PREFIX).unwrap_or(stem)))
+}
+
+impl CLIManifest {
+    pub fn run(self) -> Result<()> {
+        // The library is loaded to find out the types it provides, so only describe libraries
+        // you trust.
+        let plugin_host = PluginHost::default();
+        plugin_host.add_plugin(&self.library)?;
+
+        let plugins: Vec<_> = plugin_host
+            .list_plugins()
+            .into_iter()
+            .filter(|p| p.library.as_deref() == Some(self.library.as_path()))
+            .collect();
+        ensure!(!plugins.is_empty(), "library provides no plugin");
+
+        let name = match self.name {
+            Some(name) => name,
+            None => library_name(&self.library)
+                .ok_or_else(|| anyhow!("can't name the plugin, pass --name"))?,
+        };
+        let version = match self.plugin_version {
+            Some(version) => version,
+            None => plugins
+                .iter()
+                .find_map(|p| p.metadata.version.clone())
+                .ok_or_else(|| {
+                    anyhow!("plugins don't report their version, pass --plugin-version")
+                })?,
+        };
+        let types: Vec<&str> = plugins.iter().map(|p| p.plugin_type.as_str()).collect();
+
+        let manifest = PluginManifest::for_library(&self.library, &name, &version, &types)?;
+        manifest.write(&self.library)?;
+        plugin_host.shutdown();
+
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&AllowedPlugin::pinned(&manifest))?
+        );
+        Ok(())
+    }
+}
+
+fn main() {
+    let cli = CLIManifest::parse();
+    if let Err(e) = cli.run() {
+        eprintln!("Fatal: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,29 @@
+use anyhow::Result;
+
+use directory_watch::DirectoryWatcher;
+
+use notify::NotifyPlugin;
+
+use plugin_host::PluginHost;
+
+/// Registers the plugins compiled into the node.
+pub fn register(mut plugin_host: PluginHost) -> Result<PluginHost> {
+    plugin_host.add_in_memory_trigger_plugin(Box::new(DirectoryWatcher::default()))?;
+    plugin_host.add_in_memory_action_plugin(Box::new(NotifyPlugin::default()))?;
+    Ok(plugin_host)
+}
+
+#[cfg(test)]
+mod tests {
+    use plugin_host::PluginHost;
+
+    #[test]
+    fn register() {
+        let plugin_host = super::register(PluginHost::default()).unwrap();
+
+        assert!(plugin_host
+            .get_trigger_metadata("directory_watch")
+            .is_some());
+        assert!(plugin_host.get_action_metadata("notify").is_some());
+    }
+}
@@ -5,8 +5,13 @@ use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
 use action_executor::{
-    iface_impl::{InMemoryActionManifestQueueReader, PubsubActionManifestQueueReader},
-    ActionManifestQueueReader, ExecutorSystem, ExecutorSystemConfig,
+    iface_impl::{
+        InMemoryActionManifestQueueReader, InMemoryActionRecordQueueWriter,
+        InMemoryDeadLetterQueueWriter, PubSubActionRecordWriter, PubSubDeadLetterWriter,
+        PubsubActionManifestQueueReader,
+    },
+    ActionManifestQueueReader, ActionRecordQueueWriter, DeadLetterQueueWriter, ExecutorSystem,
+    ExecutorSystemConfig,
 };
 
 use crate::{ResourceManager, Service};
@@ -14,6 +19,14 @@ use crate::{ResourceManager, Service};
 #[derive(Debug, Deserialize, PartialEq, Serialize)]
 pub struct ExecutorSystemConfiguration {
     pub queue_reader: QueueReaderConfiguration,
+
+    /// Where the records of executed actions are published, if anywhere.
+    #[serde(default)]
+    pub record_writer: Option<RecordWriterConfiguration>,
+
+    /// Where quarantined actions are published, if anywhere.
+    #[serde(default)]
+    pub dead_letter_writer: Option<DeadLetterWriterConfiguration>,
 }
 
 impl ExecutorSystemConfiguration {
@@ -22,8 +35,26 @@ impl ExecutorSystemConfiguration {
             .queue_reader
             .into_instance(resource_manager.clone())
             .await?;
+        let record_writer = match self.record_writer {
+            Some(record_writer) => Some(
+                record_writer
+                    .into_instance(resource_manager.clone())
+                    .await?,
+            ),
+            None => None,
+        };
+        let dead_letter_writer = match self.dead_letter_writer {
+            Some(dead_letter_writer) => Some(
+                dead_letter_writer
+                    .into_instance(resource_manager.clone())
+                    .await?,
+            ),
+            None => None,
+        };
         Ok(Box::from(ExecutorSystem::start(ExecutorSystemConfig {
             queue_reader,
+            record_writer,
+            dead_letter_writer,
             plugin_host: resource_manager.get_plugin_host(),
         })))
     }
@@ -68,3 +99,81 @@ impl QueueReaderConfiguration {
         Ok(b)
     }
 }
+
+/// Configuration of the writer publishing the records of executed actions.
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "type")]
+pub enum RecordWriterConfiguration {
+    PubSub {
+        project_id: String,
+        credentials_file_path: String,
+        topic: String,
+    },
+    InMemory {
+        topic: String,
+    },
+}
+
+impl RecordWriterConfiguration {
+    async fn into_instance(
+        self,
+        resource_manager: Arc<ResourceManager>,
+    ) -> Result<Box<dyn ActionRecordQueueWriter + Send + Sync>> {
+        let b: Box<dyn ActionRecordQueueWriter + Send + Sync> = match self {
+            RecordWriterConfiguration::PubSub {
+                project_id,
+                credentials_file_path,
+                topic,
+            } => Box::from(
+                PubSubActionRecordWriter::from_credentials(
+                    project_id,
+                    credentials_file_path,
+                    topic,
+                )
+                .await?,
+            ),
+            RecordWriterConfiguration::InMemory { topic } => Box::from(
+                InMemoryActionRecordQueueWriter::new(resource_manager.get_memory_queue(&topic)?),
+            ),
+        };
+
+        Ok(b)
+    }
+}
+
+/// Configuration of the writer publishing quarantined actions.
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "type")]
+pub enum DeadLetterWriterConfiguration {
+    PubSub {
+        project_id: String,
+        credentials_file_path: String,
+        topic: String,
+    },
+    InMemory {
+        topic: String,
+    },
+}
+
+impl DeadLetterWriterConfiguration {
+    async fn into_instance(
+        self,
+        resource_manager: Arc<ResourceManager>,
+    ) -> Result<Box<dyn DeadLetterQueueWriter + Send + Sync>> {
+        let b: Box<dyn DeadLetterQueueWriter + Send + Sync> = match self {
+            DeadLetterWriterConfiguration::PubSub {
+                project_id,
+                credentials_file_path,
+                topic,
+            } => Box::from(
+                PubSubDeadLetterWriter::from_credentials(project_id, credentials_file_path, topic)
+                    .await?,
+            ),
+            DeadLetterWriterConfiguration::InMemory { topic } => Box::from(
+                InMemoryDeadLetterQueueWriter::new(resource_manager.get_memory_queue(&topic)?),
+            ),
+        };
+
+        Ok(b)
+    }
+}
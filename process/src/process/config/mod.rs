@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 
@@ -7,13 +8,61 @@ mod trigger;
 
 use anyhow::Result;
 
+use plugin_host::{AllowedPlugin, CallTimeouts, PluginSource, WasmLimits};
+
 use serde::{Deserialize, Serialize};
 
+use serde_json::Value;
+
 use crate::{ResourceManager, Service};
 
 #[derive(Default, Deserialize, Serialize)]
 pub struct Configuration {
     pub plugin_paths: Vec<PathBuf>,
+
+    /// Plugin executables, talking to the node over their standard input and output.
+    #[serde(default)]
+    pub process_plugins: Vec<PathBuf>,
+
+    /// Fuel and memory available to the WASM plugins found in the plugin paths.
+    #[serde(default)]
+    pub wasm_limits: WasmLimits,
+
+    /// Plugins allowed to be loaded from the plugin paths, which must come with a manifest.
+    /// No plugin is loaded from the plugin paths when not set. Entries are printed by the
+    /// `shift3-manifest` tool.
+    #[serde(default)]
+    pub plugin_allowlist: Option<Vec<AllowedPlugin>>,
+
+    /// Order in which plugin sources take precedence when several plugins handle the same
+    /// type, e.g. `["library", "wasm", "process"]`. Among plugins of the same source, the ones
+    /// of earlier plugin paths take precedence.
+    #[serde(default)]
+    pub plugin_precedence: Vec<PluginSource>,
+
+    /// Library, module or executable to use for a plugin type when several handle it.
+    #[serde(default)]
+    pub preferred_plugins: HashMap<String, PathBuf>,
+
+    /// How long plugin calls may run, in milliseconds, by default, by plugin type and by rule.
+    /// Calls that run longer fail with a timeout error, and are retried.
+    #[serde(default)]
+    pub plugin_timeouts: CallTimeouts,
+
+    /// Settings passed to plugins when they are initialized, keyed by plugin type.
+    #[serde(default)]
+    pub plugin_settings: HashMap<String, Value>,
+
+    /// Directory of the embedded database holding plugin state.
+    /// Plugin state is lost on restart when not set.
+    #[serde(default)]
+    pub state_directory: Option<PathBuf>,
+
+    /// Interval, in seconds, at which the plugin paths are checked for new or modified plugins.
+    /// Plugins are only loaded at startup when not set.
+    #[serde(default)]
+    pub plugin_reload_interval: Option<u64>,
+
     pub systems: Vec<SystemConfiguration>,
 }
 
@@ -63,4 +112,22 @@ mod tests {
         .await
         .unwrap();
     }
+
+    #[test]
+    fn plugin_settings() {
+        let cfg: Configuration = serde_json::from_str(
+            r#"{
+                "plugin_paths": [],
+                "plugin_settings": {"notify": {"command": "echo"}},
+                "systems": []
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(cfg.plugin_settings["notify"]["command"], "echo");
+
+        // Plugin settings are optional.
+        let cfg: Configuration =
+            serde_json::from_str(r#"{"plugin_paths": [], "systems": []}"#).unwrap();
+        assert!(cfg.plugin_settings.is_empty());
+    }
 }
@@ -1,3 +1,5 @@
+#[cfg(feature = "builtins")]
+mod builtins;
 mod config;
 mod node;
 mod resource_manager;
@@ -1,12 +1,12 @@
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 
 use crate::{Configuration, ResourceManager, Service};
 
 /// A single process node.
 pub struct Node {
-    _resource_manager: Arc<ResourceManager>, // TODO: This handle will  be needed for the persistence API.
+    resource_manager: Arc<ResourceManager>,
     services: Vec<Service>,
 }
 
@@ -32,31 +32,106 @@ impl Node {
         }
 
         Ok(Node {
-            _resource_manager: resource_manager,
+            resource_manager,
             services,
         })
     }
 
+    /// Stops every service, then shuts the plugins down.
+    ///
+    /// Services failing to stop don't prevent the others from stopping, nor the plugins from
+    /// shutting down. Their errors are returned together.
     pub fn stop(self) -> Result<()> {
-        for svc in self.services.into_iter() {
-            svc.stop()?;
+        let errors: Vec<String> = self
+            .services
+            .into_iter()
+            .filter_map(|svc| svc.stop().err())
+            .map(|e| format!("{:#}", e))
+            .collect();
+
+        // Plugins are shut down once no system uses them anymore.
+        self.resource_manager.get_plugin_host().shutdown();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "failed to stop {} service(s): {}",
+                errors.len(),
+                errors.join("; ")
+            ))
         }
-        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use anyhow::{anyhow, Error};
+
+    use toolkit::Stop;
+
     use super::{Configuration, Node};
+    use crate::ResourceManager;
+
+    struct TestService {
+        stopped: Arc<AtomicUsize>,
+        fail: bool,
+    }
+
+    impl Stop for TestService {
+        type Error = Error;
+
+        fn stop(self: Box<Self>) -> Result<(), Error> {
+            self.stopped.fetch_add(1, Ordering::SeqCst);
+            if self.fail {
+                Err(anyhow!("stuck"))
+            } else {
+                Ok(())
+            }
+        }
+    }
 
     #[tokio::test]
     async fn node_full_loop() {
         let cfg = Configuration {
             plugin_paths: Vec::new(),
+            process_plugins: Vec::new(),
+            wasm_limits: Default::default(),
+            plugin_allowlist: None,
+            plugin_precedence: Vec::new(),
+            preferred_plugins: HashMap::new(),
+            plugin_timeouts: Default::default(),
+            plugin_settings: HashMap::new(),
+            state_directory: None,
+            plugin_reload_interval: None,
             systems: Vec::new(),
         };
 
         let n = Node::start(cfg).await.unwrap();
         n.stop().unwrap();
     }
+
+    #[test]
+    fn stop_failures_are_collected() {
+        let stopped = Arc::new(AtomicUsize::new(0));
+        let service = |fail: bool| {
+            Box::new(TestService {
+                stopped: stopped.clone(),
+                fail,
+            }) as crate::Service
+        };
+
+        let n = Node {
+            resource_manager: Arc::new(ResourceManager::default()),
+            services: vec![service(true), service(false), service(true)],
+        };
+
+        let err = n.stop().unwrap_err();
+        assert_eq!(stopped.load(Ordering::SeqCst), 3);
+        assert!(err.to_string().starts_with("failed to stop 2 service(s)"));
+    }
 }
@@ -1,10 +1,11 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use anyhow::{anyhow, Result};
 
-use plugin_host::PluginHost;
+use plugin_host::{PluginHost, PluginHostConfig, PluginWatcher};
 
 use toolkit::db::sled::SledStore;
 use toolkit::queue::MemoryQueue;
@@ -17,6 +18,7 @@ use crate::Configuration;
 #[derive(Default)]
 pub struct ResourceManager {
     plugin_host: Arc<PluginHost>,
+    plugin_watcher: Option<PluginWatcher>,
 
     queues: Mutex<HashMap<String, Arc<MemoryQueue>>>,
 
@@ -25,11 +27,48 @@ pub struct ResourceManager {
 
 impl ResourceManager {
     pub fn new(config: &Configuration) -> Result<ResourceManager> {
-        Ok(ResourceManager {
-            plugin_host: Arc::from(PluginHost::initialize(&config.plugin_paths)?),
+        let mut manager = ResourceManager {
+            plugin_host: Arc::default(),
+            plugin_watcher: None,
             queues: Mutex::new(HashMap::<String, Arc<MemoryQueue>>::new()),
             sleds: Mutex::new(HashMap::<PathBuf, Arc<SledStore>>::new()),
-        })
+        };
+
+        // Plugin state lives in the embedded store, which might be shared with the systems.
+        let state_store = match &config.state_directory {
+            Some(directory) => Some(manager.get_embedded_store(directory)?),
+            None => None,
+        };
+
+        let plugin_host = PluginHost::initialize(PluginHostConfig {
+            search_paths: config.plugin_paths.clone(),
+            process_plugins: config.process_plugins.clone(),
+            wasm_limits: config.wasm_limits.clone(),
+            allowlist: config.plugin_allowlist.clone(),
+            precedence: config.plugin_precedence.clone(),
+            preferred: config.preferred_plugins.clone(),
+            timeouts: config.plugin_timeouts.clone(),
+            settings: config
+                .plugin_settings
+                .iter()
+                .map(|(plugin_type, settings)| (plugin_type.clone(), settings.clone().into()))
+                .collect(),
+            state_store,
+        })?;
+
+        #[cfg(feature = "builtins")]
+        let plugin_host = crate::builtins::register(plugin_host)?;
+
+        manager.plugin_host = Arc::from(plugin_host);
+
+        if let Some(interval) = config.plugin_reload_interval {
+            manager.plugin_watcher = Some(PluginWatcher::start(
+                &manager.plugin_host,
+                Duration::from_secs(interval),
+            ));
+        }
+
+        Ok(manager)
     }
 
     pub fn get_plugin_host(&self) -> Arc<PluginHost> {
@@ -1,10 +1,13 @@
 use serde::{Deserialize, Serialize};
 
-use crate::RuleID;
+use crate::{Envelope, Payload, RuleID};
 
 #[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
 pub struct ActionManifest {
     pub rule: RuleID,
     pub action_type: String,
-    pub data: String,
+    pub data: Payload,
+
+    #[serde(default = "Envelope::unversioned")]
+    pub envelope: Envelope,
 }
@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{ActionManifest, Envelope, Payload, RuleID};
+
+/// Outcome of an action executed successfully, reported by its plugin.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(default)]
+pub struct ActionResult {
+    /// Short human-readable description of the outcome (e.g. an HTTP status), logged by the
+    /// executor.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+
+    /// Structured output of the action (e.g. the id of a created resource). Null when the
+    /// action has none.
+    pub data: Payload,
+}
+
+impl ActionResult {
+    pub fn new(data: Payload) -> Self {
+        ActionResult {
+            summary: None,
+            data,
+        }
+    }
+
+    pub fn with_summary<S: Into<String>>(mut self, summary: S) -> Self {
+        self.summary = Some(summary.into());
+        self
+    }
+}
+
+/// Why an attempt of an action failed.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct ActionFailure {
+    pub error: String,
+
+    /// Attempt which failed, starting at 1.
+    pub attempt: u32,
+
+    /// Whether the action is retried, or was given up on.
+    pub retrying: bool,
+}
+
+/// Record of an executed or failed action, published by the executor for follow-up processing
+/// (e.g. audit history or chained actions).
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct ActionRecord {
+    pub rule: RuleID,
+    pub action_type: String,
+
+    /// Empty when the action failed.
+    #[serde(default)]
+    pub result: ActionResult,
+
+    /// Set when the action failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<ActionFailure>,
+
+    /// Derived from the envelope of the executed manifest.
+    #[serde(default = "Envelope::unversioned")]
+    pub envelope: Envelope,
+}
+
+impl ActionRecord {
+    /// Creates the record of an executed action.
+    pub fn new(manifest: &ActionManifest, result: ActionResult) -> Self {
+        ActionRecord {
+            rule: manifest.rule.clone(),
+            action_type: manifest.action_type.clone(),
+            result,
+            failure: None,
+            envelope: manifest.envelope.derive(),
+        }
+    }
+
+    /// Creates the record of a failed attempt of an action.
+    pub fn failed(manifest: &ActionManifest, failure: ActionFailure) -> Self {
+        ActionRecord {
+            rule: manifest.rule.clone(),
+            action_type: manifest.action_type.clone(),
+            result: ActionResult::default(),
+            failure: Some(failure),
+            envelope: manifest.envelope.derive(),
+        }
+    }
+}
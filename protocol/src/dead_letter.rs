@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+use crate::ActionManifest;
+
+/// An action quarantined by the executor, published so it can be inspected and replayed.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct DeadLetter {
+    pub manifest: ActionManifest,
+
+    /// Error of the last attempt.
+    pub error: String,
+}
+
+impl DeadLetter {
+    pub fn new<S: Into<String>>(manifest: ActionManifest, error: S) -> Self {
+        DeadLetter {
+            manifest,
+            error: error.into(),
+        }
+    }
+}
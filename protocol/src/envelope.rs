@@ -0,0 +1,85 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use uuid::Uuid;
+
+pub type MessageID = String;
+
+/// Version of the message schema written by this build.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Identity and bookkeeping attached to every message flowing through the pipeline.
+///
+/// Messages derived from a trigger (e.g. action manifests) keep the id of that trigger,
+/// which makes it possible to follow a single event from poll to execution.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Envelope {
+    /// Unique id of the message.
+    pub id: MessageID,
+
+    /// Creation time of the message, in milliseconds since the unix epoch.
+    pub created_at: u64,
+
+    /// Id of the trigger that started the chain of messages.
+    pub trigger_id: MessageID,
+
+    /// Processing attempt of the message, starting at 1.
+    pub attempt: u32,
+
+    pub schema_version: u32,
+}
+
+impl Envelope {
+    /// Creates the envelope of a message starting a new chain.
+    pub fn new() -> Self {
+        let id = Uuid::new_v4().to_string();
+        Envelope {
+            trigger_id: id.clone(),
+            id,
+            created_at: now_millis(),
+            attempt: 1,
+            schema_version: SCHEMA_VERSION,
+        }
+    }
+
+    /// Creates the envelope of a message derived from this one.
+    pub fn derive(&self) -> Self {
+        Envelope {
+            trigger_id: self.trigger_id.clone(),
+            ..Envelope::new()
+        }
+    }
+
+    /// Envelope assigned to messages that were written before envelopes existed.
+    pub(crate) fn unversioned() -> Self {
+        Envelope {
+            schema_version: 0,
+            ..Envelope::new()
+        }
+    }
+}
+
+impl Default for Envelope {
+    fn default() -> Self {
+        Envelope::new()
+    }
+}
+
+impl fmt::Display for Envelope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "msg={} trigger={} attempt={}",
+            self.id, self.trigger_id, self.attempt
+        )
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
@@ -1,9 +1,19 @@
 mod action_manifest;
+mod action_result;
+mod dead_letter;
+mod envelope;
+mod payload;
 pub mod rule;
+mod schedule;
 pub mod trigger;
 
 pub type RuleID = String;
 
 pub use action_manifest::ActionManifest;
-pub use rule::Rule;
+pub use action_result::{ActionFailure, ActionRecord, ActionResult};
+pub use dead_letter::DeadLetter;
+pub use envelope::{Envelope, MessageID, SCHEMA_VERSION};
+pub use payload::Payload;
+pub use rule::{Rule, RuleAction};
+pub use schedule::Schedule;
 pub use trigger::{Trigger, TriggerConfiguration};
@@ -0,0 +1,168 @@
+use std::fmt;
+use std::ops::Deref;
+
+use google_cloud::datastore::{FromValue, IntoValue, Value as DatastoreValue};
+use google_cloud::error::ConvertError;
+
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+
+use serde_json::Value;
+
+/// Structured data carried by trigger configurations, triggers and action manifests.
+///
+/// Payloads used to be JSON-encoded strings. Those are still accepted when deserializing:
+/// a string holding a JSON object or array is decoded to the structured value it contains.
+/// Other strings, such as `"42"`, stay strings.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct Payload(Value);
+
+impl Payload {
+    /// Creates a payload from a JSON value.
+    pub fn new(value: Value) -> Self {
+        Payload(value)
+    }
+
+    /// Creates a payload from any serializable structure.
+    pub fn encode<T: Serialize>(data: &T) -> serde_json::Result<Self> {
+        Ok(Payload(serde_json::to_value(data)?))
+    }
+
+    /// Creates a payload from a JSON-encoded string.
+    ///
+    /// Strings that aren't valid JSON are kept as a plain string payload.
+    pub fn from_json_str(data: &str) -> Self {
+        match serde_json::from_str(data) {
+            Ok(value) => Payload(value),
+            Err(_) => Payload(Value::String(String::from(data))),
+        }
+    }
+
+    /// Deserializes the payload into a concrete structure.
+    pub fn decode<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        T::deserialize(&self.0)
+    }
+
+    /// Returns a reference to the underlying JSON value.
+    pub fn as_value(&self) -> &Value {
+        &self.0
+    }
+
+    /// Consumes the payload, returning the underlying JSON value.
+    pub fn into_inner(self) -> Value {
+        self.0
+    }
+}
+
+impl Deref for Payload {
+    type Target = Value;
+
+    fn deref(&self) -> &Value {
+        &self.0
+    }
+}
+
+impl From<Value> for Payload {
+    fn from(value: Value) -> Self {
+        Payload(value)
+    }
+}
+
+impl fmt::Display for Payload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Payload {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Value::deserialize(deserializer)? {
+            // Legacy records (sled, file queues) hold the payload as a JSON-encoded string.
+            // Legacy payloads were always objects or arrays, so scalars in strings are kept as
+            // strings.
+            Value::String(data) => match serde_json::from_str(&data) {
+                Ok(value @ Value::Object(_)) | Ok(value @ Value::Array(_)) => Ok(Payload(value)),
+                _ => Ok(Payload(Value::String(data))),
+            },
+            value => Ok(Payload(value)),
+        }
+    }
+}
+
+// Datastore has no native JSON type, so payloads are stored JSON-encoded,
+// which is also how they were stored before being structured.
+impl IntoValue for Payload {
+    fn into_value(self) -> DatastoreValue {
+        DatastoreValue::StringValue(self.0.to_string())
+    }
+}
+
+impl FromValue for Payload {
+    fn from_value(value: DatastoreValue) -> Result<Self, ConvertError> {
+        match value {
+            DatastoreValue::StringValue(data) => Ok(Payload::from_json_str(&data)),
+            other => Err(ConvertError::UnexpectedPropertyType {
+                expected: String::from("string"),
+                got: String::from(other.type_name()),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use serde_json::json;
+
+    use super::Payload;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Record {
+        data: Payload,
+    }
+
+    #[test]
+    fn deserialize_structured() {
+        let record: Record = serde_json::from_str(r#"{"data": {"file_name": "a.pdf"}}"#).unwrap();
+        assert_eq!(record.data, Payload::from(json!({"file_name": "a.pdf"})));
+    }
+
+    #[test]
+    fn deserialize_legacy_string() {
+        let record: Record =
+            serde_json::from_str(r#"{"data": "{\"file_name\": \"a.pdf\"}"}"#).unwrap();
+        assert_eq!(record.data, Payload::from(json!({"file_name": "a.pdf"})));
+    }
+
+    #[test]
+    fn deserialize_plain_string() {
+        let record: Record = serde_json::from_str(r#"{"data": "bing bong"}"#).unwrap();
+        assert_eq!(record.data, Payload::from(json!("bing bong")));
+    }
+
+    #[test]
+    fn deserialize_scalar_string() {
+        let record: Record = serde_json::from_str(r#"{"data": "42"}"#).unwrap();
+        assert_eq!(record.data, Payload::from(json!("42")));
+
+        let record: Record = serde_json::from_str(r#"{"data": "true"}"#).unwrap();
+        assert_eq!(record.data, Payload::from(json!("true")));
+    }
+
+    #[test]
+    fn round_trip() {
+        let payload = Payload::from(json!({"directory": "/tmp", "depth": 2}));
+        let serialized = serde_json::to_string(&payload).unwrap();
+        let deserialized: Payload = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, payload);
+
+        let payload = Payload::from(json!("42"));
+        let serialized = serde_json::to_string(&payload).unwrap();
+        let deserialized: Payload = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, payload);
+    }
+}
@@ -1,11 +1,265 @@
+use std::collections::HashMap;
+
+use handlebars::Handlebars;
+
 use serde::{Deserialize, Serialize};
 
-use google_cloud::datastore::{FromValue, IntoValue};
+use snafu::{ResultExt, Snafu};
+
+use google_cloud::datastore::{FromValue, IntoValue, Value};
+use google_cloud::error::ConvertError;
+
+use crate::Payload;
+
+/// Failure to render the configuration template of an action.
+#[derive(Debug, Snafu)]
+pub enum RenderError {
+    #[snafu(display("failed to render action configuration: {}", source))]
+    Template {
+        source: Box<handlebars::RenderError>,
+    },
+    #[snafu(display(
+        "rendered action configuration isn't valid JSON ({}): {}",
+        source,
+        rendered
+    ))]
+    InvalidJson {
+        rendered: String,
+        source: serde_json::Error,
+    },
+}
 
-#[derive(Clone, Debug, FromValue, IntoValue, Deserialize, Serialize)]
+/// A single action performed when a rule is triggered.
+#[derive(Clone, Debug, FromValue, IntoValue, Deserialize, PartialEq, Serialize)]
 #[datastore(rename_all = "snake_case")]
+pub struct RuleAction {
+    pub action_type: String,
+
+    /// Handlebars template of the action configuration, rendered with the data of the trigger.
+    /// It must render to JSON.
+    pub action_config: String,
+}
+
+impl RuleAction {
+    /// Renders the configuration of the action using the data of a trigger.
+    pub fn render(&self, trigger_data: &Payload) -> Result<Payload, RenderError> {
+        let rendered = Handlebars::new()
+            .render_template(&self.action_config, trigger_data.as_value())
+            .map_err(Box::new)
+            .context(TemplateSnafu)?;
+
+        let value = serde_json::from_str(&rendered).context(InvalidJsonSnafu {
+            rendered: rendered.clone(),
+        })?;
+        Ok(Payload::new(value))
+    }
+}
+
+/// A rule, binding a trigger configuration to an ordered list of actions.
+///
+/// Rules written before multi-action support (with a single `action_type` and `action_config`)
+/// are still accepted, and loaded as a rule with a single action.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(from = "RuleRepr")]
 pub struct Rule {
     pub trigger_config_id: i64,
-    pub action_config: String,
-    pub action_type: String,
+    pub actions: Vec<RuleAction>,
+
+    /// Condition the trigger data must satisfy for the actions to run (e.g. `size > 10MB`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub condition: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RuleRepr {
+    Actions {
+        trigger_config_id: i64,
+        actions: Vec<RuleAction>,
+        #[serde(default)]
+        condition: Option<String>,
+    },
+    Legacy {
+        trigger_config_id: i64,
+        action_type: String,
+        action_config: String,
+        #[serde(default)]
+        condition: Option<String>,
+    },
+}
+
+impl From<RuleRepr> for Rule {
+    fn from(repr: RuleRepr) -> Self {
+        match repr {
+            RuleRepr::Actions {
+                trigger_config_id,
+                actions,
+                condition,
+            } => Rule {
+                trigger_config_id,
+                actions,
+                condition,
+            },
+            RuleRepr::Legacy {
+                trigger_config_id,
+                action_type,
+                action_config,
+                condition,
+            } => Rule {
+                trigger_config_id,
+                actions: vec![RuleAction {
+                    action_type,
+                    action_config,
+                }],
+                condition,
+            },
+        }
+    }
+}
+
+fn take_property<T: FromValue>(
+    properties: &mut HashMap<String, Value>,
+    name: &str,
+) -> Result<T, ConvertError> {
+    let value = properties
+        .remove(name)
+        .ok_or_else(|| ConvertError::MissingProperty(String::from(name)))?;
+    T::from_value(value)
+}
+
+impl FromValue for Rule {
+    fn from_value(value: Value) -> Result<Self, ConvertError> {
+        let mut properties = match value {
+            Value::EntityValue(properties) => properties,
+            other => {
+                return Err(ConvertError::UnexpectedPropertyType {
+                    expected: String::from("entity"),
+                    got: String::from(other.type_name()),
+                })
+            }
+        };
+
+        let trigger_config_id = take_property(&mut properties, "trigger_config_id")?;
+
+        let actions = if properties.contains_key("actions") {
+            take_property(&mut properties, "actions")?
+        } else {
+            // Legacy single-action rule.
+            vec![RuleAction {
+                action_type: take_property(&mut properties, "action_type")?,
+                action_config: take_property(&mut properties, "action_config")?,
+            }]
+        };
+
+        let condition = match properties.remove("condition") {
+            Some(condition) => Some(String::from_value(condition)?),
+            None => None,
+        };
+
+        Ok(Rule {
+            trigger_config_id,
+            actions,
+            condition,
+        })
+    }
+}
+
+impl IntoValue for Rule {
+    fn into_value(self) -> Value {
+        let mut properties = HashMap::new();
+        properties.insert(
+            String::from("trigger_config_id"),
+            self.trigger_config_id.into_value(),
+        );
+        properties.insert(String::from("actions"), self.actions.into_value());
+        if let Some(condition) = self.condition {
+            properties.insert(String::from("condition"), condition.into_value());
+        }
+        Value::EntityValue(properties)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::Payload;
+
+    use super::{Rule, RuleAction};
+
+    fn action(action_config: &str) -> RuleAction {
+        RuleAction {
+            action_type: String::from("notify"),
+            action_config: String::from(action_config),
+        }
+    }
+
+    #[test]
+    fn render_json() {
+        let data = Payload::from(json!({"file_name": "a.pdf", "size": 42}));
+        let rendered = action("{\"body\": \"New file: {{file_name}}\", \"size\": {{size}}}")
+            .render(&data)
+            .unwrap();
+        assert_eq!(
+            rendered,
+            Payload::from(json!({"body": "New file: a.pdf", "size": 42}))
+        );
+    }
+
+    #[test]
+    fn render_rejects_invalid_json() {
+        let data = Payload::from(json!({"file_name": "a.pdf"}));
+        assert!(action("New file: {{file_name}}").render(&data).is_err());
+    }
+
+    #[test]
+    fn deserialize_actions() {
+        let rule: Rule = serde_json::from_str(
+            r#"{
+                "trigger_config_id": 1,
+                "actions": [
+                    {"action_type": "notify", "action_config": "{}"},
+                    {"action_type": "email", "action_config": "{\"to\": \"a@b.c\"}"}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(rule.trigger_config_id, 1);
+        assert_eq!(rule.actions.len(), 2);
+        assert_eq!(rule.actions[1].action_type, "email");
+        assert_eq!(rule.condition, None);
+    }
+
+    #[test]
+    fn deserialize_legacy() {
+        let rule: Rule = serde_json::from_str(
+            r#"{"trigger_config_id": 1, "action_type": "notify", "action_config": "{}"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            rule.actions,
+            vec![RuleAction {
+                action_type: String::from("notify"),
+                action_config: String::from("{}"),
+            }]
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let rule = Rule {
+            trigger_config_id: 3,
+            actions: vec![RuleAction {
+                action_type: String::from("notify"),
+                action_config: String::from("{}"),
+            }],
+            condition: Some(String::from("file_name ends_with \".pdf\"")),
+        };
+
+        let serialized = serde_json::to_string(&rule).unwrap();
+        let deserialized: Rule = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, rule);
+    }
 }
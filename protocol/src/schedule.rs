@@ -0,0 +1,70 @@
+use google_cloud::datastore::{FromValue, IntoValue, Value as DatastoreValue};
+use google_cloud::error::ConvertError;
+
+use serde::{Deserialize, Serialize};
+
+/// When the trigger system polls a trigger configuration.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Schedule {
+    /// On every cycle of the trigger system.
+    #[default]
+    Always,
+
+    /// At a fixed interval, in milliseconds. Intervals shorter than a cycle of the trigger
+    /// system behave like `Always`.
+    #[serde(rename = "interval_ms")]
+    Interval(u64),
+
+    /// Whenever a cron expression matches, in UTC. Expressions have five fields (minute, hour,
+    /// day of the month, month, day of the week), or six with leading seconds. Days of the week
+    /// are numbered from 0 for Sunday to 6 for Saturday, and 7 is Sunday too.
+    Cron(String),
+}
+
+// Stored JSON-encoded, like payloads.
+impl IntoValue for Schedule {
+    fn into_value(self) -> DatastoreValue {
+        // Serializing a schedule can't fail: it only holds strings and numbers.
+        DatastoreValue::StringValue(serde_json::to_string(&self).unwrap_or_default())
+    }
+}
+
+impl FromValue for Schedule {
+    fn from_value(value: DatastoreValue) -> Result<Self, ConvertError> {
+        match value {
+            DatastoreValue::StringValue(data) => {
+                serde_json::from_str(&data).map_err(|_| ConvertError::UnexpectedPropertyType {
+                    expected: String::from("schedule"),
+                    got: data,
+                })
+            }
+            other => Err(ConvertError::UnexpectedPropertyType {
+                expected: String::from("string"),
+                got: String::from(other.type_name()),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::Schedule;
+
+    #[test]
+    fn serialization() {
+        assert_eq!(
+            serde_json::to_value(Schedule::Always).unwrap(),
+            json!("always")
+        );
+        assert_eq!(
+            serde_json::to_value(Schedule::Interval(30_000)).unwrap(),
+            json!({"interval_ms": 30000})
+        );
+
+        let schedule: Schedule = serde_json::from_value(json!({"cron": "*/5 * * * *"})).unwrap();
+        assert_eq!(schedule, Schedule::Cron(String::from("*/5 * * * *")));
+    }
+}
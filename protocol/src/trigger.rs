@@ -2,7 +2,7 @@ use google_cloud::datastore::{FromValue, IntoValue};
 
 use serde::{Deserialize, Serialize};
 
-use crate::RuleID;
+use crate::{Envelope, Payload, RuleID, Schedule};
 
 #[derive(Clone, FromValue, IntoValue, Debug, Deserialize, PartialEq, Serialize)]
 #[datastore(rename_all = "snake_case")]
@@ -10,12 +10,31 @@ pub struct TriggerConfiguration {
     pub id: i64,
     pub rule: RuleID,
     pub trigger_type: String,
-    pub data: String, // JSON-encoded for now, willing to discuss formatting or alternatives later.
+    pub data: Payload,
+
+    /// When the configuration is polled. Push triggers aren't polled, and ignore it.
+    #[serde(default)]
+    pub schedule: Schedule,
 }
 
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct Trigger {
     pub rule: RuleID,
     pub trigger_type: String,
-    pub data: String, // Again, JSON-encoded for now.
+    pub data: Payload,
+
+    #[serde(default = "Envelope::unversioned")]
+    pub envelope: Envelope,
+}
+
+impl Trigger {
+    /// Creates a trigger starting a new chain of messages.
+    pub fn new(rule: RuleID, trigger_type: String, data: Payload) -> Self {
+        Trigger {
+            rule,
+            trigger_type,
+            data,
+            envelope: Envelope::new(),
+        }
+    }
 }
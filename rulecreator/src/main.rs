@@ -1,19 +1,42 @@
-use protocol::{Rule, TriggerConfiguration};
+use plugin_host::PluginHost;
+
+use protocol::{Rule, RuleAction, TriggerConfiguration};
+
+use serde_json::json;
+
+use trigger_interpreter::Condition;
 
 use toolkit::db::sled::{EntityStore, SledStore};
 
 fn main() {
+    // Rules are validated against the plugin libraries given as arguments before being created.
+    // The libraries are trusted, as they are picked by whoever runs this.
+    let plugin_host = PluginHost::default();
+    for library in std::env::args().skip(1) {
+        plugin_host.add_plugin(library).unwrap();
+    }
+
     let store = SledStore::new("./test.db").unwrap();
 
     let rules: EntityStore<Rule> = store.entity("Rule").unwrap();
 
     let r = Rule {
         trigger_config_id: 1,
-        action_config:
-            "{\"body\": \"New File: {{file_name}}\", \"title\": \"ShifTTT Notification\"}".into(),
-        action_type: String::from("notify"),
+        actions: vec![RuleAction {
+            action_config:
+                "{\"body\": \"New File: {{file_name}}\", \"title\": \"ShifTTT Notification\"}"
+                    .into(),
+            action_type: String::from("notify"),
+        }],
+        condition: None,
     };
 
+    // Rules with an invalid condition are refused by the interpreter, so don't create them.
+    Condition::of_rule(&r).expect("invalid rule condition");
+    plugin_host
+        .validate_rule(&r, "directory_watch")
+        .expect("invalid rule");
+
     let r_id = rules.insert(&r).unwrap();
 
     let trigger_cfgs: EntityStore<TriggerConfiguration> =
@@ -23,8 +46,12 @@ fn main() {
         id: 1,
         rule: r_id,
         trigger_type: "directory_watch".into(),
-        data: "{\"directory\": \"/home/wduss/temp\"}".into(),
+        data: json!({ "directory": "/home/wduss/temp" }).into(),
+        schedule: Default::default(),
     };
+    plugin_host
+        .validate_trigger_config(&trigger_cfg)
+        .expect("invalid trigger configuration");
     trigger_cfgs.insert(&trigger_cfg).unwrap();
 
     trigger_cfgs.flush().unwrap();
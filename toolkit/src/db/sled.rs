@@ -20,6 +20,7 @@ pub enum Error {
     OpenTree { source: sled::Error },
     OpenDatabase { source: sled::Error },
     ReadItem { source: sled::Error },
+    RemoveItem { source: sled::Error },
     SerializeItem { source: serde_json::Error },
 }
 
@@ -45,6 +46,49 @@ impl SledStore {
     {
         EntityStore::new(&self.db, kind)
     }
+
+    /// Get a handle to a raw key/value subset of the database.
+    pub fn kv(&self, name: &str) -> Result<KvStore> {
+        KvStore::new(&self.db, name)
+    }
+}
+
+/// A handle to a subset of the sled tree, storing raw bytes by key.
+#[derive(Clone)]
+pub struct KvStore {
+    tree: sled::Tree,
+}
+
+impl KvStore {
+    /// Fetch a subtree from the database and return a new handle to it.
+    pub fn new(db: &sled::Db, name: &str) -> Result<KvStore> {
+        let tree = db.open_tree(name).context(OpenTreeSnafu)?;
+        Ok(KvStore { tree })
+    }
+
+    /// Flush the store to disk.
+    pub fn flush(&self) -> Result<()> {
+        self.tree.flush().context(FlushSnafu)?;
+        Ok(())
+    }
+
+    /// Get the value stored at a key.
+    pub fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let value = self.tree.get(key).context(ReadItemSnafu)?;
+        Ok(value.map(|v| v.to_vec()))
+    }
+
+    /// Store a value at a key, replacing the previous value.
+    pub fn set(&self, key: &str, value: &[u8]) -> Result<()> {
+        self.tree.insert(key, value).context(InsertItemSnafu)?;
+        Ok(())
+    }
+
+    /// Remove the value stored at a key, if any.
+    pub fn remove(&self, key: &str) -> Result<()> {
+        self.tree.remove(key).context(RemoveItemSnafu)?;
+        Ok(())
+    }
 }
 
 /// A handle to a subset of the sled tree.
@@ -0,0 +1,435 @@
+//! Rule conditions, evaluated against the data of a trigger.
+//!
+//! A condition is a boolean expression over the fields of the trigger data, e.g.
+//! `file_name ends_with ".pdf" and size > 10MB`.
+//!
+//! Supported syntax:
+//! - Fields: `file_name`, `metadata.size` (missing fields evaluate to `null`).
+//! - Literals: `"strings"`, `'strings'`, numbers (`42`, `-1.5`, `10KB`, `10MB`, `1GB`),
+//!   `true`, `false`, `null`.
+//! - Comparisons: `==`, `!=`, `<`, `<=`, `>`, `>=`, `contains`, `starts_with`, `ends_with`,
+//!   `matches` (the right-hand side of `matches` must be a regex string literal).
+//! - Boolean logic: `and` (`&&`), `or` (`||`), `not` (`!`), and parentheses.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use protocol::Rule;
+
+use regex::Regex;
+
+use serde_json::Value;
+
+static NULL: Value = Value::Null;
+
+/// A parsed rule condition.
+#[derive(Debug)]
+pub struct Condition {
+    source: String,
+    expr: Expr,
+}
+
+impl Condition {
+    /// Parses a condition expression, failing on syntax errors.
+    pub fn parse(source: &str) -> Result<Condition> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+        };
+
+        let expr = parser.parse_or()?;
+        if let Some((token, pos)) = parser.peek_with_position() {
+            bail!("unexpected token '{}' at position {}", token, pos);
+        }
+
+        Ok(Condition {
+            source: String::from(source),
+            expr,
+        })
+    }
+
+    /// Parses the condition of a rule, if it has one.
+    pub fn of_rule(rule: &Rule) -> Result<Option<Condition>> {
+        match &rule.condition {
+            Some(source) => Condition::parse(source)
+                .map(Some)
+                .with_context(|| format!("invalid condition '{}'", source)),
+            None => Ok(None),
+        }
+    }
+
+    /// Evaluates the condition against trigger data.
+    pub fn evaluate(&self, data: &Value) -> Result<bool> {
+        self.expr.evaluate(data)
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+#[derive(Debug)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Operand, CompareOp, Operand),
+    Matches(Operand, Regex),
+    Operand(Operand),
+}
+
+impl Expr {
+    fn evaluate(&self, data: &Value) -> Result<bool> {
+        match self {
+            Expr::And(lhs, rhs) => Ok(lhs.evaluate(data)? && rhs.evaluate(data)?),
+            Expr::Or(lhs, rhs) => Ok(lhs.evaluate(data)? || rhs.evaluate(data)?),
+            Expr::Not(expr) => Ok(!expr.evaluate(data)?),
+            Expr::Compare(lhs, op, rhs) => op.apply(lhs.resolve(data), rhs.resolve(data)),
+            Expr::Matches(lhs, regex) => match lhs.resolve(data) {
+                Value::String(s) => Ok(regex.is_match(s)),
+                other => bail!("cannot match {} against a regex", other),
+            },
+            Expr::Operand(operand) => match operand.resolve(data) {
+                Value::Bool(b) => Ok(*b),
+                Value::Null => Ok(false),
+                other => bail!("expected a boolean, got {}", other),
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Operand {
+    Literal(Value),
+    Field(Vec<String>),
+}
+
+impl Operand {
+    fn resolve<'a>(&'a self, data: &'a Value) -> &'a Value {
+        match self {
+            Operand::Literal(value) => value,
+            Operand::Field(path) => path
+                .iter()
+                .try_fold(data, |value, key| value.get(key))
+                .unwrap_or(&NULL),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+    StartsWith,
+    EndsWith,
+}
+
+impl CompareOp {
+    fn apply(self, lhs: &Value, rhs: &Value) -> Result<bool> {
+        match self {
+            CompareOp::Eq => Ok(values_equal(lhs, rhs)),
+            CompareOp::Ne => Ok(!values_equal(lhs, rhs)),
+            CompareOp::Lt => Ok(order(lhs, rhs)? == Ordering::Less),
+            CompareOp::Le => Ok(order(lhs, rhs)? != Ordering::Greater),
+            CompareOp::Gt => Ok(order(lhs, rhs)? == Ordering::Greater),
+            CompareOp::Ge => Ok(order(lhs, rhs)? != Ordering::Less),
+            CompareOp::Contains => match (lhs, rhs) {
+                (Value::String(haystack), Value::String(needle)) => {
+                    Ok(haystack.contains(needle.as_str()))
+                }
+                (Value::Array(items), needle) => {
+                    Ok(items.iter().any(|item| values_equal(item, needle)))
+                }
+                _ => bail!("cannot check whether {} contains {}", lhs, rhs),
+            },
+            CompareOp::StartsWith => {
+                let (s, prefix) = as_strings(lhs, rhs, "starts_with")?;
+                Ok(s.starts_with(prefix))
+            }
+            CompareOp::EndsWith => {
+                let (s, suffix) = as_strings(lhs, rhs, "ends_with")?;
+                Ok(s.ends_with(suffix))
+            }
+        }
+    }
+}
+
+fn values_equal(lhs: &Value, rhs: &Value) -> bool {
+    match (lhs.as_f64(), rhs.as_f64()) {
+        (Some(l), Some(r)) => l == r,
+        _ => lhs == rhs,
+    }
+}
+
+fn order(lhs: &Value, rhs: &Value) -> Result<Ordering> {
+    match (lhs, rhs) {
+        (Value::Number(l), Value::Number(r)) => l
+            .as_f64()
+            .zip(r.as_f64())
+            .and_then(|(l, r)| l.partial_cmp(&r))
+            .ok_or_else(|| anyhow!("cannot compare {} and {}", lhs, rhs)),
+        (Value::String(l), Value::String(r)) => Ok(l.cmp(r)),
+        _ => bail!("cannot compare {} and {}", lhs, rhs),
+    }
+}
+
+fn as_strings<'a>(lhs: &'a Value, rhs: &'a Value, op: &str) -> Result<(&'a str, &'a str)> {
+    match (lhs, rhs) {
+        (Value::String(l), Value::String(r)) => Ok((l.as_str(), r.as_str())),
+        _ => bail!("{} expects strings, got {} and {}", op, lhs, rhs),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Number(f64),
+    Compare(CompareOp),
+    Matches,
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(ident) => write!(f, "{}", ident),
+            Token::Str(s) => write!(f, "{:?}", s),
+            Token::Number(n) => write!(f, "{}", n),
+            Token::Compare(op) => write!(f, "{:?}", op),
+            Token::Matches => write!(f, "matches"),
+            Token::And => write!(f, "and"),
+            Token::Or => write!(f, "or"),
+            Token::Not => write!(f, "not"),
+            Token::LParen => write!(f, "("),
+            Token::RParen => write!(f, ")"),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let two: String = chars[i..usize::min(i + 2, chars.len())].iter().collect();
+        let (token, len) = match two.as_str() {
+            "==" => (Token::Compare(CompareOp::Eq), 2),
+            "!=" => (Token::Compare(CompareOp::Ne), 2),
+            "<=" => (Token::Compare(CompareOp::Le), 2),
+            ">=" => (Token::Compare(CompareOp::Ge), 2),
+            "&&" => (Token::And, 2),
+            "||" => (Token::Or, 2),
+            _ => match c {
+                '<' => (Token::Compare(CompareOp::Lt), 1),
+                '>' => (Token::Compare(CompareOp::Gt), 1),
+                '!' => (Token::Not, 1),
+                '(' => (Token::LParen, 1),
+                ')' => (Token::RParen, 1),
+                '"' | '\'' => {
+                    let (s, len) = read_string(&chars[i..], start)?;
+                    (Token::Str(s), len)
+                }
+                c if c.is_ascii_digit()
+                    || (c == '-' && chars.get(i + 1).is_some_and(char::is_ascii_digit)) =>
+                {
+                    let (n, len) = read_number(&chars[i..], start)?;
+                    (Token::Number(n), len)
+                }
+                c if c.is_alphabetic() || c == '_' => {
+                    let len = chars[i..]
+                        .iter()
+                        .take_while(|c| c.is_alphanumeric() || **c == '_' || **c == '.')
+                        .count();
+                    let word: String = chars[i..i + len].iter().collect();
+                    (keyword(word), len)
+                }
+                c => bail!("unexpected character '{}' at position {}", c, start),
+            },
+        };
+
+        tokens.push((token, start));
+        i += len;
+    }
+
+    Ok(tokens)
+}
+
+fn keyword(word: String) -> Token {
+    match word.as_str() {
+        "and" => Token::And,
+        "or" => Token::Or,
+        "not" => Token::Not,
+        "contains" => Token::Compare(CompareOp::Contains),
+        "starts_with" => Token::Compare(CompareOp::StartsWith),
+        "ends_with" => Token::Compare(CompareOp::EndsWith),
+        "matches" => Token::Matches,
+        _ => Token::Ident(word),
+    }
+}
+
+fn read_string(chars: &[char], start: usize) -> Result<(String, usize)> {
+    let quote = chars[0];
+    let mut s = String::new();
+    let mut i = 1;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 < chars.len() => {
+                s.push(chars[i + 1]);
+                i += 2;
+            }
+            c if c == quote => return Ok((s, i + 1)),
+            c => {
+                s.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    bail!("unterminated string starting at position {}", start)
+}
+
+fn read_number(chars: &[char], start: usize) -> Result<(f64, usize)> {
+    let len = 1 + chars[1..]
+        .iter()
+        .take_while(|c| c.is_ascii_digit() || **c == '.')
+        .count();
+    let digits: String = chars[..len].iter().collect();
+    let value: f64 = digits
+        .parse()
+        .map_err(|_| anyhow!("invalid number '{}' at position {}", digits, start))?;
+
+    // Optional size suffix (e.g. 10MB).
+    let suffix_len = chars[len..]
+        .iter()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .count();
+    let suffix: String = chars[len..len + suffix_len].iter().collect();
+    let multiplier = match suffix.to_ascii_uppercase().as_str() {
+        "" => 1.0,
+        "KB" => 1024.0,
+        "MB" => 1024.0 * 1024.0,
+        "GB" => 1024.0 * 1024.0 * 1024.0,
+        _ => bail!("unknown unit '{}' at position {}", suffix, start + len),
+    };
+
+    Ok((value * multiplier, len + suffix_len))
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(t, _)| t)
+    }
+
+    fn peek_with_position(&self) -> Option<(&Token, usize)> {
+        self.tokens.get(self.position).map(|(t, p)| (t, *p))
+    }
+
+    fn next(&mut self) -> Result<Token> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .map(|(t, _)| t.clone())
+            .ok_or_else(|| anyhow!("unexpected end of condition"))?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.position += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_not()?;
+        while self.peek() == Some(&Token::And) {
+            self.position += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_not()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr> {
+        if self.peek() == Some(&Token::Not) {
+            self.position += 1;
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr> {
+        if self.peek() == Some(&Token::LParen) {
+            self.position += 1;
+            let expr = self.parse_or()?;
+            match self.next()? {
+                Token::RParen => return Ok(expr),
+                other => bail!("expected ')', got '{}'", other),
+            }
+        }
+
+        let lhs = self.parse_operand()?;
+        match self.peek() {
+            Some(Token::Compare(op)) => {
+                let op = *op;
+                self.position += 1;
+                Ok(Expr::Compare(lhs, op, self.parse_operand()?))
+            }
+            Some(Token::Matches) => {
+                self.position += 1;
+                match self.next()? {
+                    Token::Str(pattern) => Ok(Expr::Matches(lhs, Regex::new(&pattern)?)),
+                    other => bail!("matches expects a regex string, got '{}'", other),
+                }
+            }
+            _ => Ok(Expr::Operand(lhs)),
+        }
+    }
+
+    fn parse_operand(&mut self) -> Result<Operand> {
+        let operand = match self.next()? {
+            Token::Str(s) => Operand::Literal(Value::String(s)),
+            Token::Number(n) => Operand::Literal(Value::from(n)),
+            Token::Ident(ident) => match ident.as_str() {
+                "true" => Operand::Literal(Value::Bool(true)),
+                "false" => Operand::Literal(Value::Bool(false)),
+                "null" => Operand::Literal(Value::Null),
+                _ => Operand::Field(ident.split('.').map(String::from).collect()),
+            },
+            other => bail!("expected a field or a value, got '{}'", other),
+        };
+        Ok(operand)
+    }
+}
@@ -18,7 +18,16 @@ use protocol::Rule;
 
 use toolkit::db::sled::{EntityStore, SledStore};
 
-use crate::interface::ActionConfigReader;
+use crate::condition::Condition;
+use crate::interface::{ActionConfigReader, InvalidRule};
+
+/// Refuses rules which can't be interpreted, so they are reported as soon as they are read.
+fn checked(id: &str, rule: Rule) -> Result<Rule> {
+    if let Err(e) = Condition::of_rule(&rule) {
+        return Err(InvalidRule::new(id, format!("{:#}", e)).into());
+    }
+    Ok(rule)
+}
 
 pub struct DatastoreActionConfigLoader {
     client: Mutex<datastore::Client>,
@@ -53,7 +62,7 @@ impl ActionConfigReader for DatastoreActionConfigLoader {
 
         match result {
             None => Err(Error::msg(format!("Rule with id '{}' not found.", id))),
-            Some(r) => Ok(r),
+            Some(r) => checked(id, r),
         }
     }
 }
@@ -84,7 +93,7 @@ impl ActionConfigReader for FileActionConfigReader {
         let data = fs::read_to_string(path)?;
         let rule = serde_json::from_str(data.as_ref())?;
 
-        Ok(rule)
+        checked(id, rule)
     }
 }
 
@@ -105,8 +114,50 @@ impl ActionConfigReader for EmbeddedActionConfigReader {
         let r = self.rules.get(id)?;
 
         match r {
-            Some(rule) => Ok(rule),
+            Some(rule) => checked(id, rule),
             None => Err(anyhow!("Rule doesn't exist")),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use protocol::RuleAction;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn write_rule(dir: &Path, id: &str, condition: &str) {
+        let rule = Rule {
+            trigger_config_id: 1,
+            actions: vec![RuleAction {
+                action_type: String::from("notify"),
+                action_config: String::from("{}"),
+            }],
+            condition: Some(String::from(condition)),
+        };
+        fs::write(
+            dir.join(format!("action_config_{}.txt", id)),
+            serde_json::to_string(&rule).unwrap(),
+        )
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn invalid_conditions_are_refused() {
+        let temp_dir = tempdir().unwrap();
+        write_rule(temp_dir.path(), "1", "file_name ends_with \".pdf\"");
+        write_rule(temp_dir.path(), "2", "file_name ends_with");
+
+        let reader = FileActionConfigReader::new(temp_dir.path()).unwrap();
+        assert!(reader.get_rule("1").await.is_ok());
+
+        let err = reader.get_rule("2").await.unwrap_err();
+        let invalid = err.downcast_ref::<InvalidRule>().unwrap();
+        assert_eq!(invalid.rule, "2");
+        assert!(invalid.reason.contains("file_name ends_with"));
+    }
+}
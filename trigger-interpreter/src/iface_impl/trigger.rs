@@ -142,6 +142,8 @@ mod tests {
 
     use protocol::Trigger;
 
+    use serde_json::json;
+
     use tempfile::tempdir;
 
     use super::*;
@@ -151,11 +153,11 @@ mod tests {
         // Setup a test directory.
         let temp_dir = tempdir().unwrap();
         let f = fs::File::create(temp_dir.path().join("trigger_1.txt")).unwrap();
-        let expected_trigger = Trigger {
-            rule: String::from("bing"),
-            trigger_type: String::from("something"),
-            data: String::from("bing bong"),
-        };
+        let expected_trigger = Trigger::new(
+            String::from("bing"),
+            String::from("something"),
+            json!({ "bing": "bong" }).into(),
+        );
         serde_json::to_writer(f, &expected_trigger).unwrap();
 
         // Test the queue
@@ -1,3 +1,5 @@
+use std::fmt;
+
 use anyhow::Result;
 
 use async_trait::async_trait;
@@ -18,6 +20,32 @@ pub trait ActionConfigReader {
     async fn get_rule(&self, id: &str) -> Result<Rule>;
 }
 
+/// Error of an [`ActionConfigReader`] refusing a rule which can't be interpreted, e.g. because
+/// its condition doesn't parse. Redelivering the triggers of such rules wouldn't fix them, so
+/// they are dropped.
+#[derive(Debug)]
+pub struct InvalidRule {
+    pub rule: String,
+    pub reason: String,
+}
+
+impl InvalidRule {
+    pub fn new<E: fmt::Display>(rule: &str, reason: E) -> Self {
+        InvalidRule {
+            rule: String::from(rule),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for InvalidRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "rule {} is invalid: {}", self.rule, self.reason)
+    }
+}
+
+impl std::error::Error for InvalidRule {}
+
 /// Trait describing an object capable of pushing an action manifest to a queue.
 #[async_trait]
 pub trait ActionManifestQueueWriter {
@@ -1,12 +1,18 @@
-use std::sync::mpsc;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    mpsc,
+};
 use std::thread;
 use std::time::Duration;
 
 use anyhow::Result;
 
-use protocol::{ActionManifest, Trigger};
+use protocol::{ActionManifest, Rule, Trigger};
 
-use crate::{templating::render_template, BoxedCfgReader, BoxedQueueReader, BoxedQueueWriter};
+use crate::{
+    condition::Condition, interface::InvalidRule, BoxedCfgReader, BoxedQueueReader,
+    BoxedQueueWriter,
+};
 
 /// The interpreter manager is the "main" thread of the trigger interpreter.
 pub struct TriggerManager {
@@ -14,6 +20,8 @@ pub struct TriggerManager {
     cfg_reader: BoxedCfgReader,
     queue_writer: BoxedQueueWriter,
     stop_rx: mpsc::Receiver<()>,
+
+    dropped_triggers: AtomicU64,
 }
 
 impl TriggerManager {
@@ -28,31 +36,117 @@ impl TriggerManager {
             cfg_reader,
             queue_writer,
             stop_rx,
+
+            dropped_triggers: AtomicU64::new(0),
         })
     }
 
+    /// Counts a trigger dropped without running the actions of its rule.
+    fn drop_trigger(&self) -> u64 {
+        self.dropped_triggers.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Reads a rule, along with its parsed condition.
+    async fn read_rule(&self, id: &str) -> Result<(Rule, Option<Condition>)> {
+        let rule = self.cfg_reader.get_rule(id).await?;
+
+        // Readers aren't required to refuse invalid rules themselves.
+        let condition =
+            Condition::of_rule(&rule).map_err(|e| InvalidRule::new(id, format!("{:#}", e)))?;
+        Ok((rule, condition))
+    }
+
     async fn interpret_trigger(&self, trigger: Trigger) -> Result<()> {
-        log::debug!("begin interpreting the trigger data");
+        log::debug!("[{}] begin interpreting the trigger data", trigger.envelope);
 
         log::debug!("fetching the rule ({}) by its id", trigger.rule);
         // Get action configuration associated with the trigger's rule.
-        let rule = self.cfg_reader.get_rule(&trigger.rule).await?;
+        let (rule, condition) = match self.read_rule(&trigger.rule).await {
+            Ok(read) => read,
+            // Redelivering the trigger wouldn't fix the rule, so triggers of invalid rules are
+            // dropped.
+            Err(e) if e.is::<InvalidRule>() => {
+                log::error!(
+                    "[{}] trigger dropped: {} ({} dropped so far)",
+                    trigger.envelope,
+                    e,
+                    self.drop_trigger()
+                );
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
         log::debug!("rule fetched {:?}", rule);
 
-        log::debug!("rendering the template from the action configuration");
-        let action_config = render_template(rule.action_config, trigger.data)?;
-        log::debug!("template rendered: {:?}", action_config);
+        if let Some(condition) = condition {
+            let met = condition
+                .evaluate(trigger.data.as_value())
+                .unwrap_or_else(|e| {
+                    log::warn!(
+                        "[{}] condition of rule {} failed to evaluate, treating it as not met: {}",
+                        trigger.envelope,
+                        trigger.rule,
+                        e
+                    );
+                    false
+                });
+            if !met {
+                let dropped = self.drop_trigger();
+                log::info!(
+                    "[{}] trigger dropped, condition of rule {} not met: {} ({} dropped so far)",
+                    trigger.envelope,
+                    trigger.rule,
+                    condition,
+                    dropped
+                );
+                return Ok(());
+            }
+        }
 
-        let action_manifest = ActionManifest {
-            rule: trigger.rule,
-            action_type: rule.action_type,
-            data: action_config,
-        };
+        // Render every action before pushing anything, so a bad template
+        // doesn't result in a partial fan-out.
+        let mut manifests = Vec::with_capacity(rule.actions.len());
+        for action in rule.actions.into_iter() {
+            log::debug!(
+                "rendering the template of the {} action",
+                action.action_type
+            );
+            // Rendering depends on the trigger data, which a redelivery wouldn't change, so
+            // triggers whose actions can't be rendered are dropped too.
+            let action_config = match action.render(&trigger.data) {
+                Ok(action_config) => action_config,
+                Err(e) => {
+                    log::error!(
+                        "[{}] trigger dropped, the {} action of rule {} can't be rendered: {} ({} dropped so far)",
+                        trigger.envelope,
+                        action.action_type,
+                        trigger.rule,
+                        e,
+                        self.drop_trigger()
+                    );
+                    return Ok(());
+                }
+            };
+            log::debug!("template rendered: {:?}", action_config);
+
+            manifests.push(ActionManifest {
+                rule: trigger.rule.clone(),
+                action_type: action.action_type,
+                data: action_config,
+                envelope: trigger.envelope.derive(),
+            });
+        }
 
-        log::debug!("pushing the action manifest");
-        self.queue_writer
-            .push_action_manifest(action_manifest)
-            .await?;
+        for action_manifest in manifests.into_iter() {
+            log::info!(
+                "[{}] pushing action manifest ({})",
+                action_manifest.envelope,
+                &action_manifest.action_type
+            );
+            self.queue_writer
+                .push_action_manifest(action_manifest)
+                .await?;
+        }
 
         Ok(())
     }
@@ -0,0 +1,86 @@
+use serde_json::json;
+
+use crate::Condition;
+
+fn eval(condition: &str, data: serde_json::Value) -> bool {
+    Condition::parse(condition)
+        .unwrap()
+        .evaluate(&data)
+        .unwrap()
+}
+
+#[test]
+fn string_operators() {
+    let data = json!({ "file_name": "report.pdf" });
+
+    assert!(eval("file_name ends_with \".pdf\"", data.clone()));
+    assert!(eval("file_name starts_with 'rep'", data.clone()));
+    assert!(eval("file_name contains \"port\"", data.clone()));
+    assert!(eval("file_name == \"report.pdf\"", data.clone()));
+    assert!(eval("file_name matches \"^[a-z]+\\\\.pdf$\"", data.clone()));
+    assert!(!eval("file_name ends_with \".txt\"", data));
+}
+
+#[test]
+fn numeric_operators() {
+    let data = json!({ "size": 20 * 1024 * 1024, "count": 3 });
+
+    assert!(eval("size > 10MB", data.clone()));
+    assert!(eval("size >= 20MB", data.clone()));
+    assert!(!eval("size < 1GB and count > 3", data.clone()));
+    assert!(eval("count == 3.0", data.clone()));
+    assert!(eval("count != -3", data));
+}
+
+#[test]
+fn boolean_logic() {
+    let data = json!({ "a": true, "b": false, "nested": { "flag": true } });
+
+    assert!(eval("a and not b", data.clone()));
+    assert!(eval("b || a", data.clone()));
+    assert!(eval("!(a && b)", data.clone()));
+    assert!(eval("nested.flag", data.clone()));
+    assert!(!eval("missing", data.clone()));
+    assert!(eval("missing == null", data));
+}
+
+#[test]
+fn precedence() {
+    let data = json!({ "a": true, "b": false, "c": false });
+
+    // Equivalent to `a or (b and c)`.
+    assert!(eval("a or b and c", data.clone()));
+    assert!(!eval("(a or b) and c", data));
+}
+
+#[test]
+fn syntax_errors() {
+    for invalid in &[
+        "",
+        "file_name ends_with",
+        "(size > 10",
+        "size > 10 size",
+        "size > 10TB",
+        "file_name == \"unterminated",
+        "file_name matches 42",
+        "file_name matches \"(\"",
+        "size # 2",
+    ] {
+        assert!(
+            Condition::parse(invalid).is_err(),
+            "{} should fail",
+            invalid
+        );
+    }
+}
+
+#[test]
+fn evaluation_errors() {
+    let condition = Condition::parse("size ends_with \"MB\"").unwrap();
+    assert!(condition.evaluate(&json!({ "size": 42 })).is_err());
+
+    let condition = Condition::parse("file_name").unwrap();
+    assert!(condition
+        .evaluate(&json!({ "file_name": "a.pdf" }))
+        .is_err());
+}
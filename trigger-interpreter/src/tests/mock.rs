@@ -25,8 +25,8 @@ impl ActionConfigReader for Dummy {
     async fn get_rule(&self, _id: &str) -> Result<Rule> {
         Ok(Rule {
             trigger_config_id: 1,
-            action_config: String::from(""),
-            action_type: String::from(""),
+            actions: Vec::new(),
+            condition: None,
         })
     }
 }
@@ -1,2 +1,3 @@
+mod condition;
 mod mock;
 mod trigger_interpreter;
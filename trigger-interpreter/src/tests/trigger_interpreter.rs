@@ -2,7 +2,9 @@ use std::collections::HashMap;
 use std::time;
 use std::{sync::Arc, sync::Mutex, thread};
 
-use protocol::{ActionManifest, Rule, Trigger};
+use protocol::{ActionManifest, Rule, RuleAction, Trigger};
+
+use serde_json::json;
 
 use crate::interpreter::TriggerInterpreter;
 
@@ -23,20 +25,24 @@ fn basic_test() {
 fn in_memory_full_loop() {
     let rule = Rule {
         trigger_config_id: 1,
-        action_config: String::from(
-            "{\"body\": \"New file: {{file_name}}\", \"title\": \"ShifTTT: New File Created\"}",
-        ),
-        action_type: String::from("notify"),
+        actions: vec![RuleAction {
+            action_config: String::from(
+                "{\"body\": \"New file: {{file_name}}\", \"title\": \"ShifTTT: New File Created\"}",
+            ),
+            action_type: String::from("notify"),
+        }],
+        condition: None,
     };
     let mut action_configs = HashMap::new();
     action_configs.insert("1".into(), rule.clone());
 
     let file_name = "test";
-    let triggers = vec![Trigger {
-        rule: "1".into(),
-        trigger_type: String::from("file"),
-        data: String::from(format!("{{\"file_name\": \"{}\"}}", file_name)),
-    }];
+    let triggers = vec![Trigger::new(
+        "1".into(),
+        String::from("file"),
+        json!({ "file_name": file_name }).into(),
+    )];
+    let trigger_envelope = triggers[0].envelope.clone();
 
     let cfg_loader = Box::new(mock::InMemoryActionConfigReader::new(action_configs));
     let queue_reader = Box::new(Arc::new(Mutex::new(mock::InMemoryQueueReader::new(
@@ -62,17 +68,91 @@ fn in_memory_full_loop() {
 
     // Makes sure the trigger was properly interpreted
     assert_eq!(queue_writer_ref.queue.len(), 1);
+
+    let manifest = queue_writer_ref.queue.first().unwrap();
     assert_eq!(
-        queue_writer_ref.queue.first().unwrap(),
+        manifest,
         &ActionManifest {
             rule: "1".into(),
-            action_type: rule.action_type.clone(),
-            data: String::from(format!(
-                "{{\"body\": \"New file: {}\", \"title\": \"ShifTTT: New File Created\"}}",
-                file_name
-            ))
+            action_type: String::from("notify"),
+            data: json!({
+                "body": format!("New file: {}", file_name),
+                "title": "ShifTTT: New File Created"
+            })
+            .into(),
+            envelope: manifest.envelope.clone(),
         }
     );
+
+    // Makes sure the manifest can be traced back to its trigger.
+    assert_eq!(manifest.envelope.trigger_id, trigger_envelope.id);
+    assert_ne!(manifest.envelope.id, trigger_envelope.id);
+}
+
+#[test]
+fn in_memory_fan_out() {
+    let rule = Rule {
+        trigger_config_id: 1,
+        actions: vec![
+            RuleAction {
+                action_config: String::from("{\"title\": \"{{file_name}}\"}"),
+                action_type: String::from("notify"),
+            },
+            RuleAction {
+                action_config: String::from("{\"subject\": \"New file: {{file_name}}\"}"),
+                action_type: String::from("email"),
+            },
+        ],
+        condition: None,
+    };
+    let mut action_configs = HashMap::new();
+    action_configs.insert("1".into(), rule);
+
+    let triggers = vec![Trigger::new(
+        "1".into(),
+        String::from("file"),
+        json!({ "file_name": "test" }).into(),
+    )];
+    let trigger_envelope = triggers[0].envelope.clone();
+
+    let cfg_loader = Box::new(mock::InMemoryActionConfigReader::new(action_configs));
+    let queue_reader = Box::new(Arc::new(Mutex::new(mock::InMemoryQueueReader::new(
+        triggers,
+    ))));
+    let queue_writer = Box::new(Arc::new(Mutex::new(mock::InMemoryQueueWriter::new())));
+
+    let system = TriggerInterpreter::start(queue_reader.clone(), cfg_loader, queue_writer.clone());
+
+    thread::sleep(time::Duration::from_millis(100)); // Give the system a chance to boot.
+
+    system.terminate().unwrap();
+
+    let queue_writer_guard = queue_writer.lock().unwrap();
+    let queue_writer_ref = &(*queue_writer_guard);
+
+    let queue_reader_guard = queue_reader.lock().unwrap();
+    let queue_reader_ref = &(*queue_reader_guard);
+
+    // Makes sure the trigger was acknowledged once, after both manifests were pushed.
+    assert_eq!(queue_reader_ref.ack_count(), 1);
+
+    // Makes sure one manifest was emitted per action, in order.
+    let manifests = &queue_writer_ref.queue;
+    assert_eq!(manifests.len(), 2);
+
+    assert_eq!(manifests[0].action_type, "notify");
+    assert_eq!(manifests[0].data, json!({ "title": "test" }).into());
+
+    assert_eq!(manifests[1].action_type, "email");
+    assert_eq!(
+        manifests[1].data,
+        json!({ "subject": "New file: test" }).into()
+    );
+
+    for manifest in manifests.iter() {
+        assert_eq!(manifest.envelope.trigger_id, trigger_envelope.id);
+    }
+    assert_ne!(manifests[0].envelope.id, manifests[1].envelope.id);
 }
 
 #[test]
@@ -80,11 +160,11 @@ fn in_memory_action_config_missing() {
     let action_configs = HashMap::new();
 
     let file_name = "test";
-    let triggers = vec![Trigger {
-        rule: "1".into(),
-        trigger_type: String::from("file"),
-        data: String::from(format!("{{\"file_name\": \"{}\"}}", file_name)),
-    }];
+    let triggers = vec![Trigger::new(
+        "1".into(),
+        String::from("file"),
+        json!({ "file_name": file_name }).into(),
+    )];
 
     let cfg_loader = Box::new(mock::InMemoryActionConfigReader::new(action_configs));
     let queue_reader = Box::new(Arc::new(Mutex::new(mock::InMemoryQueueReader::new(
@@ -110,3 +190,197 @@ fn in_memory_action_config_missing() {
     // Makes sure the trigger was not interpreted
     assert_eq!(queue_writer_ref.queue.len(), 0);
 }
+
+#[test]
+fn in_memory_condition_filters_triggers() {
+    let rule = Rule {
+        trigger_config_id: 1,
+        actions: vec![RuleAction {
+            action_config: String::from("{\"title\": \"{{file_name}}\"}"),
+            action_type: String::from("notify"),
+        }],
+        condition: Some(String::from("file_name ends_with \".pdf\" and size > 1MB")),
+    };
+    let mut action_configs = HashMap::new();
+    action_configs.insert("1".into(), rule);
+
+    let triggers = vec![
+        Trigger::new(
+            "1".into(),
+            String::from("file"),
+            json!({ "file_name": "small.pdf", "size": 10 }).into(),
+        ),
+        Trigger::new(
+            "1".into(),
+            String::from("file"),
+            json!({ "file_name": "big.txt", "size": 10_000_000 }).into(),
+        ),
+        Trigger::new(
+            "1".into(),
+            String::from("file"),
+            json!({ "file_name": "big.pdf", "size": 10_000_000 }).into(),
+        ),
+    ];
+
+    let cfg_loader = Box::new(mock::InMemoryActionConfigReader::new(action_configs));
+    let queue_reader = Box::new(Arc::new(Mutex::new(mock::InMemoryQueueReader::new(
+        triggers,
+    ))));
+    let queue_writer = Box::new(Arc::new(Mutex::new(mock::InMemoryQueueWriter::new())));
+
+    let system = TriggerInterpreter::start(queue_reader.clone(), cfg_loader, queue_writer.clone());
+
+    thread::sleep(time::Duration::from_millis(500)); // Give the system a chance to consume all triggers.
+
+    system.terminate().unwrap();
+
+    let queue_writer_guard = queue_writer.lock().unwrap();
+    let queue_writer_ref = &(*queue_writer_guard);
+
+    let queue_reader_guard = queue_reader.lock().unwrap();
+    let queue_reader_ref = &(*queue_reader_guard);
+
+    // Dropped triggers are acknowledged too.
+    assert_eq!(queue_reader_ref.ack_count(), 3);
+
+    assert_eq!(queue_writer_ref.queue.len(), 1);
+    assert_eq!(
+        queue_writer_ref.queue[0].data,
+        json!({ "title": "big.pdf" }).into()
+    );
+}
+
+#[test]
+fn in_memory_invalid_condition() {
+    let rule = Rule {
+        trigger_config_id: 1,
+        actions: vec![RuleAction {
+            action_config: String::from("{}"),
+            action_type: String::from("notify"),
+        }],
+        condition: Some(String::from("file_name ends_with")),
+    };
+    let mut action_configs = HashMap::new();
+    action_configs.insert("1".into(), rule);
+
+    let triggers = vec![Trigger::new(
+        "1".into(),
+        String::from("file"),
+        json!({ "file_name": "test.pdf" }).into(),
+    )];
+
+    let cfg_loader = Box::new(mock::InMemoryActionConfigReader::new(action_configs));
+    let queue_reader = Box::new(Arc::new(Mutex::new(mock::InMemoryQueueReader::new(
+        triggers,
+    ))));
+    let queue_writer = Box::new(Arc::new(Mutex::new(mock::InMemoryQueueWriter::new())));
+
+    let system = TriggerInterpreter::start(queue_reader.clone(), cfg_loader, queue_writer.clone());
+
+    thread::sleep(time::Duration::from_millis(100)); // Give the system a chance to boot.
+
+    system.terminate().unwrap();
+
+    let queue_writer_guard = queue_writer.lock().unwrap();
+    let queue_reader_guard = queue_reader.lock().unwrap();
+
+    // The rule is rejected: nothing is emitted, and the trigger is dropped.
+    assert_eq!(queue_reader_guard.ack_count(), 1);
+    assert!(queue_writer_guard.queue.is_empty());
+}
+
+#[test]
+fn in_memory_unrenderable_template() {
+    let rule = Rule {
+        trigger_config_id: 1,
+        actions: vec![
+            RuleAction {
+                action_config: String::from("{}"),
+                action_type: String::from("notify"),
+            },
+            RuleAction {
+                action_config: String::from("{\"path\": \"{{file_name}}\"}"),
+                action_type: String::from("email"),
+            },
+        ],
+        condition: None,
+    };
+    let mut action_configs = HashMap::new();
+    action_configs.insert("1".into(), rule);
+
+    // Handlebars doesn't escape backslashes, so this file name renders invalid JSON.
+    let triggers = vec![
+        Trigger::new(
+            "1".into(),
+            String::from("file"),
+            json!({ "file_name": "C:\\data.pdf" }).into(),
+        ),
+        Trigger::new(
+            "1".into(),
+            String::from("file"),
+            json!({ "file_name": "test.pdf" }).into(),
+        ),
+    ];
+
+    let cfg_loader = Box::new(mock::InMemoryActionConfigReader::new(action_configs));
+    let queue_reader = Box::new(Arc::new(Mutex::new(mock::InMemoryQueueReader::new(
+        triggers,
+    ))));
+    let queue_writer = Box::new(Arc::new(Mutex::new(mock::InMemoryQueueWriter::new())));
+
+    let system = TriggerInterpreter::start(queue_reader.clone(), cfg_loader, queue_writer.clone());
+
+    thread::sleep(time::Duration::from_millis(300)); // Give the system a chance to boot.
+
+    system.terminate().unwrap();
+
+    let queue_writer_guard = queue_writer.lock().unwrap();
+    let queue_reader_guard = queue_reader.lock().unwrap();
+
+    // The unrenderable trigger is dropped without a partial fan-out, and the other goes through.
+    assert_eq!(queue_reader_guard.ack_count(), 2);
+    assert_eq!(queue_writer_guard.queue.len(), 2);
+    assert_eq!(
+        queue_writer_guard.queue[1].data,
+        json!({ "path": "test.pdf" }).into()
+    );
+}
+
+#[test]
+fn in_memory_condition_evaluation_error() {
+    let rule = Rule {
+        trigger_config_id: 1,
+        actions: vec![RuleAction {
+            action_config: String::from("{}"),
+            action_type: String::from("notify"),
+        }],
+        condition: Some(String::from("size ends_with \"MB\"")),
+    };
+    let mut action_configs = HashMap::new();
+    action_configs.insert("1".into(), rule);
+
+    let triggers = vec![Trigger::new(
+        "1".into(),
+        String::from("file"),
+        json!({ "size": 42 }).into(),
+    )];
+
+    let cfg_loader = Box::new(mock::InMemoryActionConfigReader::new(action_configs));
+    let queue_reader = Box::new(Arc::new(Mutex::new(mock::InMemoryQueueReader::new(
+        triggers,
+    ))));
+    let queue_writer = Box::new(Arc::new(Mutex::new(mock::InMemoryQueueWriter::new())));
+
+    let system = TriggerInterpreter::start(queue_reader.clone(), cfg_loader, queue_writer.clone());
+
+    thread::sleep(time::Duration::from_millis(100)); // Give the system a chance to boot.
+
+    system.terminate().unwrap();
+
+    let queue_writer_guard = queue_writer.lock().unwrap();
+    let queue_reader_guard = queue_reader.lock().unwrap();
+
+    // The condition isn't met: nothing is emitted, and the trigger is dropped.
+    assert_eq!(queue_reader_guard.ack_count(), 1);
+    assert!(queue_writer_guard.queue.is_empty());
+}
@@ -10,6 +10,8 @@ use gcloud::AuthProvider;
 
 use google_cloud::datastore;
 
+use protocol::Schedule;
+
 use crate::interface::{TriggerConfigLoader, TriggerConfiguration};
 
 pub struct DatastoreTriggerConfigLoader {
@@ -42,7 +44,16 @@ impl TriggerConfigLoader for DatastoreTriggerConfigLoader {
             .await?
             .into_iter()
             .map(|e| {
-                datastore::FromValue::from_value(e.into_properties()).map_err(anyhow::Error::new)
+                let mut properties = e.into_properties();
+
+                // Configs stored before schedules existed are polled on every cycle.
+                if let datastore::Value::EntityValue(fields) = &mut properties {
+                    fields
+                        .entry(String::from("schedule"))
+                        .or_insert_with(|| datastore::IntoValue::into_value(Schedule::default()));
+                }
+
+                datastore::FromValue::from_value(properties).map_err(anyhow::Error::new)
             })
             .collect();
         configs
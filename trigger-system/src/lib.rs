@@ -1,6 +1,8 @@
 pub mod iface_impl;
 mod interface;
 mod manager;
+mod push;
+mod scheduler;
 mod system;
 
 // Public interface.
@@ -1,20 +1,42 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::{mpsc, Arc};
-use std::thread;
 use std::time;
 
-use anyhow::{ensure, Result};
+use anyhow::{anyhow, Error, Result};
 
-use plugin_core::TriggerPlugin;
+use chrono::Utc;
+
+use plugin_core::{AsyncTriggerPlugin, Error as PluginError, PushTriggerPlugin};
 use plugin_host::PluginHost;
 
-use protocol::TriggerConfiguration;
+use protocol::{Trigger, TriggerConfiguration};
+
+use tokio::sync::mpsc::{self as async_mpsc, UnboundedReceiver, UnboundedSender};
 
+use crate::push::ChannelSink;
+use crate::scheduler::Scheduler;
 use crate::{BoxedCfgLoader, BoxedQueueWriter};
 
 const EXIT_POLL_FREQUENCY: time::Duration = time::Duration::from_millis(100);
 const CONFIG_UPDATE_FREQUENCY: time::Duration = time::Duration::from_secs(60 * 5); // Default to 5 min. TODO: Make configurable.
 
+/// Delay before polling a config again after a retryable failure without a retry hint, doubled
+/// on every subsequent failure.
+const BASE_RETRY_DELAY: time::Duration = time::Duration::from_secs(1);
+const MAX_RETRY_DELAY: time::Duration = time::Duration::from_secs(60);
+
+/// A push trigger configuration started by the manager.
+struct RunningPushConfig {
+    cfg: TriggerConfiguration,
+    sink: Arc<ChannelSink>,
+}
+
+/// Outcome of a poll run in the background.
+struct PollOutcome {
+    cfg: TriggerConfiguration,
+    result: Result<Vec<Trigger>, PluginError>,
+}
+
 /// The trigger manager is the "main" thread of the trigger system.
 pub struct TriggerManager {
     cfg_loader: BoxedCfgLoader,
@@ -24,7 +46,36 @@ pub struct TriggerManager {
     configs: Vec<TriggerConfiguration>,
     last_config_update: time::Instant,
 
-    executors: HashMap<String, Arc<Box<dyn TriggerPlugin>>>,
+    // When the pull configs are due, from their schedule.
+    scheduler: Scheduler,
+
+    // Configs not to be polled before the given instant, after they failed.
+    backoffs: HashMap<i64, time::Instant>,
+
+    // Number of consecutive retryable failures of the configs, reset by a successful poll.
+    failures: HashMap<i64, u32>,
+
+    // Configs that failed permanently. They stay out of the polling loop until they change.
+    quarantined: HashMap<i64, TriggerConfiguration>,
+
+    executors: HashMap<String, Arc<Box<dyn AsyncTriggerPlugin>>>,
+
+    // Generation of the plugin host the executors were fetched at.
+    plugin_generation: u64,
+
+    push_executors: HashMap<String, Arc<Box<dyn PushTriggerPlugin>>>,
+    running_push_configs: HashMap<i64, RunningPushConfig>,
+    push_tx: UnboundedSender<Trigger>,
+    push_rx: UnboundedReceiver<Trigger>,
+
+    // Configs being polled. A config isn't polled again before its previous poll is done.
+    polling: HashSet<i64>,
+    poll_tx: UnboundedSender<PollOutcome>,
+    poll_rx: UnboundedReceiver<PollOutcome>,
+
+    // Triggers not pushed to the queue yet, oldest first. They are kept while the queue can't
+    // be reached, and pushed again on the next cycle.
+    unsent: VecDeque<Trigger>,
 
     plugin_host: Arc<PluginHost>,
 }
@@ -36,6 +87,9 @@ impl TriggerManager {
         queue_writer: BoxedQueueWriter,
         plugin_host: Arc<PluginHost>,
     ) -> Result<Self> {
+        let (push_tx, push_rx) = async_mpsc::unbounded_channel();
+        let (poll_tx, poll_rx) = async_mpsc::unbounded_channel();
+
         let mut manager = TriggerManager {
             cfg_loader,
             queue_writer,
@@ -45,8 +99,26 @@ impl TriggerManager {
             last_config_update: time::Instant::now()
                 .checked_sub(2 * CONFIG_UPDATE_FREQUENCY)
                 .unwrap(), // hack to mark configs as out of date. this is because we dont want to fetch configs in new() because its not async.
+            scheduler: Scheduler::new(),
+
+            backoffs: HashMap::new(),
+            failures: HashMap::new(),
+            quarantined: HashMap::new(),
 
             executors: HashMap::new(),
+            plugin_generation: 0,
+
+            push_executors: HashMap::new(),
+            running_push_configs: HashMap::new(),
+            push_tx,
+            push_rx,
+
+            polling: HashSet::new(),
+            poll_tx,
+            poll_rx,
+
+            unsent: VecDeque::new(),
+
             plugin_host,
         };
 
@@ -56,47 +128,344 @@ impl TriggerManager {
     }
 
     fn refresh_plugins(&mut self) -> Result<()> {
+        self.plugin_generation = self.plugin_host.generation();
         self.executors.clear();
         for trigger_plugin in self.plugin_host.get_trigger_plugins() {
             let trigger_name = String::from(trigger_plugin.get_type());
             self.executors.insert(trigger_name, trigger_plugin.clone());
         }
 
+        self.push_executors.clear();
+        for trigger_plugin in self.plugin_host.get_push_trigger_plugins() {
+            let trigger_name = String::from(trigger_plugin.get_type());
+            self.push_executors
+                .insert(trigger_name, trigger_plugin.clone());
+        }
+
         Ok(())
     }
 
-    async fn execute_trigger(&mut self, cfg: &TriggerConfiguration) -> Result<()> {
-        log::debug!("checking trigger {}/{}", &cfg.trigger_type, cfg.id);
+    /// Picks up the plugins loaded or reloaded by the host since the last refresh.
+    ///
+    /// Push configs are restarted on the new plugins, and all configs are validated again.
+    fn refresh_plugins_if_changed(&mut self) -> Result<()> {
+        if self.plugin_host.generation() == self.plugin_generation {
+            return Ok(());
+        }
+
+        log::info!("plugins changed, refreshing trigger executors");
+        self.stop_all_push_configs();
+        self.refresh_plugins()?;
+
+        // Configs quarantined by the previous plugins get another chance.
+        self.quarantined.clear();
+        self.mark_configs_outdated();
+
+        Ok(())
+    }
+
+    fn mark_configs_outdated(&mut self) {
+        self.last_config_update = time::Instant::now()
+            .checked_sub(2 * CONFIG_UPDATE_FREQUENCY)
+            .unwrap();
+    }
+
+    /// Filters out the configurations rejected by their trigger plugin, and the pull
+    /// configurations whose schedule can't be followed.
+    ///
+    /// Rejected configurations are reported once per config refresh instead of failing every poll.
+    fn validate_configs(&self, configs: Vec<TriggerConfiguration>) -> Vec<TriggerConfiguration> {
+        configs
+            .into_iter()
+            .filter(|cfg| {
+                let result = if let Some(executor) = self.executors.get(&cfg.trigger_type) {
+                    self.plugin_host
+                        .guard(&cfg.trigger_type, || executor.validate_config(cfg))
+                        .map_err(Error::from)
+                        .and_then(|_| Scheduler::validate(&cfg.schedule))
+                } else if let Some(executor) = self.push_executors.get(&cfg.trigger_type) {
+                    self.plugin_host
+                        .guard(&cfg.trigger_type, || executor.validate_config(cfg))
+                        .map_err(Error::from)
+                } else {
+                    Err(anyhow!("unknown trigger type: {}", cfg.trigger_type))
+                };
+
+                match result {
+                    Ok(()) => true,
+                    Err(e) => {
+                        log::error!(
+                            "rejected trigger config {}/{} (rule {}): {}",
+                            &cfg.trigger_type,
+                            cfg.id,
+                            &cfg.rule,
+                            e
+                        );
+                        false
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Starts the push configurations that appeared, and stops the ones that were removed.
+    ///
+    /// Modified configurations are restarted.
+    fn sync_push_configs(&mut self, configs: Vec<TriggerConfiguration>) {
+        let removed_ids: Vec<i64> = self
+            .running_push_configs
+            .iter()
+            .filter(|(_, running)| !configs.contains(&running.cfg))
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in removed_ids.into_iter() {
+            if let Some(running) = self.running_push_configs.remove(&id) {
+                self.stop_push_config(running);
+            }
+        }
+
+        for cfg in configs.into_iter() {
+            if self.running_push_configs.contains_key(&cfg.id) {
+                continue;
+            }
+
+            // Configs were validated, so the executor exists.
+            if let Some(executor) = self.push_executors.get(&cfg.trigger_type) {
+                let sink = Arc::new(ChannelSink::new(self.push_tx.clone()));
+                let started = self
+                    .plugin_host
+                    .guard(&cfg.trigger_type, || executor.start(&cfg, sink.clone()));
+                match started {
+                    Ok(()) => {
+                        log::info!("started push trigger {}/{}", &cfg.trigger_type, cfg.id);
+                        self.running_push_configs
+                            .insert(cfg.id, RunningPushConfig { cfg, sink });
+                    }
+                    Err(e) => log::error!(
+                        "failed to start push trigger {}/{}: {}",
+                        &cfg.trigger_type,
+                        cfg.id,
+                        e
+                    ),
+                }
+            }
+        }
+    }
+
+    fn stop_push_config(&self, running: RunningPushConfig) {
+        running.sink.close();
 
-        let executor_maybe = self.executors.get_mut(&cfg.trigger_type);
+        if let Some(executor) = self.push_executors.get(&running.cfg.trigger_type) {
+            let stopped = self
+                .plugin_host
+                .guard(&running.cfg.trigger_type, || executor.stop(&running.cfg));
+            match stopped {
+                Ok(()) => log::info!(
+                    "stopped push trigger {}/{}",
+                    &running.cfg.trigger_type,
+                    running.cfg.id
+                ),
+                Err(e) => log::error!(
+                    "failed to stop push trigger {}/{}: {}",
+                    &running.cfg.trigger_type,
+                    running.cfg.id,
+                    e
+                ),
+            }
+        }
+    }
 
-        ensure!(
-            executor_maybe.is_some(),
-            format!("Unknown trigger type: {}", cfg.trigger_type)
-        );
+    fn stop_all_push_configs(&mut self) {
+        let running_configs: Vec<RunningPushConfig> = self
+            .running_push_configs
+            .drain()
+            .map(|(_, running)| running)
+            .collect();
+        for running in running_configs.into_iter() {
+            self.stop_push_config(running);
+        }
+    }
+
+    /// Collects the triggers emitted by push plugins, to be pushed to the queue.
+    fn collect_pushed_triggers(&mut self) {
+        while let Ok(trigger) = self.push_rx.try_recv() {
+            log::info!(
+                "[{}] trigger pushed for {} (rule {})",
+                trigger.envelope,
+                &trigger.trigger_type,
+                &trigger.rule
+            );
+            self.unsent.push_back(trigger);
+        }
+    }
 
-        // unwrap is safe because of ensure()
-        for trigger in executor_maybe.unwrap().pull_trigger(cfg)? {
-            self.queue_writer.push_trigger(trigger).await?;
+    /// Pushes the collected triggers to the queue, in order. The triggers left are kept when a
+    /// push fails, to be pushed on the next cycle.
+    async fn push_unsent(&mut self) -> Result<()> {
+        while let Some(trigger) = self.unsent.front() {
+            if let Err(e) = self.queue_writer.push_trigger(trigger.clone()).await {
+                return Err(e.context(format!(
+                    "failed to push triggers, {} kept for the next cycle",
+                    self.unsent.len()
+                )));
+            }
+            self.unsent.pop_front();
         }
+
         Ok(())
     }
 
+    /// Polls a config in the background.
+    ///
+    /// Polls are bounded by the timeouts of the plugin host, and run concurrently, so a slow
+    /// plugin doesn't hold up the other configs.
+    fn poll_trigger(&mut self, cfg: &TriggerConfiguration) -> Result<()> {
+        log::debug!("checking trigger {}/{}", &cfg.trigger_type, cfg.id);
+
+        let executor = self
+            .executors
+            .get(&cfg.trigger_type)
+            .cloned()
+            .ok_or_else(|| anyhow!("Unknown trigger type: {}", cfg.trigger_type))?;
+
+        self.polling.insert(cfg.id);
+        let plugin_host = self.plugin_host.clone();
+        let poll_tx = self.poll_tx.clone();
+        let cfg = cfg.clone();
+        tokio::spawn(async move {
+            let result = plugin_host
+                .watch(&cfg.trigger_type, &cfg.rule, executor.pull_trigger(&cfg))
+                .await;
+
+            // The manager only goes away when stopping.
+            let _ = poll_tx.send(PollOutcome { cfg, result });
+        });
+
+        Ok(())
+    }
+
+    /// Collects the triggers of the polls that are done, to be pushed to the queue, and handles
+    /// their failures.
+    fn collect_polls(&mut self) {
+        while let Ok(outcome) = self.poll_rx.try_recv() {
+            let cfg = outcome.cfg;
+            self.polling.remove(&cfg.id);
+
+            match outcome.result {
+                Ok(triggers) => {
+                    self.failures.remove(&cfg.id);
+                    for trigger in triggers.into_iter() {
+                        log::info!(
+                            "[{}] trigger fired for {}/{} (rule {})",
+                            trigger.envelope,
+                            &cfg.trigger_type,
+                            cfg.id,
+                            &trigger.rule
+                        );
+                        self.unsent.push_back(trigger);
+                    }
+                }
+                Err(e) => self.handle_failure(&cfg, Error::from(e)),
+            }
+        }
+    }
+
+    /// Decides what happens to a config whose poll failed, based on the kind of plugin error.
+    ///
+    /// Retryable errors leave the config in the polling loop, honoring the retry hint of the
+    /// plugin, or backing off exponentially without one. Other plugin errors quarantine the
+    /// config until it is modified.
+    fn handle_failure(&mut self, cfg: &TriggerConfiguration, error: Error) {
+        match error.downcast_ref::<PluginError>() {
+            Some(plugin_error) if plugin_error.is_retryable() => {
+                let failures = self.failures.entry(cfg.id).or_insert(0);
+                *failures += 1;
+                let delay = plugin_error.retry_after.unwrap_or_else(|| {
+                    BASE_RETRY_DELAY
+                        .saturating_mul(2u32.saturating_pow(*failures - 1))
+                        .min(MAX_RETRY_DELAY)
+                });
+
+                log::warn!(
+                    "trigger {}/{} failed, retrying in {:?}: {}",
+                    &cfg.trigger_type,
+                    cfg.id,
+                    delay,
+                    plugin_error
+                );
+                self.backoffs.insert(cfg.id, time::Instant::now() + delay);
+            }
+            Some(plugin_error) => {
+                log::error!(
+                    "trigger {}/{} (rule {}) quarantined until its config changes: {}",
+                    &cfg.trigger_type,
+                    cfg.id,
+                    &cfg.rule,
+                    plugin_error
+                );
+                self.configs.retain(|c| c.id != cfg.id);
+                self.backoffs.remove(&cfg.id);
+                self.failures.remove(&cfg.id);
+                self.quarantined.insert(cfg.id, cfg.clone());
+            }
+            None => log::error!("{:?}", error),
+        }
+    }
+
     async fn check_all_triggers(&mut self) -> Result<()> {
         log::debug!("begin checking all triggers");
 
+        self.refresh_plugins_if_changed()?;
+
         let now = time::Instant::now();
         if now.duration_since(self.last_config_update) > CONFIG_UPDATE_FREQUENCY {
             // Update trigger configs.
             log::info!("refreshing trigger configs");
             self.last_config_update = now;
-            self.configs = self.cfg_loader.get_all_configurations().await?;
+            let mut configs = self.cfg_loader.get_all_configurations().await?;
+
+            // Quarantined configs are given another chance once they are modified.
+            self.quarantined.retain(|_, cfg| configs.contains(cfg));
+            configs.retain(|cfg| !self.quarantined.contains_key(&cfg.id));
+
+            let (push_configs, pull_configs): (Vec<_>, Vec<_>) = self
+                .validate_configs(configs)
+                .into_iter()
+                .partition(|cfg| self.push_executors.contains_key(&cfg.trigger_type));
+            self.scheduler.sync(&pull_configs, Utc::now());
+            self.configs = pull_configs;
+            self.sync_push_configs(push_configs);
             log::info!("trigger config refresh complete");
         }
 
+        self.collect_pushed_triggers();
+        self.collect_polls();
+        self.push_unsent().await?;
+
+        let scheduled_at = Utc::now();
         let configs_copy = self.configs.clone();
         for config in configs_copy.into_iter() {
-            self.execute_trigger(&config).await?;
+            // Configs still being polled when they are due are polled once their poll is done.
+            if self.polling.contains(&config.id) {
+                continue;
+            }
+
+            // Failed configs are retried once their backoff is over, whatever their schedule.
+            let retrying = match self.backoffs.get(&config.id) {
+                Some(retry_at) if now < *retry_at => continue,
+                Some(_) => true,
+                None => false,
+            };
+            if !retrying && !self.scheduler.is_due(config.id, scheduled_at) {
+                continue;
+            }
+            self.backoffs.remove(&config.id);
+            self.scheduler.polled(config.id, scheduled_at);
+
+            if let Err(e) = self.poll_trigger(&config) {
+                self.handle_failure(&config, e);
+            }
         }
 
         Ok(())
@@ -113,7 +482,17 @@ impl TriggerManager {
                 log::error!("{:?}", e);
             }
 
-            thread::sleep(EXIT_POLL_FREQUENCY);
+            tokio::time::sleep(EXIT_POLL_FREQUENCY).await;
+        }
+
+        self.stop_all_push_configs();
+
+        // Don't lose the triggers emitted before the push configs were stopped, or found by
+        // the polls that are done.
+        self.collect_pushed_triggers();
+        self.collect_polls();
+        if let Err(e) = self.push_unsent().await {
+            log::error!("trigger system stopped, triggers lost: {:?}", e);
         }
     }
 }
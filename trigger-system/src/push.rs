@@ -0,0 +1,42 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use plugin_core::{Error, TriggerSink};
+
+use protocol::Trigger;
+
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Sink handed to push trigger plugins, forwarding their triggers to the trigger manager.
+///
+/// Each running configuration gets its own sink, closed when the configuration is stopped.
+pub struct ChannelSink {
+    tx: UnboundedSender<Trigger>,
+    closed: AtomicBool,
+}
+
+impl ChannelSink {
+    pub fn new(tx: UnboundedSender<Trigger>) -> Self {
+        ChannelSink {
+            tx,
+            closed: AtomicBool::new(false),
+        }
+    }
+
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+    }
+}
+
+impl TriggerSink for ChannelSink {
+    fn emit(&self, trigger: Trigger) -> Result<(), Error> {
+        if self.closed.load(Ordering::SeqCst) {
+            return Err(Error::permanent(String::from(
+                "trigger configuration was stopped",
+            )));
+        }
+
+        self.tx
+            .send(trigger)
+            .map_err(|_| Error::permanent(String::from("trigger system is stopped")))
+    }
+}
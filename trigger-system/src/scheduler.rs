@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time;
+
+use anyhow::{anyhow, Result};
+
+use chrono::{DateTime, Duration, Utc};
+
+use protocol::{Schedule, TriggerConfiguration};
+
+/// A schedule, parsed.
+enum Timing {
+    Always,
+    Interval(Duration),
+    Cron(Box<cron::Schedule>),
+}
+
+impl Timing {
+    fn parse(schedule: &Schedule) -> Result<Self> {
+        match schedule {
+            Schedule::Always => Ok(Timing::Always),
+            Schedule::Interval(0) => Err(anyhow!("poll interval must be positive")),
+            Schedule::Interval(interval_ms) => {
+                Duration::from_std(time::Duration::from_millis(*interval_ms))
+                    .map(Timing::Interval)
+                    .map_err(|_| anyhow!("poll interval is too long: {}ms", interval_ms))
+            }
+            Schedule::Cron(expression) => {
+                // The cron crate wants seconds, which the usual five-field expressions don't have.
+                let mut fields: Vec<String> =
+                    expression.split_whitespace().map(String::from).collect();
+                if fields.len() == 5 {
+                    fields.insert(0, String::from("0"));
+                }
+                if let Some(days) = fields.get_mut(5) {
+                    *days = days_of_week(days)
+                        .map_err(|e| anyhow!("invalid cron expression '{}': {}", expression, e))?;
+                }
+                cron::Schedule::from_str(&fields.join(" "))
+                    .map(|schedule| Timing::Cron(Box::new(schedule)))
+                    .map_err(|e| anyhow!("invalid cron expression '{}': {}", expression, e))
+            }
+        }
+    }
+
+    /// Returns when a configuration polled at `at` is due next, or `None` if it is never due
+    /// again.
+    fn next_after(&self, at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Timing::Always => Some(at),
+            Timing::Interval(interval) => at.checked_add_signed(*interval),
+            Timing::Cron(schedule) => schedule.after(&at).next(),
+        }
+    }
+}
+
+/// Translates a day-of-week field from the standard numbering (0 to 6 from Sunday, and 7 for
+/// Sunday again) to the numbering of the cron crate (1 to 7 from Sunday).
+///
+/// Numeric items are expanded to the list of days they match, and named days are kept as is.
+fn days_of_week(field: &str) -> Result<String> {
+    let mut translated = Vec::new();
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (range, Some(step)),
+            None => (item, None),
+        };
+        if range == "*" && step.is_none() {
+            translated.push(String::from(item));
+            continue;
+        }
+
+        let parse = |day: &str| day.parse::<u32>().ok().filter(|day| *day <= 7);
+        let (first, last) = match range.split_once('-') {
+            Some((first, last)) => (parse(first), parse(last)),
+            None if range == "*" => (Some(0), Some(6)),
+            // A single day with a step runs to the end of the week.
+            None if step.is_some() => (parse(range), Some(6)),
+            None => (parse(range), parse(range)),
+        };
+        let step = match step {
+            Some(step) => step.parse::<usize>().ok().filter(|step| *step > 0),
+            None => Some(1),
+        };
+
+        match (first, last, step) {
+            (Some(first), Some(last), Some(step)) if first <= last => {
+                let mut days: Vec<u32> = (first..=last)
+                    .step_by(step)
+                    .map(|day| day % 7 + 1)
+                    .collect();
+                days.sort_unstable();
+                days.dedup();
+                translated.extend(days.iter().map(u32::to_string));
+            }
+            // Named days are numbered by the cron crate.
+            _ if item.chars().any(|c| c.is_ascii_alphabetic()) => {
+                translated.push(String::from(item))
+            }
+            _ => return Err(anyhow!("invalid day of the week '{}'", item)),
+        }
+    }
+    Ok(translated.join(","))
+}
+
+/// A configuration tracked by the scheduler.
+struct Scheduled {
+    schedule: Schedule,
+    timing: Timing,
+    next: Option<DateTime<Utc>>,
+}
+
+/// Decides when the pull configurations are polled, from their schedule.
+///
+/// Times are passed in by the caller, so the scheduler doesn't depend on the clock.
+pub(crate) struct Scheduler {
+    scheduled: HashMap<i64, Scheduled>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Scheduler {
+            scheduled: HashMap::new(),
+        }
+    }
+
+    /// Checks that a schedule can be followed.
+    pub fn validate(schedule: &Schedule) -> Result<()> {
+        Timing::parse(schedule).map(|_| ())
+    }
+
+    /// Tracks the given configurations, and forgets the others.
+    ///
+    /// Configurations whose schedule changed are rescheduled: configurations on an interval
+    /// are due immediately, and configurations on a cron expression at its next match.
+    pub fn sync(&mut self, configs: &[TriggerConfiguration], now: DateTime<Utc>) {
+        self.scheduled
+            .retain(|id, _| configs.iter().any(|cfg| cfg.id == *id));
+
+        for cfg in configs.iter() {
+            if let Some(scheduled) = self.scheduled.get(&cfg.id) {
+                if scheduled.schedule == cfg.schedule {
+                    continue;
+                }
+            }
+
+            // Configs are validated before they are scheduled.
+            let timing = match Timing::parse(&cfg.schedule) {
+                Ok(timing) => timing,
+                Err(e) => {
+                    log::error!(
+                        "trigger {}/{} can't be scheduled: {}",
+                        &cfg.trigger_type,
+                        cfg.id,
+                        e
+                    );
+                    self.scheduled.remove(&cfg.id);
+                    continue;
+                }
+            };
+            let next = match &timing {
+                Timing::Cron(_) => timing.next_after(now),
+                _ => Some(now),
+            };
+            self.scheduled.insert(
+                cfg.id,
+                Scheduled {
+                    schedule: cfg.schedule.clone(),
+                    timing,
+                    next,
+                },
+            );
+        }
+    }
+
+    /// Returns whether a configuration should be polled at `now`.
+    pub fn is_due(&self, id: i64, now: DateTime<Utc>) -> bool {
+        match self.scheduled.get(&id) {
+            Some(scheduled) => scheduled.next.map(|next| next <= now).unwrap_or(false),
+            None => false,
+        }
+    }
+
+    /// Schedules the next poll of a configuration polled at `now`.
+    pub fn polled(&mut self, id: i64, now: DateTime<Utc>) {
+        if let Some(scheduled) = self.scheduled.get_mut(&id) {
+            scheduled.next = scheduled.timing.next_after(now);
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::Arc;
 
 use anyhow::{Context, Error, Result};
@@ -34,7 +35,12 @@ impl TriggerSystem {
                     cfg.queue_writer,
                     cfg.plugin_host,
                 ) {
-                    Ok(mut man) => man.start(),
+                    Ok(mut man) => {
+                        // Plugin calls are guarded, so this is a bug of the manager itself.
+                        if panic::catch_unwind(AssertUnwindSafe(|| man.start())).is_err() {
+                            log::error!("trigger manager panicked, stopping");
+                        }
+                    }
                     Err(e) => log::error!("failed to start manager: {:?}", e),
                 }
             }),
@@ -5,7 +5,7 @@ use std::sync::Mutex;
 
 use plugin_core::{Error, TriggerPlugin};
 
-use protocol::{Trigger, TriggerConfiguration};
+use protocol::{Payload, Trigger, TriggerConfiguration};
 
 use serde::{Deserialize, Serialize};
 
@@ -29,15 +29,23 @@ impl TriggerPlugin for DirectoryWatcher {
         "directory_watch"
     }
 
+    fn validate_config(&self, cfg: &TriggerConfiguration) -> Result<(), Error> {
+        cfg.data
+            .decode::<DirectoryWatchPayload>()
+            .map_err(|e| Error::invalid_config(e.to_string()))?;
+        Ok(())
+    }
+
     fn pull_trigger(&self, cfg: &TriggerConfiguration) -> Result<Vec<Trigger>, Error> {
-        let payload: DirectoryWatchPayload =
-            serde_json::from_str(&cfg.data).map_err(|e| Error {
-                message: e.to_string(),
-            })?;
+        let payload: DirectoryWatchPayload = cfg
+            .data
+            .decode()
+            .map_err(|e| Error::invalid_config(e.to_string()))?;
 
-        let mut seen_files_guard = self.seen_files.lock().map_err(|e| Error {
-            message: e.to_string(),
-        })?;
+        let mut seen_files_guard = self
+            .seen_files
+            .lock()
+            .map_err(|e| Error::transient(e.to_string()))?;
         let seen_files = &mut (*seen_files_guard);
 
         match seen_files.get_mut(&cfg.rule) {
@@ -45,24 +53,21 @@ impl TriggerPlugin for DirectoryWatcher {
                 let mut results = Vec::new();
 
                 for entry in fs::read_dir(&payload.directory)
-                    .map_err(|e| Error {
-                        message: e.to_string(),
-                    })?
+                    .map_err(|e| Error::transient(e.to_string()))?
                     .filter_map(Result::ok)
                 {
                     if !seen_files.contains(&entry.path()) {
                         // Add a trigger.
                         seen_files.insert(entry.path());
-                        results.push(Trigger {
-                            rule: cfg.rule.clone(),
-                            trigger_type: cfg.trigger_type.clone(),
-                            data: serde_json::to_string(&TriggerData {
-                                file_name: entry.file_name().to_string_lossy().to_string(),
-                            })
-                            .map_err(|e| Error {
-                                message: e.to_string(),
-                            })?,
+                        let data = Payload::encode(&TriggerData {
+                            file_name: entry.file_name().to_string_lossy().to_string(),
                         })
+                        .map_err(|e| Error::transient(e.to_string()))?;
+                        results.push(Trigger::new(
+                            cfg.rule.clone(),
+                            cfg.trigger_type.clone(),
+                            data,
+                        ))
                     }
                 }
                 Ok(results)
@@ -71,9 +76,7 @@ impl TriggerPlugin for DirectoryWatcher {
                 let mut initial_files = HashSet::new();
 
                 for entry in fs::read_dir(&payload.directory)
-                    .map_err(|e| Error {
-                        message: e.to_string(),
-                    })?
+                    .map_err(|e| Error::transient(e.to_string()))?
                     .filter_map(Result::ok)
                 {
                     initial_files.insert(entry.path());
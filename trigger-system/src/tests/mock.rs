@@ -1,9 +1,15 @@
 use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time;
 
-use anyhow::Error;
+use anyhow::{anyhow, Error};
 
 use async_trait::async_trait;
 
+use plugin_core::{
+    AsyncTriggerPlugin, Error as PluginError, PushTriggerPlugin, TriggerPlugin, TriggerSink,
+};
+
 use crate::interface::{Trigger, TriggerConfigLoader, TriggerConfiguration, TriggerQueueWriter};
 
 pub struct Dummy {}
@@ -47,11 +53,17 @@ impl TriggerConfigLoader for InMemoryConfigLoader {
 
 pub struct InMemoryQueueWriter {
     pub queue: Vec<Trigger>,
+
+    // Number of pushes failing before the queue is reachable.
+    pub unavailable: usize,
 }
 
 impl InMemoryQueueWriter {
     pub fn new() -> Self {
-        Self { queue: Vec::new() }
+        Self {
+            queue: Vec::new(),
+            unavailable: 0,
+        }
     }
 }
 
@@ -62,7 +74,127 @@ impl TriggerQueueWriter for MultiThreadQueueWriter {
     async fn push_trigger(&self, trigger: Trigger) -> Result<(), Error> {
         let mut guard = self.lock().unwrap(); // We won't get poisoning in a simple test.
         let queue_handle = &mut *guard;
+        if queue_handle.unavailable > 0 {
+            queue_handle.unavailable -= 1;
+            return Err(anyhow!("queue unavailable"));
+        }
         queue_handle.queue.push(trigger);
         Ok(())
     }
 }
+
+/// Push trigger plugin emitting a single trigger from a background thread when started.
+#[derive(Clone, Default)]
+pub struct PushOnce {
+    pub started: Arc<Mutex<Vec<i64>>>,
+    pub stopped: Arc<Mutex<Vec<i64>>>,
+}
+
+impl PushTriggerPlugin for PushOnce {
+    fn get_type(&self) -> &str {
+        "push_once"
+    }
+
+    fn start(
+        &self,
+        cfg: &TriggerConfiguration,
+        sink: Arc<dyn TriggerSink>,
+    ) -> Result<(), PluginError> {
+        self.started.lock().unwrap().push(cfg.id);
+
+        let trigger = Trigger::new(cfg.rule.clone(), cfg.trigger_type.clone(), cfg.data.clone());
+        thread::spawn(move || sink.emit(trigger).unwrap());
+
+        Ok(())
+    }
+
+    fn stop(&self, cfg: &TriggerConfiguration) -> Result<(), PluginError> {
+        self.stopped.lock().unwrap().push(cfg.id);
+        Ok(())
+    }
+}
+
+/// Trigger plugin failing permanently on every poll.
+#[derive(Clone, Default)]
+pub struct BrokenTrigger {
+    pub polls: Arc<Mutex<usize>>,
+}
+
+impl TriggerPlugin for BrokenTrigger {
+    fn get_type(&self) -> &str {
+        "broken"
+    }
+
+    fn pull_trigger(&self, _cfg: &TriggerConfiguration) -> Result<Vec<Trigger>, PluginError> {
+        *self.polls.lock().unwrap() += 1;
+        Err(PluginError::permanent("remote resource is gone"))
+    }
+}
+
+/// Trigger plugin failing with a transient error on every poll, with the given retry hint.
+#[derive(Clone, Default)]
+pub struct UnavailableTrigger {
+    pub polls: Arc<Mutex<usize>>,
+    pub retry_after: Option<time::Duration>,
+}
+
+impl TriggerPlugin for UnavailableTrigger {
+    fn get_type(&self) -> &str {
+        "unavailable"
+    }
+
+    fn pull_trigger(&self, _cfg: &TriggerConfiguration) -> Result<Vec<Trigger>, PluginError> {
+        *self.polls.lock().unwrap() += 1;
+        let error = PluginError::transient("service unavailable");
+        Err(match self.retry_after {
+            Some(delay) => error.with_retry_after(delay),
+            None => error,
+        })
+    }
+}
+
+/// Trigger plugin counting its polls, never firing.
+#[derive(Clone, Default)]
+pub struct CountingTrigger {
+    pub polls: Arc<Mutex<usize>>,
+}
+
+impl TriggerPlugin for CountingTrigger {
+    fn get_type(&self) -> &str {
+        "count"
+    }
+
+    fn pull_trigger(&self, _cfg: &TriggerConfiguration) -> Result<Vec<Trigger>, PluginError> {
+        *self.polls.lock().unwrap() += 1;
+        Ok(Vec::new())
+    }
+}
+
+/// Async trigger plugin whose polls never return.
+#[derive(Clone, Default)]
+pub struct HangingTrigger;
+
+#[async_trait]
+impl AsyncTriggerPlugin for HangingTrigger {
+    fn get_type(&self) -> &str {
+        "hang"
+    }
+
+    async fn pull_trigger(&self, _cfg: &TriggerConfiguration) -> Result<Vec<Trigger>, PluginError> {
+        std::future::pending().await
+    }
+}
+
+/// Trigger plugin panicking on every poll.
+#[derive(Clone, Default)]
+pub struct PanickingTrigger;
+
+impl TriggerPlugin for PanickingTrigger {
+    fn get_type(&self) -> &str {
+        "panic"
+    }
+
+    fn pull_trigger(&self, _cfg: &TriggerConfiguration) -> Result<Vec<Trigger>, PluginError> {
+        panic!("bing bong")
+    }
+}
@@ -1,3 +1,4 @@
 mod dir_watch;
 mod mock;
+mod scheduler;
 mod trigger_system;
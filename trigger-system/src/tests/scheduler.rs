@@ -0,0 +1,145 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+
+use protocol::{Schedule, TriggerConfiguration};
+
+use serde_json::json;
+
+use crate::scheduler::Scheduler;
+
+fn config(id: i64, schedule: Schedule) -> TriggerConfiguration {
+    TriggerConfiguration {
+        id,
+        rule: id.to_string(),
+        trigger_type: String::from("count"),
+        data: json!({}).into(),
+        schedule,
+    }
+}
+
+fn at(hour: u32, min: u32, sec: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2021, 3, 1, hour, min, sec).unwrap()
+}
+
+/// 9 AM on the given day of the week of 2021-02-28 (a Sunday) to 2021-03-06 (a Saturday).
+fn on(weekday: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2021, 2, 28, 9, 0, 0).unwrap() + Duration::days(weekday.into())
+}
+
+/// Days of the week, from 0 for Sunday, on which a cron expression is due at 9 AM.
+fn due_days(expression: &str) -> Vec<u32> {
+    let mut scheduler = Scheduler::new();
+    scheduler.sync(
+        &[config(1, Schedule::Cron(String::from(expression)))],
+        on(0) - Duration::days(1),
+    );
+
+    let mut days = Vec::new();
+    for weekday in 0..7 {
+        if scheduler.is_due(1, on(weekday)) {
+            days.push(weekday);
+            scheduler.polled(1, on(weekday));
+        }
+    }
+    days
+}
+
+#[test]
+fn validation() {
+    assert!(Scheduler::validate(&Schedule::Always).is_ok());
+    assert!(Scheduler::validate(&Schedule::Interval(1000)).is_ok());
+    assert!(Scheduler::validate(&Schedule::Interval(0)).is_err());
+    assert!(Scheduler::validate(&Schedule::Cron(String::from("*/5 * * * *"))).is_ok());
+    assert!(Scheduler::validate(&Schedule::Cron(String::from("30 */5 * * * *"))).is_ok());
+    assert!(Scheduler::validate(&Schedule::Cron(String::from("every minute"))).is_err());
+    assert!(Scheduler::validate(&Schedule::Cron(String::from("0 9 * * 8"))).is_err());
+    assert!(Scheduler::validate(&Schedule::Cron(String::from("0 9 * * 5-1"))).is_err());
+    assert!(Scheduler::validate(&Schedule::Cron(String::from("0 9 * * */0"))).is_err());
+}
+
+#[test]
+fn always_is_always_due() {
+    let mut scheduler = Scheduler::new();
+    scheduler.sync(&[config(1, Schedule::Always)], at(12, 0, 0));
+
+    assert!(scheduler.is_due(1, at(12, 0, 0)));
+    scheduler.polled(1, at(12, 0, 0));
+    assert!(scheduler.is_due(1, at(12, 0, 0)));
+}
+
+#[test]
+fn interval() {
+    let mut scheduler = Scheduler::new();
+    scheduler.sync(&[config(1, Schedule::Interval(30_000))], at(12, 0, 0));
+
+    // Due immediately, then once per interval.
+    assert!(scheduler.is_due(1, at(12, 0, 0)));
+    scheduler.polled(1, at(12, 0, 0));
+    assert!(!scheduler.is_due(1, at(12, 0, 29)));
+    assert!(scheduler.is_due(1, at(12, 0, 30)));
+}
+
+#[test]
+fn cron() {
+    let mut scheduler = Scheduler::new();
+    scheduler.sync(
+        &[config(1, Schedule::Cron(String::from("*/5 * * * *")))],
+        at(12, 1, 0),
+    );
+
+    // Due at the next match only.
+    assert!(!scheduler.is_due(1, at(12, 1, 0)));
+    assert!(!scheduler.is_due(1, at(12, 4, 59)));
+    assert!(scheduler.is_due(1, at(12, 5, 0)));
+
+    // A late poll isn't followed by catch-up polls.
+    scheduler.polled(1, at(12, 12, 0));
+    assert!(!scheduler.is_due(1, at(12, 14, 59)));
+    assert!(scheduler.is_due(1, at(12, 15, 0)));
+}
+
+#[test]
+fn sync_reschedules_changed_configs() {
+    let mut scheduler = Scheduler::new();
+    scheduler.sync(
+        &[
+            config(1, Schedule::Interval(60_000)),
+            config(2, Schedule::Interval(60_000)),
+        ],
+        at(12, 0, 0),
+    );
+    scheduler.polled(1, at(12, 0, 0));
+    scheduler.polled(2, at(12, 0, 0));
+
+    // Unchanged configs keep their schedule, modified ones are due again.
+    let now = at(12, 0, 0) + Duration::seconds(10);
+    scheduler.sync(
+        &[
+            config(1, Schedule::Interval(60_000)),
+            config(2, Schedule::Interval(5_000)),
+        ],
+        now,
+    );
+    assert!(!scheduler.is_due(1, now));
+    assert!(scheduler.is_due(2, now));
+
+    // Removed configs are forgotten.
+    scheduler.sync(&[config(2, Schedule::Interval(5_000))], now);
+    assert!(!scheduler.is_due(1, at(13, 0, 0)));
+}
+
+#[test]
+fn cron_days_of_week() {
+    // Days of the week follow the standard numbering, from 0 for Sunday.
+    assert_eq!(due_days("0 9 * * 1-5"), vec![1, 2, 3, 4, 5]);
+    assert_eq!(due_days("0 9 * * 0"), vec![0]);
+    assert_eq!(due_days("0 9 * * 7"), vec![0]);
+    assert_eq!(due_days("0 9 * * 5-7"), vec![0, 5, 6]);
+    assert_eq!(due_days("0 9 * * 0,6"), vec![0, 6]);
+    assert_eq!(due_days("0 9 * * */2"), vec![0, 2, 4, 6]);
+    assert_eq!(due_days("0 9 * * 1/3"), vec![1, 4]);
+    assert_eq!(due_days("0 9 * * *"), vec![0, 1, 2, 3, 4, 5, 6]);
+    assert_eq!(due_days("0 0 9 * * 3"), vec![3]);
+
+    // Named days are left to the cron crate.
+    assert_eq!(due_days("0 9 * * Mon-Fri"), vec![1, 2, 3, 4, 5]);
+}
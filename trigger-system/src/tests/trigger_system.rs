@@ -1,11 +1,12 @@
 use std::fs;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time;
 
-use plugin_host::PluginHost;
+use plugin_host::{AllowedPlugin, CallTimeouts, PluginHost, PluginHostConfig, PluginManifest};
 
-use protocol::{Trigger, TriggerConfiguration};
+use protocol::{Schedule, TriggerConfiguration};
 
 use serde_json::json;
 
@@ -36,14 +37,17 @@ fn in_memory_full_loop() {
         id: 42,
         rule: "42".into(),
         trigger_type: String::from("directory_watch"),
-        data: serde_json::to_string(&json!({ "directory": watched_dir_path })).unwrap(),
+        data: json!({ "directory": watched_dir_path }).into(),
+        schedule: Default::default(),
     };
 
     let config_loader = Box::from(mock::InMemoryConfigLoader::new(vec![trigger_config]));
     let queue_writer = Box::from(Arc::new(Mutex::new(mock::InMemoryQueueWriter::new())));
 
     let mut plugin_host = PluginHost::default();
-    plugin_host.add_in_memory_trigger_plugin(Box::new(DirectoryWatcher::default()));
+    plugin_host
+        .add_in_memory_trigger_plugin(Box::new(DirectoryWatcher::default()))
+        .unwrap();
 
     let cfg = TriggerSystemConfig {
         config_loader,
@@ -67,15 +71,457 @@ fn in_memory_full_loop() {
     let mut queue_guard = queue_writer.lock().unwrap();
     let queue_ref = &mut (*queue_guard);
     assert_eq!(queue_ref.queue.len(), 1);
+
+    let trigger = queue_ref.queue.first().unwrap();
+    assert_eq!(trigger.rule, "42");
+    assert_eq!(trigger.trigger_type, "directory_watch");
+    assert_eq!(
+        trigger.data,
+        json!({
+            "file_name": "some_file.txt"
+        })
+        .into()
+    );
+    assert_eq!(trigger.envelope.trigger_id, trigger.envelope.id);
+}
+
+#[test]
+fn queue_outage_keeps_triggers() {
+    let watched_directory = TempDir::new("shift3_ut_watch").unwrap();
+    let trigger_config = TriggerConfiguration {
+        id: 42,
+        rule: "42".into(),
+        trigger_type: String::from("directory_watch"),
+        data: json!({ "directory": watched_directory.path() }).into(),
+        schedule: Default::default(),
+    };
+
+    let config_loader = Box::from(mock::InMemoryConfigLoader::new(vec![trigger_config]));
+    let queue_writer = Arc::new(Mutex::new(mock::InMemoryQueueWriter::new()));
+    queue_writer.lock().unwrap().unavailable = 3;
+
+    let mut plugin_host = PluginHost::default();
+    plugin_host
+        .add_in_memory_trigger_plugin(Box::new(DirectoryWatcher::default()))
+        .unwrap();
+
+    let cfg = TriggerSystemConfig {
+        config_loader,
+        queue_writer: Box::from(queue_writer.clone()),
+        plugin_host: Arc::new(plugin_host),
+    };
+
+    let system = TriggerSystem::start(cfg);
+    thread::sleep(time::Duration::from_millis(100));
+
+    fs::write(watched_directory.path().join("a.txt"), "bing").unwrap();
+    fs::write(watched_directory.path().join("b.txt"), "bong").unwrap();
+    thread::sleep(time::Duration::from_millis(600));
+
+    system.terminate().unwrap();
+
+    // The triggers found while the queue was unavailable are pushed once it is back.
+    let queue_guard = queue_writer.lock().unwrap();
+    assert_eq!(queue_guard.unavailable, 0);
+    let mut file_names: Vec<String> = queue_guard
+        .queue
+        .iter()
+        .map(|t| String::from(t.data.as_value()["file_name"].as_str().unwrap()))
+        .collect();
+    file_names.sort();
+    assert_eq!(file_names, vec!["a.txt", "b.txt"]);
+}
+
+#[test]
+fn in_memory_invalid_config() {
+    let watched_directory = TempDir::new("shift3_ut_watch").unwrap();
+
+    let watched_dir_path = watched_directory.path().to_string_lossy().to_string();
+    let invalid_config = TriggerConfiguration {
+        id: 1,
+        rule: "1".into(),
+        trigger_type: String::from("directory_watch"),
+        data: json!({ "path": watched_dir_path }).into(),
+        schedule: Default::default(),
+    };
+    let unknown_config = TriggerConfiguration {
+        id: 2,
+        rule: "2".into(),
+        trigger_type: String::from("bing_bong"),
+        data: json!({}).into(),
+        schedule: Default::default(),
+    };
+    let valid_config = TriggerConfiguration {
+        id: 3,
+        rule: "3".into(),
+        trigger_type: String::from("directory_watch"),
+        data: json!({ "directory": watched_dir_path }).into(),
+        schedule: Default::default(),
+    };
+
+    let config_loader = Box::from(mock::InMemoryConfigLoader::new(vec![
+        invalid_config,
+        unknown_config,
+        valid_config,
+    ]));
+    let queue_writer = Box::from(Arc::new(Mutex::new(mock::InMemoryQueueWriter::new())));
+
+    let mut plugin_host = PluginHost::default();
+    plugin_host
+        .add_in_memory_trigger_plugin(Box::new(DirectoryWatcher::default()))
+        .unwrap();
+
+    let cfg = TriggerSystemConfig {
+        config_loader,
+        queue_writer: queue_writer.clone(),
+        plugin_host: Arc::new(plugin_host),
+    };
+
+    let system = TriggerSystem::start(cfg);
+
+    thread::sleep(time::Duration::from_millis(100)); // Give the system a chance to boot.
+
+    let file = watched_directory.path().join("some_file.txt");
+    fs::write(file, "bing bong").unwrap();
+
+    thread::sleep(time::Duration::from_millis(200)); // Give the system a chance to pickup on the change.
+
+    system.terminate().unwrap();
+
+    // The rejected configs must not prevent the valid one from firing.
+    let queue_guard = queue_writer.lock().unwrap();
+    assert_eq!(queue_guard.queue.len(), 1);
+    assert_eq!(queue_guard.queue[0].rule, "3");
+}
+
+#[test]
+fn in_memory_push_trigger() {
+    let pull_config = TriggerConfiguration {
+        id: 1,
+        rule: "1".into(),
+        trigger_type: String::from("directory_watch"),
+        data: json!({ "directory": "/" }).into(),
+        schedule: Default::default(),
+    };
+    let push_config = TriggerConfiguration {
+        id: 2,
+        rule: "2".into(),
+        trigger_type: String::from("push_once"),
+        data: json!({ "bing": "bong" }).into(),
+        schedule: Default::default(),
+    };
+
+    let config_loader = Box::from(mock::InMemoryConfigLoader::new(vec![
+        pull_config,
+        push_config,
+    ]));
+    let queue_writer = Box::from(Arc::new(Mutex::new(mock::InMemoryQueueWriter::new())));
+
+    let push_plugin = mock::PushOnce::default();
+    let mut plugin_host = PluginHost::default();
+    plugin_host
+        .add_in_memory_trigger_plugin(Box::new(DirectoryWatcher::default()))
+        .unwrap();
+    plugin_host
+        .add_in_memory_push_trigger_plugin(Box::new(push_plugin.clone()))
+        .unwrap();
+
+    let cfg = TriggerSystemConfig {
+        config_loader,
+        queue_writer: queue_writer.clone(),
+        plugin_host: Arc::new(plugin_host),
+    };
+
+    let system = TriggerSystem::start(cfg);
+    thread::sleep(time::Duration::from_millis(300)); // Give the system a chance to start the push config.
+    system.terminate().unwrap();
+
+    // Only the push config is started, and it is stopped with the system.
+    assert_eq!(*push_plugin.started.lock().unwrap(), vec![2]);
+    assert_eq!(*push_plugin.stopped.lock().unwrap(), vec![2]);
+
+    let queue_guard = queue_writer.lock().unwrap();
+    assert_eq!(queue_guard.queue.len(), 1);
+    assert_eq!(queue_guard.queue[0].rule, "2");
+    assert_eq!(queue_guard.queue[0].data, json!({ "bing": "bong" }).into());
+}
+
+#[test]
+fn permanent_failure_is_quarantined() {
+    let broken_config = TriggerConfiguration {
+        id: 1,
+        rule: "1".into(),
+        trigger_type: String::from("broken"),
+        data: json!({}).into(),
+        schedule: Default::default(),
+    };
+
+    let config_loader = Box::from(mock::InMemoryConfigLoader::new(vec![broken_config]));
+    let queue_writer = Box::from(Arc::new(Mutex::new(mock::InMemoryQueueWriter::new())));
+
+    let broken_plugin = mock::BrokenTrigger::default();
+    let mut plugin_host = PluginHost::default();
+    plugin_host
+        .add_in_memory_trigger_plugin(Box::new(broken_plugin.clone()))
+        .unwrap();
+
+    let cfg = TriggerSystemConfig {
+        config_loader,
+        queue_writer,
+        plugin_host: Arc::new(plugin_host),
+    };
+
+    let system = TriggerSystem::start(cfg);
+    thread::sleep(time::Duration::from_millis(300)); // Give the system a chance to poll a few times.
+    system.terminate().unwrap();
+
+    // The config is no longer polled after its first permanent failure.
+    assert_eq!(*broken_plugin.polls.lock().unwrap(), 1);
+}
+
+#[test]
+fn plugin_hot_reload() {
+    let plugin_directory = TempDir::new("shift3_ut_plugins").unwrap();
+    let watched_directory = TempDir::new("shift3_ut_watch").unwrap();
+
+    let trigger_config = TriggerConfiguration {
+        id: 1,
+        rule: "1".into(),
+        trigger_type: String::from("directory_watch"),
+        data: json!({ "directory": watched_directory.path() }).into(),
+        schedule: Default::default(),
+    };
+
+    let config_loader = Box::from(mock::InMemoryConfigLoader::new(vec![trigger_config]));
+    let queue_writer = Box::from(Arc::new(Mutex::new(mock::InMemoryQueueWriter::new())));
+
+    // The plugin is allowed, but not deployed yet.
+    let library = plugin_testkit::build_library("directory_watch");
+    let manifest =
+        PluginManifest::for_library(&library, "directory_watch", "0.1.0", &["directory_watch"])
+            .unwrap();
+    let plugin_host = Arc::new(
+        PluginHost::initialize(PluginHostConfig {
+            search_paths: vec![PathBuf::from(plugin_directory.path())],
+            allowlist: Some(vec![AllowedPlugin::pinned(&manifest)]),
+            ..Default::default()
+        })
+        .unwrap(),
+    );
+
+    let cfg = TriggerSystemConfig {
+        config_loader,
+        queue_writer: queue_writer.clone(),
+        plugin_host: plugin_host.clone(),
+    };
+
+    // The config is rejected, as no plugin handles it yet.
+    let system = TriggerSystem::start(cfg);
+    thread::sleep(time::Duration::from_millis(100));
+
+    // Deploy the plugin while the system is running.
+    let plugin_path = plugin_directory.path().join(library.file_name().unwrap());
+    fs::copy(&library, &plugin_path).unwrap();
+    manifest.write(&plugin_path).unwrap();
+    assert!(plugin_host.reload().unwrap());
+    thread::sleep(time::Duration::from_millis(200)); // Give the system a chance to pick up the plugin.
+
+    fs::write(watched_directory.path().join("some_file.txt"), "bing bong").unwrap();
+    thread::sleep(time::Duration::from_millis(200));
+
+    system.terminate().unwrap();
+
+    let queue_guard = queue_writer.lock().unwrap();
+    assert_eq!(queue_guard.queue.len(), 1);
     assert_eq!(
-        queue_ref.queue.first().unwrap(),
-        &Trigger {
-            rule: "42".into(),
-            trigger_type: String::from("directory_watch"),
-            data: serde_json::to_string(&json!({
-                "file_name": "some_file.txt"
-            }))
-            .unwrap()
-        }
+        queue_guard.queue[0].data,
+        json!({ "file_name": "some_file.txt" }).into()
     );
 }
+
+#[test]
+fn hung_poll_times_out() {
+    let config = |id: i64, trigger_type: &str| TriggerConfiguration {
+        id,
+        rule: id.to_string(),
+        trigger_type: String::from(trigger_type),
+        data: json!({}).into(),
+        schedule: Default::default(),
+    };
+    let config_loader = Box::from(mock::InMemoryConfigLoader::new(vec![
+        config(1, "hang"),
+        config(2, "count"),
+    ]));
+
+    let mut plugin_host = PluginHost::initialize(PluginHostConfig {
+        timeouts: CallTimeouts {
+            plugins: vec![(String::from("hang"), 100)].into_iter().collect(),
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .unwrap();
+    let counting = mock::CountingTrigger::default();
+    plugin_host
+        .add_in_memory_trigger_plugin(Box::new(counting.clone()))
+        .unwrap();
+    plugin_host
+        .add_in_memory_async_trigger_plugin(Box::new(mock::HangingTrigger))
+        .unwrap();
+    let plugin_host = Arc::new(plugin_host);
+
+    let system = TriggerSystem::start(TriggerSystemConfig {
+        config_loader,
+        queue_writer: Box::from(mock::Dummy::default()),
+        plugin_host: plugin_host.clone(),
+    });
+    thread::sleep(time::Duration::from_millis(600));
+    system.terminate().unwrap();
+
+    // The other configs are polled while the hung poll runs, and timed out polls are retried
+    // once their backoff is over.
+    assert!(*counting.polls.lock().unwrap() > 2);
+    assert_eq!(plugin_host.timed_out_calls()["hang"], 1);
+}
+
+#[test]
+fn transient_failure_backs_off() {
+    let config_loader = Box::from(mock::InMemoryConfigLoader::new(vec![
+        TriggerConfiguration {
+            id: 1,
+            rule: "1".into(),
+            trigger_type: String::from("unavailable"),
+            data: json!({}).into(),
+            schedule: Default::default(),
+        },
+    ]));
+
+    let unavailable = mock::UnavailableTrigger::default();
+    let mut plugin_host = PluginHost::default();
+    plugin_host
+        .add_in_memory_trigger_plugin(Box::new(unavailable.clone()))
+        .unwrap();
+
+    let system = TriggerSystem::start(TriggerSystemConfig {
+        config_loader,
+        queue_writer: Box::from(mock::Dummy::default()),
+        plugin_host: Arc::new(plugin_host),
+    });
+    thread::sleep(time::Duration::from_millis(500));
+    system.terminate().unwrap();
+
+    // Without a retry hint, the config isn't polled again before the default backoff is over.
+    assert_eq!(*unavailable.polls.lock().unwrap(), 1);
+}
+
+#[test]
+fn failed_polls_are_retried_regardless_of_schedule() {
+    let config_loader = Box::from(mock::InMemoryConfigLoader::new(vec![
+        TriggerConfiguration {
+            id: 1,
+            rule: "1".into(),
+            trigger_type: String::from("unavailable"),
+            data: json!({}).into(),
+            schedule: Schedule::Interval(60_000),
+        },
+    ]));
+
+    let unavailable = mock::UnavailableTrigger {
+        retry_after: Some(time::Duration::from_millis(50)),
+        ..Default::default()
+    };
+    let mut plugin_host = PluginHost::default();
+    plugin_host
+        .add_in_memory_trigger_plugin(Box::new(unavailable.clone()))
+        .unwrap();
+
+    let system = TriggerSystem::start(TriggerSystemConfig {
+        config_loader,
+        queue_writer: Box::from(mock::Dummy::default()),
+        plugin_host: Arc::new(plugin_host),
+    });
+    thread::sleep(time::Duration::from_millis(500));
+    system.terminate().unwrap();
+
+    // The config is polled again once the retry hint is over, long before its next scheduled
+    // poll.
+    assert!(*unavailable.polls.lock().unwrap() > 2);
+}
+
+#[test]
+fn panicking_poll_is_isolated() {
+    let config = |id: i64, trigger_type: &str| TriggerConfiguration {
+        id,
+        rule: id.to_string(),
+        trigger_type: String::from(trigger_type),
+        data: json!({}).into(),
+        schedule: Default::default(),
+    };
+    let config_loader = Box::from(mock::InMemoryConfigLoader::new(vec![
+        config(1, "panic"),
+        config(2, "count"),
+    ]));
+
+    let mut plugin_host = PluginHost::default();
+    let counting = mock::CountingTrigger::default();
+    plugin_host
+        .add_in_memory_trigger_plugin(Box::new(counting.clone()))
+        .unwrap();
+    plugin_host
+        .add_in_memory_trigger_plugin(Box::new(mock::PanickingTrigger))
+        .unwrap();
+    let plugin_host = Arc::new(plugin_host);
+
+    let system = TriggerSystem::start(TriggerSystemConfig {
+        config_loader,
+        queue_writer: Box::from(mock::Dummy::default()),
+        plugin_host: plugin_host.clone(),
+    });
+    thread::sleep(time::Duration::from_millis(500));
+    system.terminate().unwrap();
+
+    // The panicking config is quarantined, and the other configs keep being polled.
+    assert!(*counting.polls.lock().unwrap() > 2);
+    let unhealthy = plugin_host.unhealthy_plugins();
+    assert_eq!(unhealthy.len(), 1);
+    assert_eq!(unhealthy[0].plugin_type, "panic");
+    assert_eq!(unhealthy[0].panics, 1);
+}
+
+#[test]
+fn scheduled_configs_are_polled_when_due() {
+    let config_loader = Box::from(mock::InMemoryConfigLoader::new(vec![
+        TriggerConfiguration {
+            id: 1,
+            rule: String::from("1"),
+            trigger_type: String::from("count"),
+            data: json!({}).into(),
+            schedule: Schedule::Interval(60_000),
+        },
+        TriggerConfiguration {
+            id: 2,
+            rule: String::from("2"),
+            trigger_type: String::from("count"),
+            data: json!({}).into(),
+            schedule: Schedule::Cron(String::from("not a cron expression")),
+        },
+    ]));
+
+    let mut plugin_host = PluginHost::default();
+    let counting = mock::CountingTrigger::default();
+    plugin_host
+        .add_in_memory_trigger_plugin(Box::new(counting.clone()))
+        .unwrap();
+
+    let system = TriggerSystem::start(TriggerSystemConfig {
+        config_loader,
+        queue_writer: Box::from(mock::Dummy::default()),
+        plugin_host: Arc::new(plugin_host),
+    });
+    thread::sleep(time::Duration::from_millis(500));
+    system.terminate().unwrap();
+
+    // The config on an interval is polled once in the first minute, and the config with an
+    // invalid schedule is rejected.
+    assert_eq!(*counting.polls.lock().unwrap(), 1);
+}